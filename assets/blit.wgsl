@@ -0,0 +1,20 @@
+struct Output {
+    [[builtin(position)]] Position : vec4<f32>;
+};
+
+// One triangle that covers the whole target
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> Output {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: Output;
+    output.Position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return output;
+}
+
+[[group(0), binding(0)]]
+var frame: texture_2d<f32>;
+
+[[stage(fragment)]]
+fn fs_main(input: Output) -> [[location(0)]] vec4<f32> {
+    return textureLoad(frame, vec2<i32>(input.Position.xy), 0);
+}
@@ -1,10 +1,13 @@
-use std::time::{Duration, Instant};
-
-use PiCa::dx12_renderer::D3D12;
-use PiCa::error::Error;
-use PiCa::pica_mouse::{Button, Mouse};
-use PiCa::pica_window::{Window, WindowAttributes, ALT, CTR, SHIFT};
+#[cfg(windows)]
+use pica::dx12_renderer::D3D12;
+#[cfg(windows)]
+use pica::error::Error;
+#[cfg(windows)]
+use pica::pica_mouse::{Button, Mouse};
+#[cfg(windows)]
+use pica::pica_window::{Platform, Window, WindowAttributes, ALT, CTR, SHIFT};
 
+#[cfg(windows)]
 pub fn main() -> Result<(), Error> {
     let window_attributes = WindowAttributes::new()
         .with_title("Awesome PiCa Simulation")
@@ -15,10 +18,13 @@ pub fn main() -> Result<(), Error> {
 
     // Direct3D12 Initialization
     let mut d3d12 = D3D12::new()?;
-    d3d12.create_resources(window.win32.win32_window_handle, window.window_attributes.size)?;
+    let Platform::Win32(win32) = &window.platform else {
+        unreachable!("Direct3D12 needs a Win32 window")
+    };
+    d3d12.create_resources(win32.win32_window_handle, window.window_attributes.size)?;
 
 
-    let mut last_print_time: f32 = 0.0;
+    let mut last_print_time: f64 = 0.0;
     while window.pull() {
         match window.mouse {
             Mouse {
@@ -47,7 +53,7 @@ pub fn main() -> Result<(), Error> {
             }
             _ => {}
         }
-        if window.keys['A' as u8 as usize].pressed {
+        if window.keys[b'A' as usize].pressed {
             println!("TrIggErED!!");
         }
         if window.keys[CTR].pressed {
@@ -61,8 +67,8 @@ pub fn main() -> Result<(), Error> {
             println!("SHIFT is pressed!");
         }
         // Test that we can capture all text input with this kind of API, we do!
-        if window.text_length > 0 {
-            println!("{:?}", &window.text[..window.text_length]);
+        if !window.text.is_empty() {
+            println!("{:?}", window.text);
         }
 
         if window.time.seconds - last_print_time > 1.0 {
@@ -78,8 +84,13 @@ pub fn main() -> Result<(), Error> {
         }
 
         // Win32 DirectX12 Rendering!
-        window.push();
+        window.push()?;
         d3d12.render();
     }
     Ok(())
 }
+
+#[cfg(not(windows))]
+pub fn main() {
+    eprintln!("Direct3D12 is only available on Windows");
+}
@@ -1,38 +1,54 @@
+#[cfg(windows)]
 use std::ffi::c_void;
 
+#[cfg(windows)]
 use windows::Win32::System::Threading::{ConvertThreadToFiber, CreateFiber, SwitchToFiber};
 
+#[cfg(windows)]
 struct Fiber {
     pub main_fiber: *mut c_void,
     pub x: i32,
 }
 
+#[cfg(windows)]
 pub fn main() {
     let x = 0;
 
-    let main_fiber = unsafe { ConvertThreadToFiber(0 as *const c_void) };
+    let main_fiber = unsafe { ConvertThreadToFiber(std::ptr::null()) };
 
-    let mut fiber_data = Fiber{main_fiber, x};
+    // The worker fiber changes the data behind our back, so only touch it through a pointer.
+    let fiber_data: *mut Fiber = Box::into_raw(Box::new(Fiber { main_fiber, x }));
 
     assert!(!main_fiber.is_null());
     
     let work_fiber = unsafe { CreateFiber(
                     0,
                     Some(worker_fiber_proc),
-                    &mut fiber_data as *mut Fiber as *const c_void ,
+                    fiber_data as *const c_void ,
                 )};
     
-    while fiber_data.x <= 10 {
-        println!("fiber_data: {}", fiber_data.x);
+    loop {
+        let x = unsafe { (*fiber_data).x };
+        if x > 10 {
+            break;
+        }
+        println!("fiber_data: {}", x);
         unsafe {SwitchToFiber(work_fiber as *const c_void)}
     }
+    drop(unsafe { Box::from_raw(fiber_data) });
 }
 
+#[cfg(windows)]
 extern "system" fn worker_fiber_proc(data: *mut c_void) {
     let fiber_data = data.cast::<Fiber>();
     let fiber_data: &mut Fiber = unsafe{fiber_data.as_mut().unwrap()};
     loop {
-        fiber_data.x = fiber_data.x + 1;
+        fiber_data.x += 1;
         unsafe {SwitchToFiber(fiber_data.main_fiber);}
     }
 }
+
+#[cfg(not(windows))]
+pub fn main() {
+    eprintln!("Fibers are only available on Windows");
+}
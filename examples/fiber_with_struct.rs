@@ -1,7 +1,10 @@
+#[cfg(windows)]
 use std::ffi::c_void;
 
+#[cfg(windows)]
 use windows::Win32::System::Threading::{ConvertThreadToFiber, CreateFiber, SwitchToFiber};
 
+#[cfg(windows)]
 #[derive(Debug)]
 struct InnerStruct {
     _title: String,
@@ -10,28 +13,30 @@ struct InnerStruct {
     work_fiber: *mut c_void,
 }
 
+#[cfg(windows)]
 #[derive(Debug)]
 struct Fiber {
     inner: InnerStruct,
     x: i32,
 }
 
+#[cfg(windows)]
 impl Fiber {
     pub fn new() -> Result<*mut Self, ()> {
         let x = 0;
-        let main_fiber = unsafe { ConvertThreadToFiber(0 as *const c_void) };
+        let main_fiber = unsafe { ConvertThreadToFiber(std::ptr::null()) };
         assert!(!main_fiber.is_null());
         let fiber_data = Self {
             inner: InnerStruct {
                 _title: "TEST".to_owned(),
                 _position: (100, 100),
                 main_fiber,
-                work_fiber: 0 as *mut c_void,
+                work_fiber: std::ptr::null_mut(),
             },
             x,
         };
 
-        let mut fiber_data = Box::into_raw(Box::new(fiber_data));
+        let fiber_data = Box::into_raw(Box::new(fiber_data));
         let work_fiber = unsafe {
             CreateFiber(
                 0,
@@ -53,7 +58,8 @@ impl Fiber {
     }
 }
 
-pub fn main() -> Result<(), ()> {
+#[cfg(windows)]
+fn main() -> Result<(), ()> {
     let mut fiber_data = unsafe { Box::from_raw(Fiber::new()?) };
     
         while fiber_data.x < 10 {
@@ -64,13 +70,19 @@ pub fn main() -> Result<(), ()> {
     Ok(())
 }
 
+#[cfg(windows)]
 extern "system" fn worker_fiber_proc(data: *mut c_void) {
     let fiber_data = data.cast::<Fiber>();
     let fiber_data: &mut Fiber = unsafe { fiber_data.as_mut().unwrap() };
     loop {
-        fiber_data.x = fiber_data.x + 1;
+        fiber_data.x += 1;
         unsafe {
             SwitchToFiber(fiber_data.inner.main_fiber);
         }
     }
 }
+
+#[cfg(not(windows))]
+pub fn main() {
+    eprintln!("Fibers are only available on Windows");
+}
@@ -0,0 +1,50 @@
+use pica::error::Error;
+use pica::pica_keyboard::Key;
+use pica::pica_mouse::MouseButton;
+use pica::pica_window::headless::ScriptedInput;
+use pica::pica_window::{Window, WindowAttributes};
+
+pub fn main() -> Result<(), Error> {
+    let window_attributes = WindowAttributes::new()
+        .with_title("Headless pica Simulation")
+        .with_size(800, 600);
+
+    let mut window = Window::new_headless(window_attributes)?;
+
+    // Script three frames of input, followed by a close request.
+    window.headless().unwrap().queue_all([
+        ScriptedInput::KeyDown(Key::W),
+        ScriptedInput::ButtonDown(MouseButton::Left),
+        ScriptedInput::MouseMove(10, -5),
+        ScriptedInput::NextFrame,
+        ScriptedInput::KeyDown(Key::Space),
+        // A held down key repeats
+        ScriptedInput::KeyDown(Key::W),
+        ScriptedInput::Text("pica π 🦀".to_owned()),
+        ScriptedInput::Wheel(0.0, 2.0),
+        ScriptedInput::NextFrame,
+        ScriptedInput::KeyUp(Key::W),
+        ScriptedInput::KeyUp(Key::Space),
+        ScriptedInput::ButtonUp(MouseButton::Left),
+        ScriptedInput::NextFrame,
+        ScriptedInput::Close,
+    ]);
+
+    while window.pull() {
+        println!(
+            "t: {:.4}s, W: {:?}, space pressed: {}, left: {:?}, delta: {:?}, wheel: {}, text: {:?}",
+            window.time.seconds,
+            window.key(Key::W),
+            window.key(Key::Space).pressed,
+            window.mouse.left_button,
+            window.mouse.delta_position,
+            window.mouse.delta_wheel,
+            window.text,
+        );
+        for event in &window.events {
+            println!("    {:?}", event);
+        }
+    }
+
+    Ok(())
+}
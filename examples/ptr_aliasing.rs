@@ -1,5 +1,3 @@
-use std::{ptr, alloc::{dealloc, Layout}};
-
 // This program has UB as indicated by: cargo miri run --example ptr_aliasing
 // The problems with ptr aliasing ("rebowering", or "stacked borrows") are discussed
 // https://www.ralfj.de/blog/2018/08/07/stacked-borrows.html
@@ -9,10 +7,7 @@ use std::{ptr, alloc::{dealloc, Layout}};
 pub struct Test{pub eee: *mut i32}
 
 pub fn ub() {
-    let y: *mut i32 = {
-        let x = Box::into_raw(Box::new(42));
-        x
-    };
+    let y: *mut i32 = Box::into_raw(Box::new(42));
 
     let test = Test { eee: y };
 
@@ -29,10 +24,7 @@ pub fn ub() {
 }
 
 pub fn db() {
-    let y: *mut i32 = {
-        let x = Box::into_raw(Box::new(42));
-        x
-    };
+    let y: *mut i32 = Box::into_raw(Box::new(42));
 
     let test = Test { eee: y };
 
@@ -47,12 +39,12 @@ pub fn db() {
     println!("{}", unsafe{*test.eee});
 
     // Converting the raw pointer back into a Box with Box::from_raw for automatic cleanup:
-    unsafe { Box::from_raw(y) };
+    drop(unsafe { Box::from_raw(y) });
 
     // Or, Manual cleanup by explicitly running the destructor and deallocating the memory:
     // unsafe {
-    //     ptr::drop_in_place(y);
-    //     dealloc(y as *mut u8, Layout::new::<i32>());
+    //     std::ptr::drop_in_place(y);
+    //     std::alloc::dealloc(y as *mut u8, std::alloc::Layout::new::<i32>());
     // }
 }
 
@@ -26,7 +26,7 @@ pub fn main() -> Result<(), Error> {
     let adapter_info = adapter.get_info();
     println!("device: {:?}\nbackend: {:?}", adapter_info.name, adapter_info.backend );
 
-    let (device, queue) = pollster::block_on(adapter
+    let (device, _queue) = pollster::block_on(adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: Some("Device Features"),
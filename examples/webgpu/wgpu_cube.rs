@@ -1,11 +1,12 @@
-use glam::{Mat4, Quat, Vec3};
+use glam::{Quat, Vec3};
 use pica::error::Error;
 use pica::math;
+use pica::pica_keyboard::Key;
+use pica::pica_mouse::CursorMode;
+use pica::pica_simulation::Simulation;
 use pica::pica_window::{Window, WindowAttributes};
-use pica::utils;
 use pica::wgpu_renderer::Vertex;
-use pica::wgpu_renderer::{camera::Camera, Instance, InstanceRaw, WGPURenderer};
-use std::borrow::Cow;
+use pica::wgpu_renderer::{Instance, InstanceRaw, WGPURenderer};
 
 pub fn cube_positions() -> Vec<[i8; 3]> {
     [
@@ -103,6 +104,8 @@ pub fn main() -> Result<(), Error> {
         indices: Some(indices),
         camera_position: Vec3::new(0.5, 0.5, 0.5),
         instances: Some(instances),
+        force_fallback_adapter: false,
+        vsync: true,
     };
 
     let window_attributes = WindowAttributes::new()
@@ -112,18 +115,34 @@ pub fn main() -> Result<(), Error> {
 
     let mut window = Window::new_with_attributes(window_attributes)?;
 
-    let mut wgpu_renderer =
-        pollster::block_on(WGPURenderer::new_with_attributes(window.as_ref(), render_attributes));
+    let mut wgpu_renderer = pollster::block_on(WGPURenderer::new_with_attributes(
+        window.as_ref(),
+        render_attributes,
+    ));
 
     const ANIMATION_SPEED: f32 = 1.0;
-    const ROTATION_SPEED: f32 = 0.5 * std::f32::consts::PI / 60.0;
+    const ROTATION_SPEED: f32 = 0.5 * std::f32::consts::PI;
+
+    // The animation runs at a fixed rate, rendering blends the two most recent steps.
+    let mut simulation = Simulation::new(60);
+    let (mut previous_angle, mut angle) = (0.0f32, 0.0f32);
 
     // pica window rendering loop
     while window.pull() {
-        let dt = ANIMATION_SPEED * window.time.seconds;
+        wgpu_renderer.resize_to_window(&window);
+
+        let mut rotation = 0.0;
+        simulation.advance(window.time.simulation.delta_nanoseconds, |dt| {
+            previous_angle = angle;
+            angle += ANIMATION_SPEED * dt as f32;
+            rotation += ROTATION_SPEED * dt as f32;
+        });
+        let alpha = simulation.alpha() as f32;
+        let interpolated_angle = previous_angle + (angle - previous_angle) * alpha;
+
         let model_mat = math::create_transforms(
             [0.0, 0.0, 0.0],
-            [dt.sin(), 0.0, dt.cos()],
+            [interpolated_angle.sin(), 0.0, interpolated_angle.cos()],
             [0.15, 0.15, 0.15],
         );
         let mvp_mat = model_mat;
@@ -131,7 +150,7 @@ pub fn main() -> Result<(), Error> {
         wgpu_renderer.write_uniform(mvp_ref);
 
         for instance in wgpu_renderer.instances.as_mut().unwrap() {
-            let amount = Quat::from_rotation_y(ROTATION_SPEED);
+            let amount = Quat::from_rotation_y(rotation);
             let current = instance.rotation;
             instance.rotation = amount.mul_quat(current);
         }
@@ -143,7 +162,7 @@ pub fn main() -> Result<(), Error> {
             .map(Instance::to_raw)
             .collect::<Vec<InstanceRaw>>();
 
-        wgpu_renderer.write_instances(instance_data);
+        wgpu_renderer.write_instances(instance_data)?;
 
         wgpu_renderer.camera.update_camera(window.as_mut());
         wgpu_renderer
@@ -153,6 +172,34 @@ pub fn main() -> Result<(), Error> {
         wgpu_renderer.write_camera(&[wgpu_renderer.camera_uniform]);
 
         wgpu_renderer.render().unwrap();
+
+        // Press P to save a screenshot
+        if window.key(Key::P).pressed {
+            wgpu_renderer.save_screenshot("wgpu_cube.png")?;
+        }
+
+        // Enter pauses the animation, period steps it while paused, minus and equals slow it down
+        // and speed it up. The camera keeps moving on the wall clock.
+        if window.key(Key::Enter).pressed {
+            window.time.simulation.toggle_pause();
+        }
+        if window.key(Key::Period).pressed {
+            window.time.simulation.step(simulation.step_nanoseconds);
+        }
+        if window.key(Key::Minus).pressed {
+            window.time.simulation.scale /= 2.0;
+        }
+        if window.key(Key::Equals).pressed {
+            window.time.simulation.scale *= 2.0;
+        }
+
+        // Press L to lock the cursor and look around without holding a button
+        if window.key(Key::L).pressed {
+            window.set_cursor_mode(match window.cursor_mode() {
+                CursorMode::Normal => CursorMode::Locked,
+                CursorMode::Locked => CursorMode::Normal,
+            });
+        }
     }
 
     Ok(())
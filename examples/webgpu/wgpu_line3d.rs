@@ -1,42 +1,28 @@
-use std::borrow::Cow;
-use PiCa::error::Error;
-use PiCa::pica_window::{Window, WindowAttributes};
-use PiCa::wgpu_renderer::WGPURenderer;
-use PiCa::Vertex;
-
-fn create_vertices() -> [Vertex; 1200]{
-    let mut vertices = [Vertex {
-        position: [0.0, 0.0, 0.0, 1.0],
-        color: [0.0, 0.0, 0.0, 1.0],
-    }; 1200];
-    for i in 0..1200 {
-        let t = i as f32 / 150.0;
-        let x = (-t).exp() * (30.0 * t).sin();
-        let z = (-t).exp() * (30.0 * t).cos();
-        let y = 2.0 * (-t).sin() - 1.0;
-        vertices[i] = Vertex {
-            position: [x, y, z, 1.0],
-            color: [0.5, 0.5, 0.8, 1.0],
-        };
-    }
-    vertices
+use glam::Vec3;
+use pica::error::Error;
+use pica::pica_window::{Window, WindowAttributes};
+use pica::wgpu_renderer::{Indices, MeshAttributes, RendererAttributes, Vertex, WGPURenderer};
+
+const VERTICES: u32 = 1200;
+
+fn create_vertices() -> Vec<Vertex> {
+    (0..VERTICES)
+        .map(|i| {
+            let t = i as f32 / 150.0;
+            let x = (-t).exp() * (30.0 * t).sin();
+            let z = (-t).exp() * (30.0 * t).cos();
+            let y = 2.0 * (-t).sin() - 1.0;
+            Vertex {
+                position: [x, y, z, 1.0],
+                color: [0.5, 0.5, 0.8, 1.0],
+            }
+        })
+        .collect()
 }
 
 pub fn main() -> Result<(), Error> {
-    
     let vertices = create_vertices();
-    
-    let inputs = PiCa::wgpu_renderer::Inputs {
-        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
-            "../../../assets/line3d.wgsl"
-        ))),
-        topology: wgpu::PrimitiveTopology::LineStrip,
-        strip_index_format: Some(wgpu::IndexFormat::Uint32),
-        vertices: Some(vertices.to_vec()),
-        indices: None,
-        camera_position: (1.5, 1.0, 3.0),
-    };
-    
+
     let window_attributes = WindowAttributes::new()
         .with_title("Line3D")
         .with_position(50, 50)
@@ -44,17 +30,44 @@ pub fn main() -> Result<(), Error> {
 
     let mut window = Window::new_with_attributes(window_attributes)?;
 
-    let mut wgpu_renderer = pollster::block_on(WGPURenderer::wgpu_init(window.as_ref(), inputs));
+    let inputs = RendererAttributes {
+        camera_position: Vec3::new(1.5, 1.0, 3.0),
+        vsync: true,
+        ..Default::default()
+    };
+    let mut wgpu_renderer =
+        pollster::block_on(WGPURenderer::new_with_attributes(window.as_ref(), inputs));
 
-    // PiCa window rendering loop
+    // The spiral is drawn without indices, growing by a vertex every frame
+    let spiral = wgpu_renderer.add_mesh(MeshAttributes {
+        topology: wgpu::PrimitiveTopology::LineStrip,
+        vertices: vertices.clone().into(),
+        range: Some(0..0),
+        ..Default::default()
+    })?;
+    // Every tenth vertex of the spiral as a point, picked by 32 bit indices
+    let points = vertices
+        .iter()
+        .map(|vertex| Vertex {
+            color: [1.0, 1.0, 0.0, 1.0],
+            ..*vertex
+        })
+        .collect::<Vec<_>>();
+    wgpu_renderer.add_mesh(MeshAttributes {
+        topology: wgpu::PrimitiveTopology::PointList,
+        vertices: points.into(),
+        indices: Some(Indices::U32((0..VERTICES).step_by(10).collect())),
+        ..Default::default()
+    })?;
+
+    // pica window rendering loop
     while window.pull() {
-        
-        
-        
-        window.push();
+        wgpu_renderer.resize_to_window(&window);
+
+        let end = (wgpu_renderer.meshes[spiral.0].range().end + 1).min(VERTICES);
+        wgpu_renderer.set_range(spiral, 0..end)?;
 
-        // TODO: create a PiCa renderer trait, so this can be hidden behind the 'push()' function call
-        wgpu_renderer.render();
+        wgpu_renderer.render().unwrap();
     }
 
     Ok(())
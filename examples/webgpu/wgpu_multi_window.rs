@@ -0,0 +1,117 @@
+use glam::{Quat, Vec3};
+use pica::error::Error;
+use pica::pica_keyboard::Key;
+use pica::pica_window::{Window, WindowAttributes};
+use pica::wgpu_renderer::{Gpu, Instance, RendererAttributes, Vertex, WGPURenderer};
+
+fn cube_vertices() -> Vec<Vertex> {
+    let positions = [
+        [-1, -1, 1],
+        [1, -1, 1],
+        [1, 1, 1],
+        [-1, 1, 1],
+        [-1, -1, -1],
+        [1, -1, -1],
+        [1, 1, -1],
+        [-1, 1, -1],
+    ];
+    let colors = [
+        [0, 0, 1],
+        [1, 0, 1],
+        [1, 1, 1],
+        [0, 1, 1],
+        [0, 0, 0],
+        [1, 0, 0],
+        [1, 1, 0],
+        [0, 1, 0],
+    ];
+    positions
+        .iter()
+        .zip(colors.iter())
+        .map(|(p, c)| Vertex::vertex(*p, *c))
+        .collect()
+}
+
+fn cube_indices() -> Vec<u16> {
+    vec![
+        0, 1, 2, 2, 3, 0, // front
+        1, 5, 6, 6, 2, 1, // right
+        4, 7, 6, 6, 5, 4, // back
+        0, 3, 7, 7, 4, 0, // left
+        3, 2, 6, 6, 7, 3, // top
+        0, 4, 5, 5, 1, 0, // bottom
+    ]
+}
+
+fn cube_attributes(camera_position: Vec3) -> RendererAttributes {
+    RendererAttributes {
+        source: include_str!("../../assets/cube_face_color.wgsl"),
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        vertices: Some(cube_vertices()),
+        indices: Some(cube_indices()),
+        camera_position,
+        instances: Some(vec![Instance {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+        }]),
+        vsync: true,
+        ..Default::default()
+    }
+}
+
+// Two windows, a 3D view and a second view of the same cube, rendered from one shared device.
+pub fn main() -> Result<(), Error> {
+    let mut view_window = Window::new_with_attributes(
+        WindowAttributes::new()
+            .with_title("3D View")
+            .with_position(50, 50)
+            .with_size(800, 600),
+    )?;
+    let mut side_window = Window::new_with_attributes(
+        WindowAttributes::new()
+            .with_title("Side View")
+            .with_position(900, 50)
+            .with_size(400, 300),
+    )?;
+
+    let gpu = pollster::block_on(Gpu::new(Some(view_window.as_ref()), false))
+        .expect("Failed to find an appropriate adapter");
+    let mut view_renderer = WGPURenderer::new_with_gpu(
+        &gpu,
+        view_window.as_ref(),
+        cube_attributes(Vec3::new(0.0, 2.0, 8.0)),
+    )?;
+    let mut side_renderer = WGPURenderer::new_with_gpu(
+        &gpu,
+        side_window.as_ref(),
+        cube_attributes(Vec3::new(8.0, 0.0, 0.0)),
+    )?;
+    side_renderer.clear_color = wgpu::Color {
+        r: 0.1,
+        g: 0.1,
+        b: 0.1,
+        a: 1.0,
+    };
+
+    // Closing either window ends the example
+    while view_window.pull() & side_window.pull() {
+        if view_window.key(Key::Escape).pressed || side_window.key(Key::Escape).pressed {
+            break;
+        }
+
+        for (window, renderer) in [
+            (&mut view_window, &mut view_renderer),
+            (&mut side_window, &mut side_renderer),
+        ] {
+            renderer.resize_to_window(window);
+            renderer.camera.update_camera(window.as_mut());
+            renderer
+                .camera_uniform
+                .update_view_proj(&renderer.camera, &renderer.projection);
+            renderer.write_camera(&[renderer.camera_uniform]);
+            renderer.render().unwrap();
+        }
+    }
+
+    Ok(())
+}
@@ -1,10 +1,9 @@
-use std::borrow::Cow;
+use glam::Vec3;
 use pica::error::Error;
 use pica::pica_window::{Window, WindowAttributes};
-use pica::wgpu_renderer::{RendererAttributes, WGPURenderer};
+use pica::wgpu_renderer::{RendererAttributes, Vertex, WGPURenderer};
 
 pub fn main() -> Result<(), Error> {
-
     // Create a pica window
     let window_attributes = WindowAttributes::new()
         .with_title("Awesome pica Simulation")
@@ -18,25 +17,31 @@ pub fn main() -> Result<(), Error> {
 
     // Define the inputs for the WGPURenderer
     let inputs = RendererAttributes {
-        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../assets/shader.wgsl"))),
-        topology: wgpu::PrimitiveTopology::TriangleList,
-        strip_index_format: None,
-        vertices: None,
-        indices: None,
-        camera_position: (3.0, 1.5, 2.0),
+        camera_position: Vec3::new(0.0, 0.0, 3.0),
+        vsync: true,
+        ..Default::default()
     };
 
-    // WGPURenderer initialization
-    let mut wgpu_renderer = pollster::block_on(WGPURenderer::wgpu_init(window.as_ref(), inputs));
+    // WGPURenderer initialization, the window presents its frames from now on
+    let wgpu_renderer =
+        pollster::block_on(WGPURenderer::new_with_attributes(window.as_ref(), inputs));
+    window.set_renderer(Box::new(wgpu_renderer));
+
+    let vertices = [
+        Vertex::vertex([-1, -1, 0], [1, 0, 0]),
+        Vertex::vertex([1, -1, 0], [0, 1, 0]),
+        Vertex::vertex([0, 1, 0], [0, 0, 1]),
+    ];
+    let triangle = window.renderer().unwrap().create_mesh(&vertices, None)?;
 
     // pica window rendering loop
     while window.pull() {
-        window.push();
-        
+        window.renderer().unwrap().draw(triangle);
+
         // Paint to the window surface
-        wgpu_renderer.render();
+        window.push()?;
     }
 
     // All was well
     Ok(())
-}
\ No newline at end of file
+}
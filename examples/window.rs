@@ -1,17 +1,19 @@
-use std::time::{Duration, Instant};
-
 use pica::error::Error;
-use pica::pica_mouse::{Button, Mouse};
-use pica::pica_window::{Window, WindowAttributes, CTR, ALT, SHIFT, };
+use pica::pica_gamepad::GamepadButton;
+use pica::pica_keyboard::Key;
+use pica::pica_mouse::{Button, CursorIcon, Mouse};
+use pica::pica_window::{Window, WindowAttributes, WindowMode};
 
 pub fn main() -> Result<(), Error> {
     let window_attributes = WindowAttributes::new()
         .with_title("Awesome pica Simulation")
         .with_position(50, 50)
-        .with_size(800, 600);
+        .with_size(800, 600)
+        .with_min_size(320, 240)
+        .with_frame_rate_limit(60);
 
     let mut window = Window::new_with_attributes(window_attributes)?;
-    let mut last_print_time: f32 = 0.0;
+    let mut last_print_time: f64 = 0.0;
     while window.pull() {
         match window.mouse {
             Mouse {
@@ -40,35 +42,88 @@ pub fn main() -> Result<(), Error> {
             }
             _ => {}
         }
-        if window.keys['A' as u8 as usize].pressed {
+        if window.key(Key::A).pressed {
             println!("TrIggErED!!");
         }
-        if window.keys[CTR].pressed {
+        if window.key(Key::F11).pressed {
+            let mode = match window.mode() {
+                WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+                _ => WindowMode::Windowed,
+            };
+            window.set_mode(mode);
+        }
+        // Record input with F5 until F6, and replay it with F7.
+        if window.key(Key::F5).pressed && !window.is_replaying() {
+            window.start_input_recording("window.rec").unwrap();
+            println!("Recording input");
+        }
+        if window.key(Key::F6).pressed && window.is_recording_input() {
+            window.stop_input_recording().unwrap();
+            println!("Recorded input to window.rec");
+        }
+        if window.key(Key::F7).pressed && !window.is_recording_input() {
+            match window.start_replay("window.rec") {
+                Ok(()) => println!("Replaying window.rec"),
+                Err(error) => println!("{:?}", error),
+            }
+        }
+        if window.key(Key::H).pressed {
+            let icon = match window.cursor_icon() {
+                CursorIcon::Crosshair => CursorIcon::Hand,
+                _ => CursorIcon::Crosshair,
+            };
+            window.set_cursor_icon(icon);
+        }
+        if window.key(Key::Control).pressed {
             println!("Ctrl is pressed!");
         }
 
-        if window.keys[ALT].pressed {
+        if window.key(Key::Alt).pressed {
             println!("ALT is pressed!");
         }
-        if window.keys[SHIFT].pressed {
+        if window.key(Key::Shift).pressed {
             println!("SHIFT is pressed!");
         }
+        for (slot, gamepad) in window.gamepads.connected() {
+            if gamepad.button(GamepadButton::South).pressed {
+                println!("Gamepad {} ({}): SOUTH PRESSED", slot, gamepad.name);
+            }
+            if gamepad.left_stick() != (0.0, 0.0) {
+                println!("Gamepad {}: left stick {:?}", slot, gamepad.left_stick());
+            }
+        }
+
+        if window.key(Key::Control).down && window.key(Key::V).pressed {
+            println!("Clipboard: {:?}", window.clipboard_text());
+        }
+        for path in &window.dropped_files {
+            println!("Dropped {}", path.display());
+        }
+
         // Test that we can capture all text input with this kind of API, we do!
-        if window.text_length > 0 {
-            println!("{:?}", &window.text[..window.text_length]);
+        if !window.text.is_empty() {
+            println!("{:?}", window.text);
         }
 
-        
         if window.time.seconds - last_print_time > 1.0 {
             println!(
-                "Position: {:?}, Size: {:?}, Mouse: {:?}, delta_us: {:?}, ms: {:?}, tps: {:?}",
+                "Position: {:?}, Size: {:?}, Scale: {}, Mouse: {:?}, delta_us: {:?}, ms: {:?}, tps: {:?}",
                 window.window_attributes.position,
                 window.window_attributes.size,
+                window.scale_factor(),
                 window.mouse.position,
                 window.time.delta_microseconds,
                 window.time.milliseconds,
                 window.time.ticks_per_second,
-
+            );
+            let stats = &window.time.frame_stats;
+            println!(
+                "Frame time us min: {:?}, avg: {:?}, p99: {:?}, max: {:?}, fps: {:.1}",
+                stats.min().map(|ns| ns / 1000),
+                stats.average().map(|ns| ns / 1000),
+                stats.percentile(99.0).map(|ns| ns / 1000),
+                stats.max().map(|ns| ns / 1000),
+                stats.frames_per_second().unwrap_or_default(),
             );
             last_print_time = window.time.seconds;
         }
@@ -1,4 +1,4 @@
-use std::{env, ffi::c_void, path::PathBuf};
+use std::env;
 use windows::{
     core::{Interface, PCSTR, PCWSTR},
     Win32::{
@@ -37,8 +37,8 @@ use windows::{
                 Common::{
                     DXGI_FORMAT_R32G32B32_FLOAT, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_SAMPLE_DESC,
                 },
-                CreateDXGIFactory2, IDXGIAdapter1, IDXGIFactory4, IDXGISwapChain3, IDXGISwapChain4,
-                DXGI_ADAPTER_FLAG, DXGI_ADAPTER_FLAG_NONE, DXGI_ADAPTER_FLAG_SOFTWARE,
+                CreateDXGIFactory2, IDXGIAdapter1, IDXGIFactory4, IDXGISwapChain3,
+                DXGI_ADAPTER_FLAG_NONE, DXGI_ADAPTER_FLAG_SOFTWARE,
                 DXGI_CREATE_FACTORY_DEBUG, DXGI_MWA_NO_ALT_ENTER, DXGI_SWAP_CHAIN_DESC1,
                 DXGI_SWAP_EFFECT_FLIP_DISCARD, DXGI_USAGE_RENDER_TARGET_OUTPUT,
             },
@@ -261,7 +261,7 @@ impl Resources {
                     renderer.device.CreateRenderTargetView(
                         &render_target,
                         std::ptr::null_mut(),
-                        &D3D12_CPU_DESCRIPTOR_HANDLE {
+                        D3D12_CPU_DESCRIPTOR_HANDLE {
                             ptr: rtv_handle.ptr + i * rtv_descriptor_size,
                         },
                     )
@@ -402,7 +402,7 @@ impl Resources {
         let exe_path = std::env::current_exe().ok().unwrap();
         let asset_path = exe_path.parent().unwrap();
         let mut shaders_hlsl_path = asset_path.join("shaders.hlsl");
-        if let Some(hlsl_file_name) = env::args().skip(1).next() {
+        if let Some(hlsl_file_name) = env::args().nth(1) {
             let potential_hlsl_path = asset_path.join(&hlsl_file_name);
             if potential_hlsl_path.is_file() {
                 println!("Shader file: {}", hlsl_file_name);
@@ -422,8 +422,8 @@ impl Resources {
                 PCWSTR(shaders_hlsl.to_wide()),
                 std::ptr::null_mut(),
                 None,
-                PCSTR(b"VSMain\0".as_ptr() as *mut u8),
-                PCSTR(b"vs_5_0\0".as_ptr() as *mut u8),
+                PCSTR(c"VSMain".as_ptr().cast()),
+                PCSTR(c"vs_5_0".as_ptr().cast()),
                 compile_flags,
                 0,
                 &mut vertex_shader,
@@ -443,8 +443,8 @@ impl Resources {
                 PCWSTR(shaders_hlsl.to_wide()),
                 std::ptr::null_mut(),
                 None,
-                PCSTR(b"PSMain\0".as_ptr() as *mut u8),
-                PCSTR(b"ps_5_0\0".as_ptr() as *mut u8),
+                PCSTR(c"PSMain".as_ptr().cast()),
+                PCSTR(c"ps_5_0".as_ptr().cast()),
                 compile_flags,
                 0,
                 &mut pixel_shader,
@@ -458,7 +458,7 @@ impl Resources {
 
         let mut input_element_descs: [D3D12_INPUT_ELEMENT_DESC; 2] = [
             D3D12_INPUT_ELEMENT_DESC {
-                SemanticName: PCSTR(b"POSITION\0".as_ptr() as *mut u8),
+                SemanticName: PCSTR(c"POSITION".as_ptr().cast()),
                 SemanticIndex: 0,
                 Format: DXGI_FORMAT_R32G32B32_FLOAT,
                 InputSlot: 0,
@@ -467,7 +467,7 @@ impl Resources {
                 InstanceDataStepRate: 0,
             },
             D3D12_INPUT_ELEMENT_DESC {
-                SemanticName: PCSTR(b"COLOR\0".as_ptr() as *mut u8),
+                SemanticName: PCSTR(c"COLOR".as_ptr().cast()),
                 SemanticIndex: 0,
                 Format: DXGI_FORMAT_R32G32B32_FLOAT,
                 InputSlot: 0,
@@ -522,7 +522,7 @@ impl Resources {
                 ],
             },
             DepthStencilState: D3D12_DEPTH_STENCIL_DESC::default(),
-            SampleMask: u32::max_value(),
+            SampleMask: u32::MAX,
             PrimitiveTopologyType: D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
             NumRenderTargets: 1,
             SampleDesc: DXGI_SAMPLE_DESC {
@@ -620,12 +620,8 @@ impl Resources {
     }
 }
 
-#[derive(Debug)]
-struct Camera {
-    position: Vec3f,
-    direction: Vec3f,
-}
-
+// Only read by the input assembler, through the vertex buffer.
+#[allow(dead_code)]
 struct Vertex {
     position: Vec3f,
     color: Vec4f,
@@ -1,52 +1,296 @@
-use pica_window::WindowAttributes;
-use wgpu_renderer::RendererAttributes;
-
+#[cfg(windows)]
 pub mod dx12_renderer;
+pub mod pica_gamepad;
+pub mod pica_keyboard;
+pub mod pica_simulation;
 pub mod pica_window;
 pub mod math;
+pub mod renderer;
+pub mod software_renderer;
 pub mod wgpu_renderer;
 
 pub type Result<T> = std::result::Result<T, crate::error::Error>;
 
 
 pub mod pica_time {
+    use std::collections::VecDeque;
+    #[cfg(windows)]
     use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
-    #[derive(Default, Debug)]
+    const NANOSECONDS_PER_SECOND: i64 = 1000 * 1000 * 1000;
+
+    /// Frames [`FrameStats`] keeps, about two seconds at 60 frames per second.
+    pub const FRAME_STATS_FRAMES: usize = 120;
+
+    // How long before a deadline `sleep_until` stops sleeping and spins, the OS wakes threads late.
+    #[cfg(windows)]
+    const SPIN_NANOSECONDS: i64 = 2 * 1000 * 1000;
+    #[cfg(not(windows))]
+    const SPIN_NANOSECONDS: i64 = 250 * 1000;
+
+    /// Time since the clock started, updated once per frame.
+    ///
+    /// `Window` updates its `time` on every `pull()`, other clocks are updated with [`Time::update`] or,
+    /// with ticks of any source, [`Time::update_to`]. The fields count wall clock time, for UI and
+    /// cameras. `simulation` follows it unless paused or scaled.
+    #[derive(Default, Debug, Clone)]
     pub struct Time {
         pub delta_ticks: i64,
         pub delta_nanoseconds: i64,
         pub delta_microseconds: i64,
         pub delta_milliseconds: i64,
-        pub delta_seconds: f32,
+        pub delta_seconds: f64,
 
         pub ticks: i64,
         pub nanoseconds: i64,
         pub microseconds: i64,
         pub milliseconds: i64,
-        pub seconds: f32,
+        pub seconds: f64,
 
         pub initial_ticks: i64,
         pub ticks_per_second: i64,
+
+        pub simulation: SimulationTime,
+        pub frame_stats: FrameStats,
     }
 
     impl Time {
+        /// A clock on [`performance_counter`], starting now.
         pub fn new() -> Self {
-            let mut ticks_per_second: i64 = 0;
-            unsafe { QueryPerformanceFrequency(&mut ticks_per_second) };
-            let mut initial_ticks: i64 = 0;
-            unsafe { QueryPerformanceCounter(&mut initial_ticks) };
+            Self::with_ticks(performance_counter(), performance_frequency())
+        }
+
+        /// A clock on any tick source, starting at `initial_ticks`.
+        pub fn with_ticks(initial_ticks: i64, ticks_per_second: i64) -> Self {
             Self {
-                ticks_per_second,
                 initial_ticks,
+                ticks_per_second,
                 ..Default::default()
             }
         }
+
+        /// Advance the clock to the current [`performance_counter`].
+        pub fn update(&mut self) {
+            self.update_to(performance_counter());
+        }
+
+        /// Advance the clock to `current_ticks`, counted from the same source as `initial_ticks`.
+        pub fn update_to(&mut self, current_ticks: i64) {
+            let ticks = current_ticks - self.initial_ticks;
+            self.delta_ticks = ticks - self.ticks;
+            self.ticks = ticks;
+
+            self.delta_nanoseconds = self.ticks_to_nanoseconds(self.delta_ticks);
+            self.delta_microseconds = self.delta_nanoseconds / 1000;
+            self.delta_milliseconds = self.delta_microseconds / 1000;
+            self.delta_seconds = self.delta_ticks as f64 / self.ticks_per_second as f64;
+
+            self.nanoseconds = self.ticks_to_nanoseconds(self.ticks);
+            self.microseconds = self.nanoseconds / 1000;
+            self.milliseconds = self.microseconds / 1000;
+            self.seconds = self.ticks as f64 / self.ticks_per_second as f64;
+
+            self.simulation.advance(self.delta_nanoseconds);
+            self.frame_stats.push(self.delta_nanoseconds);
+        }
+
+        // In 128 bits, nanoseconds times the tick rate overflow 64 bits within seconds.
+        fn ticks_to_nanoseconds(&self, ticks: i64) -> i64 {
+            (ticks as i128 * NANOSECONDS_PER_SECOND as i128 / self.ticks_per_second as i128) as i64
+        }
+    }
+
+    /// Simulation clock, which can be paused, stepped and run slower or faster than the wall clock.
+    #[derive(Debug, Clone)]
+    pub struct SimulationTime {
+        /// Stops the clock, except for [`SimulationTime::step`].
+        pub paused: bool,
+        /// Simulated seconds per wall clock second: 0.1 for slow motion, 2 for fast-forward.
+        pub scale: f64,
+
+        pub delta_nanoseconds: i64,
+        pub delta_seconds: f64,
+        pub nanoseconds: i64,
+        pub seconds: f64,
+
+        // Time queued by `step`, applied on the next update.
+        pending_nanoseconds: i64,
+        // Scaled nanoseconds below one, carried to the next update.
+        remainder: f64,
+    }
+
+    impl Default for SimulationTime {
+        fn default() -> Self {
+            Self {
+                paused: false,
+                scale: 1.0,
+                delta_nanoseconds: 0,
+                delta_seconds: 0.0,
+                nanoseconds: 0,
+                seconds: 0.0,
+                pending_nanoseconds: 0,
+                remainder: 0.0,
+            }
+        }
+    }
+
+    impl SimulationTime {
+        /// Advance by `nanoseconds` on the next update, even while paused. Pass the step length of a
+        /// fixed-timestep simulation to run exactly one step.
+        pub fn step(&mut self, nanoseconds: i64) {
+            self.pending_nanoseconds += nanoseconds;
+        }
+
+        pub fn toggle_pause(&mut self) {
+            self.paused = !self.paused;
+        }
+
+        // Advance by `wall_nanoseconds` of wall clock time.
+        fn advance(&mut self, wall_nanoseconds: i64) {
+            let mut delta_nanoseconds = std::mem::take(&mut self.pending_nanoseconds);
+            if !self.paused {
+                let scaled = wall_nanoseconds as f64 * self.scale.max(0.0) + self.remainder;
+                delta_nanoseconds += scaled as i64;
+                self.remainder = scaled.fract();
+            }
+
+            self.delta_nanoseconds = delta_nanoseconds;
+            self.delta_seconds = delta_nanoseconds as f64 / NANOSECONDS_PER_SECOND as f64;
+            self.nanoseconds += delta_nanoseconds;
+            self.seconds = self.nanoseconds as f64 / NANOSECONDS_PER_SECOND as f64;
+        }
+    }
+
+    /// Frame times of the most recent [`FRAME_STATS_FRAMES`] frames, in nanoseconds.
+    #[derive(Debug, Clone, Default)]
+    pub struct FrameStats {
+        frame_times: VecDeque<i64>,
+    }
+
+    impl FrameStats {
+        /// Add the time of a frame, forgetting the oldest one when full.
+        pub fn push(&mut self, nanoseconds: i64) {
+            if self.frame_times.len() == FRAME_STATS_FRAMES {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back(nanoseconds);
+        }
+
+        pub fn len(&self) -> usize {
+            self.frame_times.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.frame_times.is_empty()
+        }
+
+        pub fn min(&self) -> Option<i64> {
+            self.frame_times.iter().copied().min()
+        }
+
+        pub fn max(&self) -> Option<i64> {
+            self.frame_times.iter().copied().max()
+        }
+
+        pub fn average(&self) -> Option<i64> {
+            match self.frame_times.len() {
+                0 => None,
+                len => Some(self.frame_times.iter().sum::<i64>() / len as i64),
+            }
+        }
+
+        /// The frame time `percentile` percent of the frames are at most as long as, e.g. 99 for
+        /// the slow frames that show up as stutter.
+        pub fn percentile(&self, percentile: f64) -> Option<i64> {
+            if self.frame_times.is_empty() {
+                return None;
+            }
+            let mut sorted: Vec<i64> = self.frame_times.iter().copied().collect();
+            sorted.sort_unstable();
+            // Nearest rank
+            let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+            Some(sorted[rank.max(1) - 1])
+        }
+
+        /// Frames per second over the average frame time.
+        pub fn frames_per_second(&self) -> Option<f64> {
+            self.average()
+                .filter(|&average| average > 0)
+                .map(|average| NANOSECONDS_PER_SECOND as f64 / average as f64)
+        }
+    }
+
+    /// Wait until [`performance_counter`] reaches `deadline`. Sleeps while the deadline is far away
+    /// and spins for the last stretch, which the OS scheduler does not hit precisely.
+    pub fn sleep_until(deadline: i64) {
+        #[cfg(windows)]
+        raise_timer_resolution();
+
+        let ticks_per_second = performance_frequency() as i128;
+        loop {
+            let remaining = deadline - performance_counter();
+            if remaining <= 0 {
+                return;
+            }
+            let remaining_nanoseconds =
+                (remaining as i128 * NANOSECONDS_PER_SECOND as i128 / ticks_per_second) as i64;
+            if remaining_nanoseconds > SPIN_NANOSECONDS {
+                let sleep = remaining_nanoseconds - SPIN_NANOSECONDS;
+                std::thread::sleep(std::time::Duration::from_nanos(sleep as u64));
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+    }
+
+    // Sleeps last a scheduler tick of up to 15.6 ms on Windows, ask for 1 ms ticks for the process.
+    #[cfg(windows)]
+    fn raise_timer_resolution() {
+        static RAISED: std::sync::Once = std::sync::Once::new();
+        RAISED.call_once(|| unsafe {
+            windows::Win32::Media::timeBeginPeriod(1);
+        });
+    }
+
+    /// Number of ticks per second of [`performance_counter`].
+    #[cfg(windows)]
+    pub fn performance_frequency() -> i64 {
+        let mut ticks_per_second: i64 = 0;
+        unsafe { QueryPerformanceFrequency(&mut ticks_per_second) };
+        ticks_per_second
+    }
+
+    /// Current value of the high resolution performance counter.
+    #[cfg(windows)]
+    pub fn performance_counter() -> i64 {
+        let mut ticks: i64 = 0;
+        if unsafe { !QueryPerformanceCounter(&mut ticks).as_bool() } {
+            let error = unsafe { windows::Win32::Foundation::GetLastError() };
+            println!("Error getting performance count: {:?}", error);
+        }
+        ticks
+    }
+
+    /// Number of ticks per second of [`performance_counter`], the monotonic clock counts nanoseconds.
+    #[cfg(not(windows))]
+    pub fn performance_frequency() -> i64 {
+        NANOSECONDS_PER_SECOND
+    }
+
+    /// Nanoseconds on a monotonic clock, counted from the first time it is queried.
+    #[cfg(not(windows))]
+    pub fn performance_counter() -> i64 {
+        use std::{sync::OnceLock, time::Instant};
+        static EPOCH: OnceLock<Instant> = OnceLock::new();
+        EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as i64
     }
 }
 
 pub mod pica_mouse {
+    #[cfg(windows)]
     use crate::error::Error;
+    #[cfg(windows)]
     use std::mem::size_of;
+    #[cfg(windows)]
     use windows::Win32::{
         Foundation::HWND,
         UI::Input::{RegisterRawInputDevices, RAWINPUTDEVICE, RAWINPUTDEVICE_FLAGS},
@@ -69,18 +313,72 @@ pub mod pica_mouse {
         }
     }
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum MouseButton {
+        Left,
+        Right,
+        Middle,
+        /// The first side button, usually "back".
+        X1,
+        /// The second side button, usually "forward".
+        X2,
+    }
+
+    /// How the OS cursor behaves over the window.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub enum CursorMode {
+        #[default]
+        Normal,
+        /// Hidden and held in the center of the window while it has focus, for camera control.
+        /// `Mouse::position` stays put, only `Mouse::delta_position` moves.
+        Locked,
+    }
+
+    /// Shape of the OS cursor over the client area.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum CursorIcon {
+        Arrow,
+        #[default]
+        Crosshair,
+        /// Pointing hand, over links and buttons.
+        Hand,
+        /// Text cursor.
+        Text,
+        Wait,
+        ResizeHorizontal,
+        ResizeVertical,
+        /// Arrows in all four directions.
+        Move,
+        NotAllowed,
+    }
+
     #[derive(Debug, Default)]
     pub struct Mouse {
         pub left_button: Button,
         pub right_button: Button,
+        pub middle_button: Button,
+        pub x1_button: Button,
+        pub x2_button: Button,
+        /// Whole wheel notches, positive away from the user.
         pub wheel: i32,
         pub delta_wheel: i32,
+        /// Whole wheel notches, positive to the right.
+        pub horizontal_wheel: i32,
+        pub delta_horizontal_wheel: i32,
+        /// Wheel rotation in notches including fractions, from high-resolution wheels and touchpads.
+        pub precise_delta_wheel: f32,
+        pub precise_delta_horizontal_wheel: f32,
         pub position: (i32, i32),
         pub delta_position: (i32, i32),
+        // Fractions of a notch not yet counted in `wheel` and `horizontal_wheel`.
+        wheel_remainder: (f32, f32),
     }
 
     impl Mouse {
+        /// Register the mouse for raw input. WM_INPUT is sent to `win32_window_handle`, or with
+        /// `HWND(0)` to whichever window of the thread has the keyboard focus.
         // what is going on with this RAWINPUTDEVICE_FLAGS??
+        #[cfg(windows)]
         pub fn new(win32_window_handle: HWND) -> Result<Self> {
             let raw_input_device = &[RAWINPUTDEVICE {
                 usUsagePage: 0x01,
@@ -99,20 +397,110 @@ pub mod pica_mouse {
             }
             Ok(Mouse::default())
         }
+
+        pub fn button(&self, button: MouseButton) -> Button {
+            match button {
+                MouseButton::Left => self.left_button,
+                MouseButton::Right => self.right_button,
+                MouseButton::Middle => self.middle_button,
+                MouseButton::X1 => self.x1_button,
+                MouseButton::X2 => self.x2_button,
+            }
+        }
+
+        pub fn button_mut(&mut self, button: MouseButton) -> &mut Button {
+            match button {
+                MouseButton::Left => &mut self.left_button,
+                MouseButton::Right => &mut self.right_button,
+                MouseButton::Middle => &mut self.middle_button,
+                MouseButton::X1 => &mut self.x1_button,
+                MouseButton::X2 => &mut self.x2_button,
+            }
+        }
+
+        // Reset the per-frame deltas and button transitions.
+        pub(crate) fn begin_frame(&mut self) {
+            self.delta_position = (0, 0);
+            self.delta_wheel = 0;
+            self.delta_horizontal_wheel = 0;
+            self.precise_delta_wheel = 0.0;
+            self.precise_delta_horizontal_wheel = 0.0;
+            for button in MouseButton::ALL {
+                let button = self.button_mut(button);
+                button.pressed = false;
+                button.released = false;
+            }
+        }
+
+        // Add a wheel rotation in notches, whole notches are counted once the fractions add up to one.
+        pub(crate) fn scroll(&mut self, horizontal: f32, vertical: f32) {
+            self.precise_delta_horizontal_wheel += horizontal;
+            self.precise_delta_wheel += vertical;
+
+            self.wheel_remainder.0 += horizontal;
+            self.wheel_remainder.1 += vertical;
+            let notches = (
+                self.wheel_remainder.0.trunc(),
+                self.wheel_remainder.1.trunc(),
+            );
+            self.wheel_remainder.0 -= notches.0;
+            self.wheel_remainder.1 -= notches.1;
+
+            self.delta_horizontal_wheel += notches.0 as i32;
+            self.horizontal_wheel += notches.0 as i32;
+            self.delta_wheel += notches.1 as i32;
+            self.wheel += notches.1 as i32;
+        }
+    }
+
+    impl MouseButton {
+        pub const ALL: [MouseButton; 5] = [
+            MouseButton::Left,
+            MouseButton::Right,
+            MouseButton::Middle,
+            MouseButton::X1,
+            MouseButton::X2,
+        ];
+
+        /// Index of this button in `Window::keys`, mouse buttons have virtual-key codes too.
+        pub fn virtual_key(self) -> usize {
+            match self {
+                MouseButton::Left => 0x01,
+                MouseButton::Right => 0x02,
+                MouseButton::Middle => 0x04,
+                MouseButton::X1 => 0x05,
+                MouseButton::X2 => 0x06,
+            }
+        }
     }
 }
 
 pub mod error {
-    use std::{error, fmt};
+    #[cfg(windows)]
+    use std::error;
+    #[cfg(windows)]
+    use std::fmt;
 
     #[derive(Debug)]
     pub enum Error {
         /// Win32 Error
+        #[cfg(windows)]
         Win32Error(Win32Error),
         Window(String),
         Mouse(String),
+        /// Failure to write a captured frame to disk
+        Capture(String),
+        /// A rendered frame differs from its golden reference image
+        Golden(String),
+        /// The system clipboard could not be written
+        Clipboard(String),
+        /// Failure to read or write an input recording
+        Replay(String),
+        /// A renderer could not produce or show a frame
+        Render(String),
     }
     /// The error type for when the OS cannot perform the requested operation.
+    #[cfg(windows)]
     #[derive(Debug)]
     pub struct Win32Error {
         line: u32,
@@ -120,6 +508,7 @@ pub mod error {
         error: windows::core::Error,
     }
 
+    #[cfg(windows)]
     impl Win32Error {
         #[allow(dead_code)]
         pub(crate) fn new(
@@ -135,10 +524,11 @@ pub mod error {
     #[macro_export]
     macro_rules! win_error {
         ($error:expr) => {
-            crate::error::Win32Error::new(line!(), file!(), $error)
+            $crate::error::Win32Error::new(line!(), file!(), $error)
         };
     }
 
+    #[cfg(windows)]
     impl fmt::Display for Win32Error {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
             f.pad(&format!(
@@ -148,7 +538,8 @@ pub mod error {
         }
     }
 
-    // impl error::Error for Win32Error {}
+    #[cfg(windows)]
+    impl error::Error for Win32Error {}
 }
 
 /// Some common utilities.
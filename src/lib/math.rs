@@ -68,13 +68,12 @@ pub fn create_view(
     Mat4::look_at_rh(camera_position, look_direction, up_direction)
 }
 pub fn create_projection(aspect: f32, is_perspective: ProjectionType) -> Mat4 {
-    let project_mat = match is_perspective {
+    match is_perspective {
         ProjectionType::PERSPECTIVE => {
             Mat4::from_cols_array(OPENGL_TO_WGPU_MATRIX) * Mat4::perspective_rh(2.0 * PI / 5.0, aspect, 0.1, 100.0)
         }
         ProjectionType::ORTHO => {
             Mat4::from_cols_array(OPENGL_TO_WGPU_MATRIX) * Mat4::orthographic_rh(-4.0, 4.0, -3.0, 3.0, -1.0, 6.0)
         }
-    };
-    project_mat
+    }
 }
\ No newline at end of file
@@ -0,0 +1,328 @@
+/// Linux gamepads through the evdev interface in `/dev/input`.
+///
+/// Needs read access to the event devices, which most distributions grant to the user at the seat.
+/// New devices are picked up by rescanning `/dev/input` once a second.
+use super::{GamepadAxis, GamepadButton, GamepadState};
+use std::{
+    ffi::CStr,
+    fs::{self, File, OpenOptions},
+    io::{self, Read},
+    mem::size_of,
+    os::unix::{fs::OpenOptionsExt, io::AsRawFd},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+// Event types and codes from linux/input-event-codes.h
+pub(super) const EV_SYN: u16 = 0x00;
+pub(super) const EV_KEY: u16 = 0x01;
+pub(super) const EV_ABS: u16 = 0x03;
+const SYN_DROPPED: u16 = 3;
+
+pub(super) const BTN_JOYSTICK: u16 = 0x120;
+pub(super) const BTN_SOUTH: u16 = 0x130;
+pub(super) const BTN_EAST: u16 = 0x131;
+pub(super) const BTN_NORTH: u16 = 0x133;
+pub(super) const BTN_WEST: u16 = 0x134;
+pub(super) const BTN_TL: u16 = 0x136;
+pub(super) const BTN_TR: u16 = 0x137;
+pub(super) const BTN_SELECT: u16 = 0x13A;
+pub(super) const BTN_START: u16 = 0x13B;
+pub(super) const BTN_MODE: u16 = 0x13C;
+pub(super) const BTN_THUMBL: u16 = 0x13D;
+pub(super) const BTN_THUMBR: u16 = 0x13E;
+pub(super) const BTN_DPAD_UP: u16 = 0x220;
+pub(super) const BTN_DPAD_DOWN: u16 = 0x221;
+pub(super) const BTN_DPAD_LEFT: u16 = 0x222;
+pub(super) const BTN_DPAD_RIGHT: u16 = 0x223;
+const KEY_MAX: usize = 0x2FF;
+
+pub(super) const ABS_X: u16 = 0x00;
+pub(super) const ABS_Y: u16 = 0x01;
+pub(super) const ABS_Z: u16 = 0x02;
+pub(super) const ABS_RX: u16 = 0x03;
+pub(super) const ABS_RY: u16 = 0x04;
+pub(super) const ABS_RZ: u16 = 0x05;
+const ABS_GAS: u16 = 0x09;
+const ABS_BRAKE: u16 = 0x0A;
+pub(super) const ABS_HAT0X: u16 = 0x10;
+pub(super) const ABS_HAT0Y: u16 = 0x11;
+const ABS_CNT: usize = 0x40;
+
+// ioctl request codes from linux/input.h
+const fn ioc_read(nr: u64, size: usize) -> u64 {
+    (2 << 30) | ((size as u64) << 16) | ((b'E' as u64) << 8) | nr
+}
+const fn eviocgname(length: usize) -> u64 {
+    ioc_read(0x06, length)
+}
+const fn eviocgkey(length: usize) -> u64 {
+    ioc_read(0x18, length)
+}
+const fn eviocgbit(event_type: u16, length: usize) -> u64 {
+    ioc_read(0x20 + event_type as u64, length)
+}
+const fn eviocgabs(axis: u16) -> u64 {
+    ioc_read(0x40 + axis as u64, size_of::<libc::input_absinfo>())
+}
+
+// Buttons by evdev key code, face buttons are named by position on both gamepads and joysticks.
+fn button(code: u16) -> Option<GamepadButton> {
+    Some(match code {
+        BTN_SOUTH | BTN_JOYSTICK => GamepadButton::South,
+        BTN_EAST | 0x121 => GamepadButton::East,
+        BTN_WEST | 0x122 => GamepadButton::West,
+        BTN_NORTH | 0x123 => GamepadButton::North,
+        BTN_TL | 0x124 => GamepadButton::LeftShoulder,
+        BTN_TR | 0x125 => GamepadButton::RightShoulder,
+        BTN_SELECT => GamepadButton::Back,
+        BTN_START => GamepadButton::Start,
+        BTN_MODE => GamepadButton::Guide,
+        BTN_THUMBL => GamepadButton::LeftStick,
+        BTN_THUMBR => GamepadButton::RightStick,
+        BTN_DPAD_UP => GamepadButton::DPadUp,
+        BTN_DPAD_DOWN => GamepadButton::DPadDown,
+        BTN_DPAD_LEFT => GamepadButton::DPadLeft,
+        BTN_DPAD_RIGHT => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+fn axis(code: u16) -> Option<GamepadAxis> {
+    Some(match code {
+        ABS_X => GamepadAxis::LeftX,
+        ABS_Y => GamepadAxis::LeftY,
+        ABS_RX => GamepadAxis::RightX,
+        ABS_RY => GamepadAxis::RightY,
+        ABS_Z | ABS_BRAKE => GamepadAxis::LeftTrigger,
+        ABS_RZ | ABS_GAS => GamepadAxis::RightTrigger,
+        _ => return None,
+    })
+}
+
+fn test_bit(bits: &[u8], bit: usize) -> bool {
+    bits.get(bit / 8)
+        .is_some_and(|byte| byte >> (bit % 8) & 1 == 1)
+}
+
+#[derive(Debug)]
+struct Device {
+    file: File,
+    path: PathBuf,
+    // Value ranges of the absolute axes the device has.
+    ranges: [Option<(i32, i32)>; ABS_CNT],
+}
+
+impl Device {
+    // Open an event device, `None` when it is no gamepad or joystick or we may not read it.
+    fn open(path: &Path) -> Option<(Self, String)> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+            .ok()?;
+        let fd = file.as_raw_fd();
+
+        let mut keys = [0u8; KEY_MAX / 8 + 1];
+        if unsafe { libc::ioctl(fd, eviocgbit(EV_KEY, keys.len()) as _, keys.as_mut_ptr()) } < 0
+            || !(test_bit(&keys, BTN_SOUTH as usize) || test_bit(&keys, BTN_JOYSTICK as usize))
+        {
+            return None;
+        }
+
+        let mut abs_bits = [0u8; ABS_CNT / 8];
+        unsafe {
+            libc::ioctl(
+                fd,
+                eviocgbit(EV_ABS, abs_bits.len()) as _,
+                abs_bits.as_mut_ptr(),
+            )
+        };
+        let mut ranges = [None; ABS_CNT];
+        for (code, range) in ranges.iter_mut().enumerate() {
+            if test_bit(&abs_bits, code) {
+                let mut info: libc::input_absinfo = unsafe { std::mem::zeroed() };
+                if unsafe { libc::ioctl(fd, eviocgabs(code as u16) as _, &mut info) } >= 0
+                    && info.maximum > info.minimum
+                {
+                    *range = Some((info.minimum, info.maximum));
+                }
+            }
+        }
+
+        let mut name = [0u8; 256];
+        unsafe { libc::ioctl(fd, eviocgname(name.len() - 1) as _, name.as_mut_ptr()) };
+        let name = CStr::from_bytes_until_nul(&name)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let device = Device {
+            file,
+            path: path.to_owned(),
+            ranges,
+        };
+        Some((device, name))
+    }
+
+    // Read the full state from the kernel, when opened and after the event queue overflowed.
+    fn sync(&self, state: &mut GamepadState) {
+        let fd = self.file.as_raw_fd();
+        let mut keys = [0u8; KEY_MAX / 8 + 1];
+        unsafe { libc::ioctl(fd, eviocgkey(keys.len()) as _, keys.as_mut_ptr()) };
+        state.buttons = [false; GamepadButton::COUNT];
+        for code in 0..=KEY_MAX as u16 {
+            if test_bit(&keys, code as usize) {
+                self.key(state, code, 1);
+            }
+        }
+        for code in 0..ABS_CNT as u16 {
+            if self.ranges[code as usize].is_some() {
+                let mut info: libc::input_absinfo = unsafe { std::mem::zeroed() };
+                if unsafe { libc::ioctl(fd, eviocgabs(code) as _, &mut info) } >= 0 {
+                    self.abs(state, code, info.value);
+                }
+            }
+        }
+    }
+
+    fn key(&self, state: &mut GamepadState, code: u16, value: i32) {
+        if let Some(button) = button(code) {
+            state.buttons[button as usize] = value != 0;
+        }
+    }
+
+    fn abs(&self, state: &mut GamepadState, code: u16, value: i32) {
+        let (minimum, maximum) = match self.ranges.get(code as usize) {
+            Some(Some(range)) => *range,
+            _ => return,
+        };
+        let normalized = (value - minimum) as f32 / (maximum - minimum) as f32;
+        match code {
+            // Most gamepads report the d-pad as a hat
+            ABS_HAT0X => {
+                state.buttons[GamepadButton::DPadLeft as usize] = value < 0;
+                state.buttons[GamepadButton::DPadRight as usize] = value > 0;
+            }
+            ABS_HAT0Y => {
+                state.buttons[GamepadButton::DPadUp as usize] = value < 0;
+                state.buttons[GamepadButton::DPadDown as usize] = value > 0;
+            }
+            _ => match axis(code) {
+                Some(axis @ (GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger)) => {
+                    state.axes[axis as usize] = normalized
+                }
+                Some(axis) => state.axes[axis as usize] = normalized * 2.0 - 1.0,
+                None => {}
+            },
+        }
+    }
+
+    // Apply all queued events, returns false once the device is gone.
+    fn read(&mut self, state: &mut GamepadState) -> bool {
+        let mut buffer = [0u8; 64 * size_of::<libc::input_event>()];
+        loop {
+            let length = match self.file.read(&mut buffer) {
+                Ok(length) => length,
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return true,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => return false,
+            };
+            if length == 0 {
+                return true;
+            }
+            for event in buffer[..length].chunks_exact(size_of::<libc::input_event>()) {
+                let event: libc::input_event =
+                    unsafe { std::ptr::read_unaligned(event.as_ptr().cast()) };
+                match (event.type_, event.code) {
+                    (EV_KEY, code) => self.key(state, code, event.value),
+                    (EV_ABS, code) => self.abs(state, code, event.value),
+                    (EV_SYN, SYN_DROPPED) => self.sync(state),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Evdev {
+    devices: Vec<Option<Device>>,
+    last_scan: Option<Instant>,
+}
+
+impl Evdev {
+    pub(super) fn new() -> Self {
+        Self {
+            devices: Vec::new(),
+            last_scan: None,
+        }
+    }
+
+    pub(super) fn poll(&mut self, states: &mut Vec<GamepadState>) {
+        if self
+            .last_scan
+            .is_none_or(|last_scan| last_scan.elapsed() >= RESCAN_INTERVAL)
+        {
+            self.last_scan = Some(Instant::now());
+            self.scan(states);
+        }
+
+        for (device, state) in self.devices.iter_mut().zip(states.iter_mut()) {
+            if let Some(open_device) = device {
+                if !open_device.read(state) {
+                    *device = None;
+                    state.connected = false;
+                }
+            }
+        }
+    }
+
+    // Open the gamepads that appeared in /dev/input, each in the first free slot.
+    fn scan(&mut self, states: &mut Vec<GamepadState>) {
+        let entries = match fs::read_dir("/dev/input") {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with("event"))
+            })
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            if self
+                .devices
+                .iter()
+                .flatten()
+                .any(|device| device.path == path)
+            {
+                continue;
+            }
+            if let Some((device, name)) = Device::open(&path) {
+                let slot = match self.devices.iter().position(Option::is_none) {
+                    Some(slot) => slot,
+                    None => {
+                        self.devices.push(None);
+                        self.devices.len() - 1
+                    }
+                };
+                if states.len() < self.devices.len() {
+                    states.resize_with(self.devices.len(), GamepadState::default);
+                }
+                let state = &mut states[slot];
+                *state = GamepadState {
+                    connected: true,
+                    name,
+                    ..Default::default()
+                };
+                device.sync(state);
+                self.devices[slot] = Some(device);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Gamepad and joystick input.
+//!
+//! `Window::gamepads` polls the controllers of the OS on every `pull()`: XInput on Windows and evdev on
+//! Linux. Every controller keeps its slot in [`Gamepads::gamepads`] while it is connected, hot-plugging
+//! is reported with `Event::GamepadConnected` and `Event::GamepadDisconnected`.
+use crate::{pica_mouse::Button, pica_window::event::Event};
+
+#[cfg(target_os = "linux")]
+pub mod evdev;
+#[cfg(target_os = "linux")]
+pub mod uinput;
+#[cfg(windows)]
+pub mod xinput;
+
+/// Radial deadzone of the sticks, XInput's recommendation for the left thumb stick.
+pub const DEFAULT_STICK_DEADZONE: f32 = 7849.0 / 32767.0;
+/// Deadzone of the triggers, XInput's trigger threshold.
+pub const DEFAULT_TRIGGER_DEADZONE: f32 = 30.0 / 255.0;
+
+/// Buttons named after the layout of an Xbox controller, face buttons by their position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    /// A on Xbox, cross on PlayStation.
+    South,
+    /// B on Xbox, circle on PlayStation.
+    East,
+    /// X on Xbox, square on PlayStation.
+    West,
+    /// Y on Xbox, triangle on PlayStation.
+    North,
+    LeftShoulder,
+    RightShoulder,
+    Back,
+    Start,
+    Guide,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const COUNT: usize = 15;
+    pub const ALL: [GamepadButton; Self::COUNT] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::Back,
+        GamepadButton::Start,
+        GamepadButton::Guide,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+/// Sticks range from -1 to 1, positive to the right and down like the mouse. Triggers range from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const COUNT: usize = 6;
+    pub const ALL: [GamepadAxis; Self::COUNT] = [
+        GamepadAxis::LeftX,
+        GamepadAxis::LeftY,
+        GamepadAxis::RightX,
+        GamepadAxis::RightY,
+        GamepadAxis::LeftTrigger,
+        GamepadAxis::RightTrigger,
+    ];
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Gamepad {
+    pub name: String,
+    pub connected: bool,
+    pub buttons: [Button; GamepadButton::COUNT],
+    /// Axis values with the deadzones applied, index with [`GamepadAxis`].
+    pub axes: [f32; GamepadAxis::COUNT],
+}
+
+impl Gamepad {
+    pub fn button(&self, button: GamepadButton) -> Button {
+        self.buttons[button as usize]
+    }
+
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    pub fn left_stick(&self) -> (f32, f32) {
+        (self.axis(GamepadAxis::LeftX), self.axis(GamepadAxis::LeftY))
+    }
+
+    pub fn right_stick(&self) -> (f32, f32) {
+        (
+            self.axis(GamepadAxis::RightX),
+            self.axis(GamepadAxis::RightY),
+        )
+    }
+}
+
+/// Raw state of a controller as a backend reports it, before deadzones and button edges.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct GamepadState {
+    pub connected: bool,
+    pub name: String,
+    pub buttons: [bool; GamepadButton::COUNT],
+    pub axes: [f32; GamepadAxis::COUNT],
+}
+
+#[derive(Debug)]
+enum Backend {
+    #[cfg(target_os = "linux")]
+    Evdev(evdev::Evdev),
+    #[cfg(windows)]
+    XInput(xinput::XInput),
+    /// Reports no controllers, for headless windows.
+    None,
+}
+
+#[derive(Debug)]
+pub struct Gamepads {
+    /// One slot per controller, a slot is reused once its controller disconnected.
+    pub gamepads: Vec<Gamepad>,
+    pub stick_deadzone: f32,
+    pub trigger_deadzone: f32,
+    backend: Backend,
+    states: Vec<GamepadState>,
+}
+
+impl Gamepads {
+    /// Poll the controllers of the OS.
+    pub fn new() -> Self {
+        #[cfg(target_os = "linux")]
+        let backend = Backend::Evdev(evdev::Evdev::new());
+        #[cfg(windows)]
+        let backend = Backend::XInput(xinput::XInput::new());
+        #[cfg(not(any(target_os = "linux", windows)))]
+        let backend = Backend::None;
+        Self::with_backend(backend)
+    }
+
+    /// Without any controllers.
+    pub fn none() -> Self {
+        Self::with_backend(Backend::None)
+    }
+
+    fn with_backend(backend: Backend) -> Self {
+        Self {
+            gamepads: Vec::new(),
+            stick_deadzone: DEFAULT_STICK_DEADZONE,
+            trigger_deadzone: DEFAULT_TRIGGER_DEADZONE,
+            backend,
+            states: Vec::new(),
+        }
+    }
+
+    /// The controller in `slot`, if it is connected.
+    pub fn get(&self, slot: usize) -> Option<&Gamepad> {
+        self.gamepads.get(slot).filter(|gamepad| gamepad.connected)
+    }
+
+    /// The connected controllers and their slots.
+    pub fn connected(&self) -> impl Iterator<Item = (usize, &Gamepad)> {
+        self.gamepads
+            .iter()
+            .enumerate()
+            .filter(|(_, gamepad)| gamepad.connected)
+    }
+
+    /// Poll the controllers, reporting connects and disconnects in `events`.
+    pub fn pull(&mut self, events: &mut Vec<Event>) {
+        match &mut self.backend {
+            #[cfg(target_os = "linux")]
+            Backend::Evdev(evdev) => evdev.poll(&mut self.states),
+            #[cfg(windows)]
+            Backend::XInput(xinput) => xinput.poll(&mut self.states),
+            Backend::None => {}
+        }
+
+        if self.gamepads.len() < self.states.len() {
+            self.gamepads
+                .resize_with(self.states.len(), Gamepad::default);
+        }
+        for (slot, (gamepad, state)) in self.gamepads.iter_mut().zip(&self.states).enumerate() {
+            if gamepad.connected != state.connected {
+                gamepad.connected = state.connected;
+                gamepad.name = state.name.clone();
+                events.push(match state.connected {
+                    true => Event::GamepadConnected(slot),
+                    false => Event::GamepadDisconnected(slot),
+                });
+            }
+
+            for (button, &is_down) in gamepad.buttons.iter_mut().zip(&state.buttons) {
+                button.update_button(is_down && state.connected);
+            }
+
+            let axes = match state.connected {
+                true => state.axes,
+                false => [0.0; GamepadAxis::COUNT],
+            };
+            let left = stick_deadzone(axes[0], axes[1], self.stick_deadzone);
+            let right = stick_deadzone(axes[2], axes[3], self.stick_deadzone);
+            gamepad.axes = [
+                left.0,
+                left.1,
+                right.0,
+                right.1,
+                trigger_deadzone(axes[4], self.trigger_deadzone),
+                trigger_deadzone(axes[5], self.trigger_deadzone),
+            ];
+        }
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Radial deadzone: the stick reads zero inside `deadzone`, outside it the remaining range is scaled
+/// back up to 0..1 so small motions past the deadzone stay small.
+pub fn stick_deadzone(x: f32, y: f32, deadzone: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= deadzone {
+        return (0.0, 0.0);
+    }
+    let scaled = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0);
+    (x / magnitude * scaled, y / magnitude * scaled)
+}
+
+/// Linear deadzone of a trigger in the 0..1 range.
+pub fn trigger_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value <= deadzone {
+        return 0.0;
+    }
+    ((value - deadzone) / (1.0 - deadzone)).min(1.0)
+}
@@ -0,0 +1,168 @@
+/// Virtual gamepads through `/dev/uinput`, to test gamepad input without a controller.
+///
+/// Needs write access to `/dev/uinput`. The kernel creates an event device for the virtual gamepad,
+/// which `Gamepads` picks up like any other controller.
+use super::evdev::*;
+use super::{GamepadAxis, GamepadButton};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    mem::size_of,
+    os::unix::io::AsRawFd,
+};
+
+// ioctl request codes from linux/uinput.h
+const UI_DEV_CREATE: u64 = 0x5501;
+const UI_DEV_DESTROY: u64 = 0x5502;
+const UI_SET_EVBIT: u64 = 0x4004_5564;
+const UI_SET_KEYBIT: u64 = 0x4004_5565;
+const UI_SET_ABSBIT: u64 = 0x4004_5567;
+
+const UINPUT_MAX_NAME_SIZE: usize = 80;
+const BUS_VIRTUAL: u16 = 0x06;
+
+/// Axis range of the virtual sticks, triggers range from 0 to `AXIS_MAX`.
+const AXIS_MAX: i32 = 32767;
+
+// struct uinput_user_dev
+#[repr(C)]
+struct UinputUserDev {
+    name: [u8; UINPUT_MAX_NAME_SIZE],
+    id: libc::input_id,
+    ff_effects_max: u32,
+    absmax: [i32; 64],
+    absmin: [i32; 64],
+    absfuzz: [i32; 64],
+    absflat: [i32; 64],
+}
+
+fn button_code(button: GamepadButton) -> u16 {
+    match button {
+        GamepadButton::South => BTN_SOUTH,
+        GamepadButton::East => BTN_EAST,
+        GamepadButton::West => BTN_WEST,
+        GamepadButton::North => BTN_NORTH,
+        GamepadButton::LeftShoulder => BTN_TL,
+        GamepadButton::RightShoulder => BTN_TR,
+        GamepadButton::Back => BTN_SELECT,
+        GamepadButton::Start => BTN_START,
+        GamepadButton::Guide => BTN_MODE,
+        GamepadButton::LeftStick => BTN_THUMBL,
+        GamepadButton::RightStick => BTN_THUMBR,
+        GamepadButton::DPadUp => BTN_DPAD_UP,
+        GamepadButton::DPadDown => BTN_DPAD_DOWN,
+        GamepadButton::DPadLeft => BTN_DPAD_LEFT,
+        GamepadButton::DPadRight => BTN_DPAD_RIGHT,
+    }
+}
+
+fn axis_code(axis: GamepadAxis) -> u16 {
+    match axis {
+        GamepadAxis::LeftX => ABS_X,
+        GamepadAxis::LeftY => ABS_Y,
+        GamepadAxis::RightX => ABS_RX,
+        GamepadAxis::RightY => ABS_RY,
+        GamepadAxis::LeftTrigger => ABS_Z,
+        GamepadAxis::RightTrigger => ABS_RZ,
+    }
+}
+
+/// A gamepad with every [`GamepadButton`] and [`GamepadAxis`], removed again when dropped.
+#[derive(Debug)]
+pub struct VirtualGamepad {
+    file: File,
+}
+
+impl VirtualGamepad {
+    pub fn new(name: &str) -> io::Result<Self> {
+        let mut file = OpenOptions::new().write(true).open("/dev/uinput")?;
+        let fd = file.as_raw_fd();
+        let ioctl = |request: u64, value: u16| -> io::Result<()> {
+            match unsafe { libc::ioctl(fd, request as _, value as libc::c_int) } {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        };
+
+        ioctl(UI_SET_EVBIT, EV_KEY)?;
+        for button in GamepadButton::ALL {
+            ioctl(UI_SET_KEYBIT, button_code(button))?;
+        }
+        let mut device = UinputUserDev {
+            name: [0; UINPUT_MAX_NAME_SIZE],
+            id: libc::input_id {
+                bustype: BUS_VIRTUAL,
+                vendor: 0,
+                product: 0,
+                version: 1,
+            },
+            ff_effects_max: 0,
+            absmax: [0; 64],
+            absmin: [0; 64],
+            absfuzz: [0; 64],
+            absflat: [0; 64],
+        };
+        ioctl(UI_SET_EVBIT, EV_ABS)?;
+        for axis in GamepadAxis::ALL {
+            let code = axis_code(axis) as usize;
+            ioctl(UI_SET_ABSBIT, code as u16)?;
+            device.absmax[code] = AXIS_MAX;
+            device.absmin[code] = match axis {
+                GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => 0,
+                _ => -AXIS_MAX,
+            };
+        }
+        let name = name.as_bytes();
+        let length = name.len().min(UINPUT_MAX_NAME_SIZE - 1);
+        device.name[..length].copy_from_slice(&name[..length]);
+
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                (&device as *const UinputUserDev).cast::<u8>(),
+                size_of::<UinputUserDev>(),
+            )
+        };
+        file.write_all(bytes)?;
+        if unsafe { libc::ioctl(fd, UI_DEV_CREATE as _) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { file })
+    }
+
+    pub fn set_button(&mut self, button: GamepadButton, is_down: bool) -> io::Result<()> {
+        self.emit(EV_KEY, button_code(button), is_down as i32)?;
+        self.emit(EV_SYN, 0, 0)
+    }
+
+    /// Move an axis, sticks range from -1 to 1 and triggers from 0 to 1.
+    pub fn set_axis(&mut self, axis: GamepadAxis, value: f32) -> io::Result<()> {
+        let value = (value.clamp(-1.0, 1.0) * AXIS_MAX as f32).round() as i32;
+        self.emit(EV_ABS, axis_code(axis), value)?;
+        self.emit(EV_SYN, 0, 0)
+    }
+
+    fn emit(&mut self, event_type: u16, code: u16, value: i32) -> io::Result<()> {
+        let event = libc::input_event {
+            time: libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            type_: event_type,
+            code,
+            value,
+        };
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                (&event as *const libc::input_event).cast::<u8>(),
+                size_of::<libc::input_event>(),
+            )
+        };
+        self.file.write_all(bytes)
+    }
+}
+
+impl Drop for VirtualGamepad {
+    fn drop(&mut self) {
+        unsafe { libc::ioctl(self.file.as_raw_fd(), UI_DEV_DESTROY as _) };
+    }
+}
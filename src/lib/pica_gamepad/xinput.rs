@@ -0,0 +1,85 @@
+/// Windows gamepads through XInput, which covers Xbox and most other controllers with a driver for it.
+use super::{GamepadAxis, GamepadButton, GamepadState};
+use std::time::{Duration, Instant};
+use windows::Win32::{
+    Foundation::ERROR_SUCCESS,
+    UI::Input::XboxController::{
+        XInputGetState, XINPUT_GAMEPAD_A, XINPUT_GAMEPAD_B, XINPUT_GAMEPAD_BACK,
+        XINPUT_GAMEPAD_DPAD_DOWN, XINPUT_GAMEPAD_DPAD_LEFT, XINPUT_GAMEPAD_DPAD_RIGHT,
+        XINPUT_GAMEPAD_DPAD_UP, XINPUT_GAMEPAD_LEFT_SHOULDER, XINPUT_GAMEPAD_LEFT_THUMB,
+        XINPUT_GAMEPAD_RIGHT_SHOULDER, XINPUT_GAMEPAD_RIGHT_THUMB, XINPUT_GAMEPAD_START,
+        XINPUT_GAMEPAD_X, XINPUT_GAMEPAD_Y, XINPUT_STATE, XUSER_MAX_COUNT,
+    },
+};
+
+// Polling an empty slot stalls for a while, only look for new controllers once a second.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+const BUTTONS: [(u32, GamepadButton); 14] = [
+    (XINPUT_GAMEPAD_A, GamepadButton::South),
+    (XINPUT_GAMEPAD_B, GamepadButton::East),
+    (XINPUT_GAMEPAD_X, GamepadButton::West),
+    (XINPUT_GAMEPAD_Y, GamepadButton::North),
+    (XINPUT_GAMEPAD_LEFT_SHOULDER, GamepadButton::LeftShoulder),
+    (XINPUT_GAMEPAD_RIGHT_SHOULDER, GamepadButton::RightShoulder),
+    (XINPUT_GAMEPAD_BACK, GamepadButton::Back),
+    (XINPUT_GAMEPAD_START, GamepadButton::Start),
+    (XINPUT_GAMEPAD_LEFT_THUMB, GamepadButton::LeftStick),
+    (XINPUT_GAMEPAD_RIGHT_THUMB, GamepadButton::RightStick),
+    (XINPUT_GAMEPAD_DPAD_UP, GamepadButton::DPadUp),
+    (XINPUT_GAMEPAD_DPAD_DOWN, GamepadButton::DPadDown),
+    (XINPUT_GAMEPAD_DPAD_LEFT, GamepadButton::DPadLeft),
+    (XINPUT_GAMEPAD_DPAD_RIGHT, GamepadButton::DPadRight),
+];
+
+#[derive(Debug)]
+pub struct XInput {
+    last_scan: Option<Instant>,
+}
+
+impl XInput {
+    pub(super) fn new() -> Self {
+        Self { last_scan: None }
+    }
+
+    pub(super) fn poll(&mut self, states: &mut Vec<GamepadState>) {
+        // XInput has a fixed slot per controller
+        states.resize_with(XUSER_MAX_COUNT as usize, GamepadState::default);
+
+        let scan = self
+            .last_scan
+            .is_none_or(|last_scan| last_scan.elapsed() >= RESCAN_INTERVAL);
+        if scan {
+            self.last_scan = Some(Instant::now());
+        }
+
+        for (slot, state) in states.iter_mut().enumerate() {
+            if !state.connected && !scan {
+                continue;
+            }
+
+            let mut xinput_state = XINPUT_STATE::default();
+            if unsafe { XInputGetState(slot as u32, &mut xinput_state) } != ERROR_SUCCESS.0 {
+                state.connected = false;
+                continue;
+            }
+            if !state.connected {
+                state.connected = true;
+                state.name = format!("XInput Controller {}", slot + 1);
+            }
+
+            let gamepad = xinput_state.Gamepad;
+            for (flag, button) in BUTTONS {
+                state.buttons[button as usize] = gamepad.wButtons as u32 & flag != 0;
+            }
+            // XInput's y axes point up, PiCa's point down like the mouse.
+            let stick = |value: i16| (value as f32 / 32767.0).max(-1.0);
+            state.axes[GamepadAxis::LeftX as usize] = stick(gamepad.sThumbLX);
+            state.axes[GamepadAxis::LeftY as usize] = -stick(gamepad.sThumbLY);
+            state.axes[GamepadAxis::RightX as usize] = stick(gamepad.sThumbRX);
+            state.axes[GamepadAxis::RightY as usize] = -stick(gamepad.sThumbRY);
+            state.axes[GamepadAxis::LeftTrigger as usize] = gamepad.bLeftTrigger as f32 / 255.0;
+            state.axes[GamepadAxis::RightTrigger as usize] = gamepad.bRightTrigger as f32 / 255.0;
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Platform independent keyboard keys.
+//!
+//! Every platform backend reports its keys in `Window::keys` at the index of the Win32 virtual-key
+//! code, which is the discriminant of each [`Key`]. Use `Window::key` instead of indexing by hand.
+
+macro_rules! keys {
+    ($($(#[$meta:meta])* $name:ident = $virtual_key:literal,)*) => {
+        #[repr(u8)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Key {
+            $($(#[$meta])* $name = $virtual_key,)*
+        }
+
+        impl Key {
+            /// Every key, in virtual-key order.
+            pub const ALL: &'static [Key] = &[$(Key::$name,)*];
+
+            /// The key at virtual-key index `virtual_key` of `Window::keys`, if any.
+            pub fn from_virtual_key(virtual_key: u8) -> Option<Key> {
+                match virtual_key {
+                    $($virtual_key => Some(Key::$name),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+keys! {
+    Backspace = 0x08,
+    Tab = 0x09,
+    Enter = 0x0D,
+    /// Either shift key.
+    Shift = 0x10,
+    /// Either control key.
+    Control = 0x11,
+    /// Either alt key.
+    Alt = 0x12,
+    Pause = 0x13,
+    CapsLock = 0x14,
+    Escape = 0x1B,
+    Space = 0x20,
+    PageUp = 0x21,
+    PageDown = 0x22,
+    End = 0x23,
+    Home = 0x24,
+    Left = 0x25,
+    Up = 0x26,
+    Right = 0x27,
+    Down = 0x28,
+    PrintScreen = 0x2C,
+    Insert = 0x2D,
+    Delete = 0x2E,
+
+    Digit0 = 0x30,
+    Digit1 = 0x31,
+    Digit2 = 0x32,
+    Digit3 = 0x33,
+    Digit4 = 0x34,
+    Digit5 = 0x35,
+    Digit6 = 0x36,
+    Digit7 = 0x37,
+    Digit8 = 0x38,
+    Digit9 = 0x39,
+
+    A = 0x41,
+    B = 0x42,
+    C = 0x43,
+    D = 0x44,
+    E = 0x45,
+    F = 0x46,
+    G = 0x47,
+    H = 0x48,
+    I = 0x49,
+    J = 0x4A,
+    K = 0x4B,
+    L = 0x4C,
+    M = 0x4D,
+    N = 0x4E,
+    O = 0x4F,
+    P = 0x50,
+    Q = 0x51,
+    R = 0x52,
+    S = 0x53,
+    T = 0x54,
+    U = 0x55,
+    V = 0x56,
+    W = 0x57,
+    X = 0x58,
+    Y = 0x59,
+    Z = 0x5A,
+
+    LeftSuper = 0x5B,
+    RightSuper = 0x5C,
+    Menu = 0x5D,
+
+    Numpad0 = 0x60,
+    Numpad1 = 0x61,
+    Numpad2 = 0x62,
+    Numpad3 = 0x63,
+    Numpad4 = 0x64,
+    Numpad5 = 0x65,
+    Numpad6 = 0x66,
+    Numpad7 = 0x67,
+    Numpad8 = 0x68,
+    Numpad9 = 0x69,
+    NumpadMultiply = 0x6A,
+    NumpadAdd = 0x6B,
+    NumpadSeparator = 0x6C,
+    NumpadSubtract = 0x6D,
+    NumpadDecimal = 0x6E,
+    NumpadDivide = 0x6F,
+
+    F1 = 0x70,
+    F2 = 0x71,
+    F3 = 0x72,
+    F4 = 0x73,
+    F5 = 0x74,
+    F6 = 0x75,
+    F7 = 0x76,
+    F8 = 0x77,
+    F9 = 0x78,
+    F10 = 0x79,
+    F11 = 0x7A,
+    F12 = 0x7B,
+    F13 = 0x7C,
+    F14 = 0x7D,
+    F15 = 0x7E,
+    F16 = 0x7F,
+    F17 = 0x80,
+    F18 = 0x81,
+    F19 = 0x82,
+    F20 = 0x83,
+    F21 = 0x84,
+    F22 = 0x85,
+    F23 = 0x86,
+    F24 = 0x87,
+
+    NumLock = 0x90,
+    ScrollLock = 0x91,
+
+    LeftShift = 0xA0,
+    RightShift = 0xA1,
+    LeftControl = 0xA2,
+    RightControl = 0xA3,
+    LeftAlt = 0xA4,
+    RightAlt = 0xA5,
+
+    Semicolon = 0xBA,
+    Equals = 0xBB,
+    Comma = 0xBC,
+    Minus = 0xBD,
+    Period = 0xBE,
+    Slash = 0xBF,
+    Grave = 0xC0,
+    LeftBracket = 0xDB,
+    Backslash = 0xDC,
+    RightBracket = 0xDD,
+    Apostrophe = 0xDE,
+}
+
+impl Key {
+    /// Index of this key in `Window::keys`.
+    pub fn virtual_key(self) -> usize {
+        self as u8 as usize
+    }
+}
+
+/// State of the modifier keys, either the left or the right key counts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    /// The Windows, Command or Super key.
+    pub logo: bool,
+}
@@ -0,0 +1,86 @@
+//! Fixed-timestep simulation.
+//!
+//! [`Simulation`] runs the classic accumulator loop: the variable frame time of `Window::pull` is
+//! collected and spent in steps of a fixed length, so the simulation behaves the same at any frame
+//! rate. What is left over is less than one step and is reported as [`Simulation::alpha`], to
+//! interpolate between the two most recent simulation states when rendering.
+
+const NANOSECONDS_PER_SECOND: i64 = 1000 * 1000 * 1000;
+
+/// Steps run by one [`Simulation::advance`] unless set otherwise.
+pub const DEFAULT_MAX_STEPS: u32 = 8;
+
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    /// Simulated time per step, in nanoseconds.
+    pub step_nanoseconds: i64,
+    /// Most steps one [`Simulation::advance`] runs. When the steps take longer than the time they
+    /// simulate, the time that does not fit is dropped instead of piling up ever more steps per frame.
+    pub max_steps: u32,
+    /// Steps run since the simulation started.
+    pub steps: u64,
+    /// Steps skipped because more than `max_steps` were due in a frame.
+    pub dropped_steps: u64,
+    accumulator: i64,
+}
+
+impl Simulation {
+    /// A simulation running `steps_per_second` steps per simulated second.
+    pub fn new(steps_per_second: u32) -> Self {
+        assert!(
+            steps_per_second > 0,
+            "a simulation needs at least one step per second"
+        );
+        Self::with_step(NANOSECONDS_PER_SECOND / steps_per_second as i64)
+    }
+
+    /// A simulation stepping `step_nanoseconds` at a time.
+    pub fn with_step(step_nanoseconds: i64) -> Self {
+        assert!(step_nanoseconds > 0, "a simulation step must take time");
+        Self {
+            step_nanoseconds,
+            max_steps: DEFAULT_MAX_STEPS,
+            steps: 0,
+            dropped_steps: 0,
+            accumulator: 0,
+        }
+    }
+
+    /// Length of a step in seconds, the `dt` passed to the step function.
+    pub fn step_seconds(&self) -> f64 {
+        self.step_nanoseconds as f64 / NANOSECONDS_PER_SECOND as f64
+    }
+
+    /// Add `delta_nanoseconds` of elapsed time and call `step` once for every whole step that is due,
+    /// at most `max_steps` times. Returns the number of steps run.
+    pub fn advance(&mut self, delta_nanoseconds: i64, mut step: impl FnMut(f64)) -> u32 {
+        self.accumulator += delta_nanoseconds.max(0);
+
+        let dt = self.step_seconds();
+        let mut steps = 0;
+        while self.accumulator >= self.step_nanoseconds && steps < self.max_steps {
+            step(dt);
+            self.accumulator -= self.step_nanoseconds;
+            steps += 1;
+        }
+        self.steps += steps as u64;
+
+        // Spiral of death: keep the partial step for interpolation, drop the whole ones.
+        if self.accumulator >= self.step_nanoseconds {
+            self.dropped_steps += (self.accumulator / self.step_nanoseconds) as u64;
+            self.accumulator %= self.step_nanoseconds;
+        }
+        steps
+    }
+
+    /// How far the simulation is into the next step, from 0 to 1. Render the previous state blended
+    /// towards the current state by `alpha`.
+    pub fn alpha(&self) -> f64 {
+        self.accumulator as f64 / self.step_nanoseconds as f64
+    }
+
+    /// Simulated time since the start, in seconds.
+    pub fn seconds(&self) -> f64 {
+        self.steps as f64 * self.step_seconds()
+    }
+}
@@ -0,0 +1,42 @@
+/// Events a PiCa window received during one `pull()`, in the order they happened.
+use crate::pica_keyboard::Key;
+pub use crate::pica_mouse::MouseButton;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A key went down, `repeat` is set for the key repeats of a key that is held down.
+    /// Modifiers report their left or right key when the platform can tell them apart.
+    KeyDown {
+        key: Key,
+        repeat: bool,
+    },
+    KeyUp {
+        key: Key,
+    },
+    MouseButtonDown(MouseButton),
+    MouseButtonUp(MouseButton),
+    /// Horizontal and vertical wheel notches, positive to the right and away from the user.
+    /// High-resolution wheels and touchpads report fractions of a notch.
+    MouseWheel(f32, f32),
+    /// Relative mouse motion.
+    MouseMove(i32, i32),
+    Text(char),
+    /// Files dropped onto the window, at a position in physical pixels of the client area.
+    FilesDropped {
+        paths: Vec<PathBuf>,
+        position: (i32, i32),
+    },
+    /// New client size of the window, in physical pixels.
+    Resized(i32, i32),
+    /// The window moved to a monitor with another scale factor, or the user changed it. Comes with a
+    /// `Resized` when the window keeps its logical size.
+    ScaleFactorChanged(f64),
+    FocusGained,
+    FocusLost,
+    /// A controller connected in this slot of `Window::gamepads`.
+    GamepadConnected(usize),
+    GamepadDisconnected(usize),
+    /// The user asked to close the window, `pull()` returns false from now on.
+    CloseRequested,
+}
@@ -0,0 +1,244 @@
+/// Headless backend for the PiCa window.
+///
+/// Creates no OS window. Every `pull()` advances the clock by a fixed frame time and applies
+/// the input that was queued since the previous `pull()`, so simulation loops run deterministically
+/// on machines without a display.
+use super::{
+    event::{Event, MouseButton},
+    Platform, Result, Window, WindowAttributes, WindowMode, WindowUpdate,
+};
+use crate::{pica_keyboard::Key, pica_mouse::Mouse, pica_time::Time};
+use std::{collections::VecDeque, path::PathBuf};
+
+/// Frame time of a headless window, unless set otherwise: 60 frames per second, in nanoseconds.
+pub const DEFAULT_FRAME_TIME: i64 = 1000 * 1000 * 1000 / 60;
+/// Client size of a headless window created without a size.
+pub const DEFAULT_SIZE: (i32, i32) = (800, 600);
+/// Size of the monitor a headless window goes fullscreen on.
+pub const MONITOR_SIZE: (i32, i32) = (1920, 1080);
+
+/// Input that a headless window reports as if it came from the OS.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptedInput {
+    KeyDown(Key),
+    KeyUp(Key),
+    ButtonDown(MouseButton),
+    ButtonUp(MouseButton),
+    /// Relative mouse motion, moves `Mouse::position` along unless the cursor is locked.
+    MouseMove(i32, i32),
+    /// Horizontal and vertical wheel notches, positive to the right and away from the user.
+    Wheel(f32, f32),
+    Text(String),
+    /// Files dropped onto the window at a position of the client area.
+    DropFiles(Vec<PathBuf>, (i32, i32)),
+    /// The user resizing the window to a physical size, within its size limits. Sets `WindowAttributes::resized`, unless
+    /// the window is not resizable or fullscreen.
+    Resize(i32, i32),
+    /// The window moving to a monitor with this scale factor. A window keeps its logical size, like
+    /// on Windows.
+    ScaleFactor(f64),
+    /// Keyboard focus gained or lost, only reported as an event.
+    Focus(bool),
+    Close,
+    /// Ends the input of the current frame, the remaining input is applied on the next `pull()`.
+    NextFrame,
+}
+
+#[derive(Debug)]
+pub struct Headless {
+    /// Nanoseconds the clock advances on every `pull()`.
+    pub frame_time: i64,
+    /// The clipboard of a headless window, its own and not shared with the OS.
+    pub clipboard: Option<String>,
+    ticks: i64,
+    input: VecDeque<ScriptedInput>,
+    keys_down: [bool; 256],
+    // Position and size to restore when leaving fullscreen.
+    windowed: Option<((i32, i32), (i32, i32))>,
+    // Size the window changes to on the next `pull()`, after a mode change.
+    pending_size: Option<(i32, i32)>,
+}
+
+impl Headless {
+    /// Queue input for the next `pull()`, or for a later one when separated by [`ScriptedInput::NextFrame`].
+    pub fn queue(&mut self, input: ScriptedInput) {
+        self.input.push_back(input);
+    }
+
+    /// Queue a sequence of input, see [`Headless::queue`].
+    pub fn queue_all(&mut self, input: impl IntoIterator<Item = ScriptedInput>) {
+        self.input.extend(input);
+    }
+
+    /// Number of queued inputs that have not been applied yet.
+    pub fn pending(&self) -> usize {
+        self.input.len()
+    }
+
+    pub(super) fn ticks(&self) -> i64 {
+        self.ticks
+    }
+}
+
+impl Window {
+    /// Create a window without an OS window, driven by scripted input and a fixed frame time.
+    pub fn new_headless(mut window_attributes: WindowAttributes) -> Result<Box<Self>> {
+        if window_attributes.size == (0, 0) {
+            window_attributes.size = DEFAULT_SIZE;
+        }
+        let headless = Headless {
+            frame_time: DEFAULT_FRAME_TIME,
+            clipboard: None,
+            ticks: 0,
+            input: VecDeque::new(),
+            keys_down: [false; 256],
+            windowed: None,
+            pending_size: None,
+        };
+        let mut pica_window = Box::new(Self::with_platform(
+            Platform::Headless(Box::new(headless)),
+            window_attributes,
+            Mouse::default(),
+        ));
+        // The headless clock counts nanoseconds from zero.
+        pica_window.time = Time::with_ticks(0, 1000 * 1000 * 1000);
+        pica_window.apply_window_attributes();
+        pica_window.pull();
+        Ok(pica_window)
+    }
+
+    /// The headless backend of this window, `None` when it is backed by an OS window.
+    pub fn headless(&mut self) -> Option<&mut Headless> {
+        match &mut self.platform {
+            Platform::Headless(headless) => Some(headless.as_mut()),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    fn headless_mut(&mut self) -> &mut Headless {
+        self.headless().expect("not a headless window")
+    }
+
+    pub(super) fn headless_window_pull(&mut self) {
+        let headless = self.headless_mut();
+        headless.ticks += headless.frame_time;
+        if let Some(size) = headless.pending_size.take() {
+            self.headless_resize(size);
+        }
+
+        while let Some(input) = self.headless_mut().input.pop_front() {
+            match input {
+                ScriptedInput::KeyDown(key) => {
+                    let keys_down = &mut self.headless_mut().keys_down;
+                    let repeat = keys_down[key.virtual_key()];
+                    keys_down[key.virtual_key()] = true;
+                    self.events.push(Event::KeyDown { key, repeat });
+                }
+                ScriptedInput::KeyUp(key) => {
+                    self.headless_mut().keys_down[key.virtual_key()] = false;
+                    self.events.push(Event::KeyUp { key });
+                }
+                ScriptedInput::ButtonDown(button) => self.mouse_button(button, true),
+                ScriptedInput::ButtonUp(button) => self.mouse_button(button, false),
+                ScriptedInput::MouseMove(x, y) => {
+                    self.mouse_move(x, y);
+                    self.mouse.position.0 += x;
+                    self.mouse.position.1 += y;
+                }
+                ScriptedInput::Wheel(horizontal, vertical) => {
+                    self.mouse_wheel(horizontal, vertical)
+                }
+                ScriptedInput::Text(text) => text.chars().for_each(|c| self.push_text(c)),
+                ScriptedInput::DropFiles(paths, position) => self.drop_files(paths, position),
+                ScriptedInput::Resize(width, height) => {
+                    if self.window_attributes.resizable
+                        && self.window_attributes.mode == WindowMode::Windowed
+                    {
+                        self.headless_resize(self.constrain_size((width, height)));
+                    }
+                }
+                ScriptedInput::ScaleFactor(scale_factor) => {
+                    let (width, height) = self.logical_size();
+                    self.set_scale_factor(scale_factor);
+                    if self.window_attributes.mode == WindowMode::Windowed {
+                        self.headless_resize((
+                            (width * scale_factor).round() as i32,
+                            (height * scale_factor).round() as i32,
+                        ));
+                    }
+                }
+                ScriptedInput::Focus(focused) => self.events.push(match focused {
+                    true => Event::FocusGained,
+                    false => Event::FocusLost,
+                }),
+                ScriptedInput::Close => {
+                    self.quit = true;
+                    self.events.push(Event::CloseRequested);
+                }
+                ScriptedInput::NextFrame => break,
+            }
+        }
+    }
+
+    pub(super) fn headless_clipboard_text(&mut self) -> Option<String> {
+        self.headless_mut().clipboard.clone()
+    }
+
+    pub(super) fn headless_set_clipboard_text(&mut self, text: &str) -> Result<()> {
+        self.headless_mut().clipboard = Some(text.to_owned());
+        Ok(())
+    }
+
+    fn headless_resize(&mut self, size: (i32, i32)) {
+        if size != self.window_attributes.size {
+            self.window_attributes.size = size;
+            self.window_attributes.resized = true;
+            self.events.push(Event::Resized(size.0, size.1));
+        }
+    }
+
+    // Headless windows keep their title, icon and cursor in the attributes, only the size changes.
+    pub(super) fn headless_update(&mut self, update: WindowUpdate) {
+        if update != WindowUpdate::Mode {
+            return;
+        }
+        let position = self.window_attributes.position;
+        let size = self.window_attributes.size;
+        let mode = self.window_attributes.mode;
+        let headless = self.headless_mut();
+        match mode {
+            WindowMode::Windowed => {
+                if let Some((position, size)) = headless.windowed.take() {
+                    headless.pending_size = Some(size);
+                    self.window_attributes.position = position;
+                }
+            }
+            WindowMode::BorderlessFullscreen | WindowMode::Fullscreen => {
+                if headless.windowed.is_none() {
+                    headless.windowed = Some((position, size));
+                }
+                headless.pending_size = Some(MONITOR_SIZE);
+                self.window_attributes.position = (0, 0);
+            }
+        }
+    }
+
+    pub(super) fn headless_keyboard_pull(&mut self) {
+        let mut keys_down = self.headless_mut().keys_down;
+        // A left or right modifier also holds down the generic one
+        for (generic, left, right) in [
+            (Key::Shift, Key::LeftShift, Key::RightShift),
+            (Key::Control, Key::LeftControl, Key::RightControl),
+            (Key::Alt, Key::LeftAlt, Key::RightAlt),
+        ] {
+            keys_down[generic.virtual_key()] |=
+                keys_down[left.virtual_key()] || keys_down[right.virtual_key()];
+        }
+        self.mouse_buttons_to_keys(&mut keys_down);
+
+        for (key, down) in self.keys.iter_mut().zip(keys_down) {
+            key.update_button(down);
+        }
+    }
+}
@@ -0,0 +1,631 @@
+/// Module for creating and managing a PiCa window
+use self::event::Event;
+use crate::{
+    pica_gamepad::Gamepads,
+    pica_keyboard::{Key, Modifiers},
+    pica_mouse::{Button, CursorIcon, CursorMode, Mouse, MouseButton},
+    pica_time::{self, Time},
+    renderer::Renderer,
+};
+use std::path::PathBuf;
+
+pub mod event;
+pub mod headless;
+pub mod replay;
+pub mod text;
+#[cfg(windows)]
+pub mod win32;
+#[cfg(target_os = "linux")]
+pub mod x11;
+
+/// Wrapper type around [`Error`]
+pub type Result<T> = std::result::Result<T, crate::error::Error>;
+
+const MAX_KEYS: usize = 256;
+// Virtual-key codes of `Window::keys`, prefer `Window::key` with a `Key`.
+pub const ALT: usize = 0x12;
+pub const CTR: usize = 0x11;
+pub const SHIFT: usize = 0x10;
+pub const SPACE: usize = 0x20;
+
+/// How a window covers the screen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WindowMode {
+    /// A window with decorations, at its own position and size.
+    #[default]
+    Windowed,
+    /// A window without decorations that covers the monitor it is on.
+    BorderlessFullscreen,
+    /// Covers the monitor and stays on top of other windows. The window manager may hand the display
+    /// over to the window, which avoids compositing.
+    Fullscreen,
+}
+
+/// A window icon of RGBA8 pixels, row by row from the top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Icon {
+    pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self> {
+        if width == 0 || height == 0 || rgba.len() != 4 * width as usize * height as usize {
+            return Err(crate::error::Error::Window(format!(
+                "Icon of {}x{} pixels needs {} bytes of RGBA, got {}.",
+                width,
+                height,
+                4 * width as usize * height as usize,
+                rgba.len()
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            rgba,
+        })
+    }
+}
+
+/// Window Attributes for creating a new PiCa window.
+///
+/// Change them on an open window with the setters of [`Window`], which apply them to the OS window.
+///
+/// Sizes come in two units. Physical pixels are the pixels of the screen, what renderers draw and
+/// the mouse position is measured in. Logical pixels are physical pixels divided by
+/// [`Window::scale_factor`], so a window of the same logical size looks the same size on any monitor.
+#[derive(Debug)]
+pub struct WindowAttributes {
+    pub title: String,
+    /// Position of the client area on the screen, in physical pixels.
+    pub position: (i32, i32),
+    /// Client size in physical pixels. The size a window is created with is in logical pixels, it
+    /// opens at that size times the scale factor of its monitor.
+    pub size: (i32, i32),
+    pub resized: bool,
+    /// Most frames per second `pull()` lets through, it sleeps until the next frame is due.
+    pub frame_rate_limit: Option<u32>,
+    pub mode: WindowMode,
+    /// Whether the user can resize the window by its frame.
+    pub resizable: bool,
+    /// Smallest client size the user can resize the window to, in logical pixels.
+    pub min_size: Option<(i32, i32)>,
+    /// Largest client size the user can resize the window to, in logical pixels.
+    pub max_size: Option<(i32, i32)>,
+    pub icon: Option<Icon>,
+}
+
+impl Default for WindowAttributes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowAttributes {
+    /// Create default window attributes
+    pub fn new() -> Self {
+        Self {
+            title: "PiCa Window".to_owned(),
+            position: (0, 0),
+            size: (0, 0),
+            resized: false,
+            frame_rate_limit: None,
+            mode: WindowMode::Windowed,
+            resizable: true,
+            min_size: None,
+            max_size: None,
+            icon: None,
+        }
+    }
+
+    /// Set title for new PiCa window.
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    /// Set position for new PiCa window.
+    pub fn with_position(mut self, x_pos: isize, y_pos: isize) -> Self {
+        self.position = (x_pos as i32, y_pos as i32);
+        self
+    }
+
+    /// Set the client size for new PiCa window, in logical pixels.
+    pub fn with_size(mut self, x_size: isize, y_size: isize) -> Self {
+        self.size = (x_size as i32, y_size as i32);
+        self
+    }
+
+    /// Limit the new PiCa window to `frames_per_second`.
+    pub fn with_frame_rate_limit(mut self, frames_per_second: u32) -> Self {
+        self.frame_rate_limit = Some(frames_per_second);
+        self
+    }
+
+    /// Open the new PiCa window windowed or fullscreen.
+    pub fn with_mode(mut self, mode: WindowMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set whether the user can resize the new PiCa window.
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Set the smallest client size the user can resize the new PiCa window to.
+    pub fn with_min_size(mut self, width: isize, height: isize) -> Self {
+        self.min_size = Some((width as i32, height as i32));
+        self
+    }
+
+    /// Set the largest client size the user can resize the new PiCa window to.
+    pub fn with_max_size(mut self, width: isize, height: isize) -> Self {
+        self.max_size = Some((width as i32, height as i32));
+        self
+    }
+
+    /// Set icon for new PiCa window.
+    pub fn with_icon(mut self, icon: Icon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+}
+
+// What changed in the window attributes or the cursor, for the platform to apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WindowUpdate {
+    Title,
+    Icon,
+    Mode,
+    Resizable,
+    SizeLimits,
+    Cursor,
+}
+
+/// The OS specific part of a PiCa window.
+#[derive(Debug)]
+pub enum Platform {
+    #[cfg(windows)]
+    Win32(win32::Win32),
+    // Boxed, so a window of one backend does not take the space of the largest.
+    #[cfg(target_os = "linux")]
+    X11(Box<x11::X11>),
+    Headless(Box<headless::Headless>),
+}
+
+#[derive(Debug)]
+pub struct Window {
+    // First, to be dropped before the OS window it presents to.
+    renderer: Option<Box<dyn Renderer>>,
+    pub platform: Platform,
+    pub window_attributes: WindowAttributes,
+    pub mouse: Mouse,
+    pub keys: [Button; 256],
+    pub gamepads: Gamepads,
+    pub time: Time,
+    /// Text typed since the previous `pull()`.
+    pub text: String,
+    /// Files dropped onto the window since the previous `pull()`.
+    pub dropped_files: Vec<PathBuf>,
+    /// Everything that happened since the previous `pull()`, in order.
+    pub events: Vec<Event>,
+    cursor_mode: CursorMode,
+    cursor_icon: CursorIcon,
+    cursor_visible: bool,
+    scale_factor: f64,
+    // When the next frame is due under the frame rate limit, in performance counter ticks.
+    next_frame_ticks: i64,
+    recorder: Option<replay::Recorder>,
+    replay: Option<replay::Replay>,
+    quit: bool,
+}
+
+unsafe impl raw_window_handle::HasRawWindowHandle for Window {
+    fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+        match &self.platform {
+            #[cfg(windows)]
+            Platform::Win32(win32) => win32.raw_window_handle(),
+            #[cfg(target_os = "linux")]
+            Platform::X11(x11) => x11.raw_window_handle(),
+            Platform::Headless(_) => panic!("A headless window has no raw window handle."),
+        }
+    }
+}
+
+impl Window {
+    // Create window with default window attributes.
+    pub fn new() -> Result<Box<Self>> {
+        let window_attributes = WindowAttributes::new();
+        Self::new_with_attributes(window_attributes)
+    }
+
+    // Create window with provided window attributes, using the native window system of the OS.
+    pub fn new_with_attributes(window_attributes: WindowAttributes) -> Result<Box<Self>> {
+        #[cfg(windows)]
+        return Self::new_win32(window_attributes);
+        #[cfg(target_os = "linux")]
+        return Self::new_x11(window_attributes);
+        #[cfg(not(any(windows, target_os = "linux")))]
+        return Err(crate::error::Error::Window(format!(
+            "No native window system on this platform, can not create {:?}",
+            window_attributes.title
+        )));
+    }
+
+    // Window state shared by all platforms, before the platform fills it in.
+    fn with_platform(
+        platform: Platform,
+        window_attributes: WindowAttributes,
+        mouse: Mouse,
+    ) -> Self {
+        // Headless windows only know scripted input
+        let gamepads = if matches!(platform, Platform::Headless(_)) {
+            Gamepads::none()
+        } else {
+            Gamepads::new()
+        };
+        Self {
+            renderer: None,
+            platform,
+            window_attributes,
+            mouse,
+            keys: [Button::default(); MAX_KEYS],
+            gamepads,
+            time: Time::new(),
+            text: String::new(),
+            dropped_files: Vec::new(),
+            events: Vec::new(),
+            cursor_mode: CursorMode::Normal,
+            cursor_icon: CursorIcon::default(),
+            cursor_visible: true,
+            scale_factor: 1.0,
+            next_frame_ticks: 0,
+            recorder: None,
+            replay: None,
+            quit: false,
+        }
+    }
+
+    pub fn pull(&mut self) -> bool {
+        self.frame_pace();
+        self.window_pull();
+        if !self.replay_pull() {
+            self.time_pull();
+            self.keyboard_pull();
+            self.mouse_pull();
+            self.gamepads.pull(&mut self.events);
+        }
+        self.record_pull();
+        !self.quit
+    }
+
+    // Wait for the next frame under the frame rate limit. Headless windows run on their own clock.
+    fn frame_pace(&mut self) {
+        let frames_per_second = match (&self.platform, self.window_attributes.frame_rate_limit) {
+            (Platform::Headless(_), _) | (_, None) | (_, Some(0)) => return,
+            (_, Some(frames_per_second)) => frames_per_second as i64,
+        };
+        let frame_ticks = pica_time::performance_frequency() / frames_per_second;
+        let now = pica_time::performance_counter();
+        if self.next_frame_ticks > now {
+            pica_time::sleep_until(self.next_frame_ticks);
+        }
+        // After a slow frame the schedule starts over, instead of rushing frames to catch up.
+        self.next_frame_ticks = self.next_frame_ticks.max(now) + frame_ticks;
+    }
+
+    fn window_pull(&mut self) {
+        self.text.clear();
+        self.dropped_files.clear();
+        self.events.clear();
+
+        self.window_attributes.resized = false;
+        self.mouse.begin_frame();
+
+        match self.platform {
+            #[cfg(windows)]
+            Platform::Win32(_) => self.win32_window_pull(),
+            #[cfg(target_os = "linux")]
+            Platform::X11(_) => self.x11_window_pull(),
+            Platform::Headless(_) => self.headless_window_pull(),
+        }
+    }
+
+    fn time_pull(&mut self) {
+        self.time.update_to(self.current_ticks());
+    }
+
+    // Now on the clock of the platform, headless windows run on their own.
+    fn current_ticks(&self) -> i64 {
+        match &self.platform {
+            Platform::Headless(headless) => headless.ticks(),
+            #[allow(unreachable_patterns)]
+            _ => pica_time::performance_counter(),
+        }
+    }
+
+    fn keyboard_pull(&mut self) {
+        match self.platform {
+            #[cfg(windows)]
+            Platform::Win32(_) => self.win32_keyboard_pull(),
+            #[cfg(target_os = "linux")]
+            Platform::X11(_) => self.x11_keyboard_pull(),
+            Platform::Headless(_) => self.headless_keyboard_pull(),
+        }
+    }
+
+    fn mouse_pull(&mut self) {
+        match self.platform {
+            #[cfg(windows)]
+            Platform::Win32(_) => self.win32_mouse_pull(),
+            #[cfg(target_os = "linux")]
+            Platform::X11(_) => self.x11_mouse_pull(),
+            // Scripted mouse motion is applied in `headless_window_pull`.
+            Platform::Headless(_) => {
+                if self.cursor_mode == CursorMode::Locked {
+                    self.mouse.position = self.window_center();
+                }
+            }
+        }
+    }
+
+    /// Present a frame with the renderer of [`Window::set_renderer`], at the size of the window.
+    /// Without a renderer nothing happens.
+    pub fn push(&mut self) -> Result<()> {
+        let mut renderer = match self.renderer.take() {
+            Some(renderer) => renderer,
+            None => return Ok(()),
+        };
+        renderer.resize_to_window(self);
+        let result = renderer.present();
+        self.renderer = Some(renderer);
+        result
+    }
+
+    /// Attach the renderer that [`Window::push`] presents with, replacing the previous one.
+    pub fn set_renderer(&mut self, renderer: Box<dyn Renderer>) {
+        self.renderer = Some(renderer);
+    }
+
+    /// The renderer of [`Window::set_renderer`], to create meshes and submit draws with.
+    pub fn renderer(&mut self) -> Option<&mut dyn Renderer> {
+        match &mut self.renderer {
+            Some(renderer) => Some(renderer.as_mut()),
+            None => None,
+        }
+    }
+
+    /// Detach the renderer, to drop it before the window or to use it elsewhere.
+    pub fn take_renderer(&mut self) -> Option<Box<dyn Renderer>> {
+        self.renderer.take()
+    }
+
+    /// State of `key` in this frame.
+    pub fn key(&self, key: Key) -> Button {
+        self.keys[key.virtual_key()]
+    }
+
+    /// Which modifier keys are held down in this frame.
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers {
+            shift: self.key(Key::Shift).down,
+            control: self.key(Key::Control).down,
+            alt: self.key(Key::Alt).down,
+            logo: self.key(Key::LeftSuper).down || self.key(Key::RightSuper).down,
+        }
+    }
+
+    pub fn cursor_mode(&self) -> CursorMode {
+        self.cursor_mode
+    }
+
+    /// Hide and lock the cursor for camera control, or release it again. Applied on the next `pull()`.
+    pub fn set_cursor_mode(&mut self, cursor_mode: CursorMode) {
+        self.cursor_mode = cursor_mode;
+    }
+
+    /// Physical pixels per logical pixel of the monitor the window is on, 1.0 at 96 DPI. Reported
+    /// with `Event::ScaleFactorChanged` when it changes.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Client size in logical pixels.
+    pub fn logical_size(&self) -> (f64, f64) {
+        let (width, height) = self.window_attributes.size;
+        (
+            width as f64 / self.scale_factor,
+            height as f64 / self.scale_factor,
+        )
+    }
+
+    // A size in logical pixels in physical pixels, rounded to the nearest pixel.
+    fn to_physical(&self, logical_size: (i32, i32)) -> (i32, i32) {
+        (
+            (logical_size.0 as f64 * self.scale_factor).round() as i32,
+            (logical_size.1 as f64 * self.scale_factor).round() as i32,
+        )
+    }
+
+    // The window moved to a monitor with another scale factor.
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        if scale_factor != self.scale_factor {
+            self.scale_factor = scale_factor;
+            self.events.push(Event::ScaleFactorChanged(scale_factor));
+        }
+    }
+
+    /// Text on the system clipboard, `None` when it holds no text.
+    pub fn clipboard_text(&mut self) -> Option<String> {
+        match self.platform {
+            #[cfg(windows)]
+            Platform::Win32(_) => self.win32_clipboard_text(),
+            #[cfg(target_os = "linux")]
+            Platform::X11(_) => self.x11_clipboard_text(),
+            Platform::Headless(_) => self.headless_clipboard_text(),
+        }
+    }
+
+    /// Put `text` on the system clipboard.
+    pub fn set_clipboard_text(&mut self, text: &str) -> Result<()> {
+        match self.platform {
+            #[cfg(windows)]
+            Platform::Win32(_) => self.win32_set_clipboard_text(text),
+            #[cfg(target_os = "linux")]
+            Platform::X11(_) => self.x11_set_clipboard_text(text),
+            Platform::Headless(_) => self.headless_set_clipboard_text(text),
+        }
+    }
+
+    pub fn cursor_icon(&self) -> CursorIcon {
+        self.cursor_icon
+    }
+
+    /// Change the shape of the cursor over the client area.
+    pub fn set_cursor_icon(&mut self, cursor_icon: CursorIcon) {
+        self.cursor_icon = cursor_icon;
+        self.update_platform(WindowUpdate::Cursor);
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Show or hide the cursor over the client area. A locked cursor is always hidden.
+    pub fn set_cursor_visible(&mut self, cursor_visible: bool) {
+        self.cursor_visible = cursor_visible;
+        self.update_platform(WindowUpdate::Cursor);
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.window_attributes.title = title.to_owned();
+        self.update_platform(WindowUpdate::Title);
+    }
+
+    /// Change the icon in the title bar and task bar, `None` for the default icon.
+    pub fn set_icon(&mut self, icon: Option<Icon>) {
+        self.window_attributes.icon = icon;
+        self.update_platform(WindowUpdate::Icon);
+    }
+
+    pub fn mode(&self) -> WindowMode {
+        self.window_attributes.mode
+    }
+
+    /// Switch between windowed and fullscreen. Going back to windowed restores the previous position
+    /// and size. The new size is reported on the next `pull()`.
+    pub fn set_mode(&mut self, mode: WindowMode) {
+        if mode != self.window_attributes.mode {
+            self.window_attributes.mode = mode;
+            self.update_platform(WindowUpdate::Mode);
+        }
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.window_attributes.resizable = resizable;
+        self.update_platform(WindowUpdate::Resizable);
+    }
+
+    /// Limit the client size the user can resize the window to, in logical pixels. `None` for no limit.
+    pub fn set_size_limits(&mut self, min_size: Option<(i32, i32)>, max_size: Option<(i32, i32)>) {
+        self.window_attributes.min_size = min_size;
+        self.window_attributes.max_size = max_size;
+        self.update_platform(WindowUpdate::SizeLimits);
+    }
+
+    // Apply the attributes the OS window was not created with, once it exists.
+    fn apply_window_attributes(&mut self) {
+        if self.window_attributes.icon.is_some() {
+            self.update_platform(WindowUpdate::Icon);
+        }
+        if !self.window_attributes.resizable {
+            self.update_platform(WindowUpdate::Resizable);
+        }
+        if self.window_attributes.min_size.is_some() || self.window_attributes.max_size.is_some() {
+            self.update_platform(WindowUpdate::SizeLimits);
+        }
+        if self.window_attributes.mode != WindowMode::Windowed {
+            self.update_platform(WindowUpdate::Mode);
+        }
+    }
+
+    fn update_platform(&mut self, update: WindowUpdate) {
+        match self.platform {
+            #[cfg(windows)]
+            Platform::Win32(_) => self.win32_update(update),
+            #[cfg(target_os = "linux")]
+            Platform::X11(_) => self.x11_update(update),
+            Platform::Headless(_) => self.headless_update(update),
+        }
+    }
+
+    // The physical client size within the size limits.
+    fn constrain_size(&self, size: (i32, i32)) -> (i32, i32) {
+        let (mut width, mut height) = size;
+        if let Some(min_size) = self.window_attributes.min_size {
+            let (min_width, min_height) = self.to_physical(min_size);
+            width = width.max(min_width);
+            height = height.max(min_height);
+        }
+        if let Some(max_size) = self.window_attributes.max_size {
+            let (max_width, max_height) = self.to_physical(max_size);
+            width = width.min(max_width);
+            height = height.min(max_height);
+        }
+        (width, height)
+    }
+
+    // Center of the client area, where a locked cursor is held.
+    fn window_center(&self) -> (i32, i32) {
+        (
+            self.window_attributes.size.0 / 2,
+            self.window_attributes.size.1 / 2,
+        )
+    }
+
+    fn mouse_button(&mut self, button: MouseButton, is_down: bool) {
+        self.mouse.button_mut(button).update_button(is_down);
+        self.events.push(match is_down {
+            true => Event::MouseButtonDown(button),
+            false => Event::MouseButtonUp(button),
+        });
+    }
+
+    fn mouse_wheel(&mut self, horizontal: f32, vertical: f32) {
+        self.mouse.scroll(horizontal, vertical);
+        self.events.push(Event::MouseWheel(horizontal, vertical));
+    }
+
+    fn mouse_move(&mut self, delta_x: i32, delta_y: i32) {
+        if (delta_x, delta_y) != (0, 0) {
+            self.mouse.delta_position.0 += delta_x;
+            self.mouse.delta_position.1 += delta_y;
+            self.events.push(Event::MouseMove(delta_x, delta_y));
+        }
+    }
+
+    // Like GetKeyboardState, the mouse buttons are part of the keyboard state.
+    fn mouse_buttons_to_keys(&self, keys_down: &mut [bool; MAX_KEYS]) {
+        for button in MouseButton::ALL {
+            keys_down[button.virtual_key()] |= self.mouse.button(button).down;
+        }
+    }
+
+    // Append a character to this frame's text input.
+    fn push_text(&mut self, character: char) {
+        self.events.push(Event::Text(character));
+        self.text.push(character);
+    }
+
+    // Add files dropped at `position` of the client area to this frame's dropped files.
+    fn drop_files(&mut self, paths: Vec<PathBuf>, position: (i32, i32)) {
+        if paths.is_empty() {
+            return;
+        }
+        self.dropped_files.extend(paths.iter().cloned());
+        self.events.push(Event::FilesDropped { paths, position });
+    }
+}
@@ -0,0 +1,593 @@
+//! Recording of the input `Window::pull` produces, and deterministic replay of it.
+//!
+//! A recording holds, for every frame, the frame time and the input state the frame saw: keys,
+//! [`Mouse`], and the input events, which include the typed text and dropped files. Replaying it
+//! feeds the same frames back to the application instead of the live input of the OS, so a session
+//! recorded on one machine runs the same on another.
+//!
+//! The window itself stays live during a replay: its size, scale factor and close button. Gamepads
+//! are not recorded and keep the state they had when the replay started.
+//!
+//! The file starts with a magic number and the clock of the recording, followed by the frames.
+//! A frame is its length in bytes, a byte of flags for the sections that follow, the frame time in
+//! ticks, and then only the sections that changed since the previous frame. Integers are LEB128
+//! varints, signed ones zigzag encoded, floats are little endian.
+use super::{event::Event, Result, Window, MAX_KEYS};
+use crate::{
+    error::Error,
+    pica_keyboard::Key,
+    pica_mouse::{Button, Mouse, MouseButton},
+};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
+
+// "PICAREC" and the format version
+const MAGIC: &[u8; 8] = b"PICAREC\x01";
+
+// Sections of a frame
+const KEYS: u8 = 1 << 0;
+const MOUSE: u8 = 1 << 1;
+const EVENTS: u8 = 1 << 2;
+const QUIT: u8 = 1 << 3;
+
+// Writes the frames of a recording.
+#[derive(Debug)]
+pub(super) struct Recorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    // State as of the previous frame, to only write what changed.
+    keys_down: [bool; MAX_KEYS],
+    mouse: Vec<u8>,
+}
+
+// Reads the frames of a recording back.
+#[derive(Debug)]
+pub(super) struct Replay {
+    path: PathBuf,
+    reader: BufReader<File>,
+    ticks: i64,
+    keys_down: [bool; MAX_KEYS],
+    mouse: Vec<u8>,
+    // Tick rate of the live clock, to return to it after the replay.
+    live_ticks_per_second: i64,
+}
+
+impl Window {
+    /// Record the input of every following `pull()` to a file at `path`, until
+    /// [`Window::stop_input_recording`] or the window closes.
+    pub fn start_input_recording<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::create(&path).map_err(|e| replay_error(&path, &e))?;
+        let mut header = Encoder::default();
+        header.bytes.extend_from_slice(MAGIC);
+        header.signed(self.time.ticks_per_second);
+        header.signed(self.time.ticks);
+
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(&header.bytes)
+            .map_err(|e| replay_error(&path, &e))?;
+        self.recorder = Some(Recorder {
+            path,
+            writer,
+            keys_down: [false; MAX_KEYS],
+            mouse: encode_mouse(&Mouse::default()),
+        });
+        Ok(())
+    }
+
+    /// Stop recording input and write out what is left of the recording.
+    pub fn stop_input_recording(&mut self) -> Result<()> {
+        match self.recorder.take() {
+            Some(mut recorder) => recorder
+                .writer
+                .flush()
+                .map_err(|e| replay_error(&recorder.path, &e)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_recording_input(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Replace the live input with the recording at `path` from the next `pull()` on. The clock
+    /// goes back to the time the recording started and runs at the recorded frame times. Live input
+    /// returns when the recording ends, and `pull()` returns false on the frame the recorded
+    /// session closed.
+    pub fn start_replay<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path).map_err(|e| replay_error(&path, &e))?;
+        let mut reader = BufReader::new(file);
+        let mut magic = [0u8; 8];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|e| replay_error(&path, &e))?;
+        if &magic != MAGIC {
+            return Err(Error::Replay(format!(
+                "{:?} is not a PiCa input recording of this version.",
+                path
+            )));
+        }
+        let mut clock = || read_signed(&mut reader).map_err(|e| replay_error(&path, &e));
+        let ticks_per_second = clock()?;
+        let start_ticks = clock()?;
+        if ticks_per_second <= 0 {
+            return Err(Error::Replay(format!("{:?} has no valid clock.", path)));
+        }
+
+        self.stop_replay();
+        let live_ticks_per_second = self.time.ticks_per_second;
+        self.time.initial_ticks = 0;
+        self.time.ticks = start_ticks;
+        self.time.ticks_per_second = ticks_per_second;
+        self.keys = [Button::default(); MAX_KEYS];
+        self.replay = Some(Replay {
+            path,
+            reader,
+            ticks: start_ticks,
+            keys_down: [false; MAX_KEYS],
+            mouse: encode_mouse(&Mouse::default()),
+            live_ticks_per_second,
+        });
+        Ok(())
+    }
+
+    /// Stop a replay, live input returns on the next `pull()`.
+    pub fn stop_replay(&mut self) {
+        if let Some(replay) = self.replay.take() {
+            // The live clock carries on from the replayed time.
+            let ticks = self.time.ticks as i128 * replay.live_ticks_per_second as i128
+                / self.time.ticks_per_second as i128;
+            self.time.ticks = ticks as i64;
+            self.time.ticks_per_second = replay.live_ticks_per_second;
+            self.time.initial_ticks = self.current_ticks() - self.time.ticks;
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    // Apply the next frame of a replay in place of the live input. Returns false without a replay,
+    // or when it ended.
+    pub(super) fn replay_pull(&mut self) -> bool {
+        let replay = match &mut self.replay {
+            Some(replay) => replay,
+            None => return false,
+        };
+        let frame = match read_frame(&mut replay.reader) {
+            Ok(Some(frame)) => Some(frame),
+            Ok(None) => {
+                log::info!("Replay of {:?} finished", replay.path);
+                self.stop_replay();
+                return false;
+            }
+            // A frame cut short, or a length past the end of the file
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => None,
+            Err(error) => {
+                log::error!("{:?}, replay stopped", replay_error(&replay.path, &error));
+                self.stop_replay();
+                return false;
+            }
+        };
+        match frame.and_then(|frame| self.apply_frame(&frame)) {
+            Some(()) => {
+                // Ending right after the last frame, the next one runs live from where it left off.
+                if let Some(replay) = &mut self.replay {
+                    if replay
+                        .reader
+                        .fill_buf()
+                        .map_or(true, |rest| rest.is_empty())
+                    {
+                        log::info!("Replay of {:?} finished", replay.path);
+                        self.stop_replay();
+                    }
+                }
+                true
+            }
+            None => {
+                if let Some(replay) = &self.replay {
+                    log::error!("{:?} is corrupt, replay stopped", replay.path);
+                }
+                self.stop_replay();
+                false
+            }
+        }
+    }
+
+    fn apply_frame(&mut self, frame: &[u8]) -> Option<()> {
+        let replay = self.replay.as_mut()?;
+        let mut decoder = Decoder { bytes: frame };
+        let flags = decoder.u8()?;
+        replay.ticks += decoder.signed()?;
+        if flags & KEYS != 0 {
+            let bits = decoder.take(MAX_KEYS / 8)?;
+            for (key, down) in replay.keys_down.iter_mut().enumerate() {
+                *down = bits[key / 8] & (1 << (key % 8)) != 0;
+            }
+        }
+        if flags & MOUSE != 0 {
+            let length = decoder.unsigned()? as usize;
+            replay.mouse = decoder.take(length)?.to_vec();
+        }
+        let mut events = Vec::new();
+        if flags & EVENTS != 0 {
+            for _ in 0..decoder.unsigned()? {
+                events.push(decoder.event()?);
+            }
+        }
+
+        // Live window events stay, live input goes.
+        self.events.retain(|event| !is_input(event));
+        self.text.clear();
+        self.dropped_files.clear();
+        for event in events {
+            match &event {
+                Event::Text(character) => self.text.push(*character),
+                Event::FilesDropped { paths, .. } => {
+                    self.dropped_files.extend(paths.iter().cloned())
+                }
+                _ => {}
+            }
+            self.events.push(event);
+        }
+        for (key, &down) in replay.keys_down.iter().enumerate() {
+            self.keys[key].update_button(down);
+        }
+        decode_mouse(&replay.mouse, &mut self.mouse)?;
+        self.time.update_to(replay.ticks);
+        if flags & QUIT != 0 && !self.quit {
+            self.quit = true;
+            self.events.push(Event::CloseRequested);
+        }
+        Some(())
+    }
+
+    // Append the input of this frame to a recording.
+    pub(super) fn record_pull(&mut self) {
+        let recorder = match &mut self.recorder {
+            Some(recorder) => recorder,
+            None => return,
+        };
+        let mut flags = 0;
+        let mut sections = Encoder::default();
+
+        let keys_down = self.keys.map(|key| key.down);
+        if keys_down != recorder.keys_down {
+            flags |= KEYS;
+            let mut bits = [0u8; MAX_KEYS / 8];
+            for (key, _) in keys_down.iter().enumerate().filter(|(_, down)| **down) {
+                bits[key / 8] |= 1 << (key % 8);
+            }
+            sections.bytes.extend_from_slice(&bits);
+            recorder.keys_down = keys_down;
+        }
+        let mouse = encode_mouse(&self.mouse);
+        if mouse != recorder.mouse {
+            flags |= MOUSE;
+            sections.unsigned(mouse.len() as u64);
+            sections.bytes.extend_from_slice(&mouse);
+            recorder.mouse = mouse;
+        }
+        let events: Vec<&Event> = self.events.iter().filter(|event| is_input(event)).collect();
+        if !events.is_empty() {
+            flags |= EVENTS;
+            sections.unsigned(events.len() as u64);
+            events.into_iter().for_each(|event| sections.event(event));
+        }
+        if self.quit {
+            flags |= QUIT;
+        }
+
+        let mut frame = Encoder::default();
+        frame.bytes.push(flags);
+        frame.signed(self.time.delta_ticks);
+        frame.bytes.extend_from_slice(&sections.bytes);
+        let mut length = Encoder::default();
+        length.unsigned(frame.bytes.len() as u64);
+
+        let written = recorder
+            .writer
+            .write_all(&length.bytes)
+            .and_then(|_| recorder.writer.write_all(&frame.bytes));
+        if let Err(error) = written {
+            log::error!(
+                "{:?}, recording stopped",
+                replay_error(&recorder.path, &error)
+            );
+            self.recorder = None;
+        }
+    }
+}
+
+// Events that come from the user rather than from the window, the ones a replay feeds back.
+fn is_input(event: &Event) -> bool {
+    !matches!(
+        event,
+        Event::Resized(..) | Event::ScaleFactorChanged(_) | Event::CloseRequested
+    )
+}
+
+fn replay_error(path: &Path, error: &std::io::Error) -> Error {
+    Error::Replay(format!(
+        "Failed to access input recording {:?}: {}",
+        path, error
+    ))
+}
+
+// The next frame of a recording, `None` at the end.
+fn read_frame(reader: &mut impl Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut first = [0u8; 1];
+    if reader.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+    let length = read_unsigned_after(reader, first[0])?;
+    // Read up to the length instead of allocating it, a corrupt one can be anything
+    let mut frame = Vec::new();
+    reader.take(length).read_to_end(&mut frame)?;
+    if frame.len() as u64 != length {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Some(frame))
+}
+
+fn read_signed(reader: &mut impl Read) -> std::io::Result<i64> {
+    let mut first = [0u8; 1];
+    reader.read_exact(&mut first)?;
+    read_unsigned_after(reader, first[0]).map(unzigzag)
+}
+
+// A varint of which the first byte was already read.
+fn read_unsigned_after(reader: &mut impl Read, first: u8) -> std::io::Result<u64> {
+    let mut value = (first & 0x7F) as u64;
+    let mut byte = first;
+    let mut shift = 7;
+    while byte & 0x80 != 0 {
+        if shift >= 64 {
+            return Err(ErrorKind::InvalidData.into());
+        }
+        let mut next = [0u8; 1];
+        reader.read_exact(&mut next)?;
+        byte = next[0];
+        value |= ((byte & 0x7F) as u64) << shift;
+        shift += 7;
+    }
+    Ok(value)
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+fn encode_mouse(mouse: &Mouse) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    let mut buttons = 0u64;
+    for (index, button) in MouseButton::ALL.into_iter().enumerate() {
+        let Button {
+            down,
+            pressed,
+            released,
+        } = mouse.button(button);
+        let bits = down as u64 | (pressed as u64) << 1 | (released as u64) << 2;
+        buttons |= bits << (3 * index);
+    }
+    encoder.unsigned(buttons);
+    for value in [
+        mouse.wheel,
+        mouse.delta_wheel,
+        mouse.horizontal_wheel,
+        mouse.delta_horizontal_wheel,
+        mouse.position.0,
+        mouse.position.1,
+        mouse.delta_position.0,
+        mouse.delta_position.1,
+    ] {
+        encoder.signed(value as i64);
+    }
+    encoder.f32(mouse.precise_delta_wheel);
+    encoder.f32(mouse.precise_delta_horizontal_wheel);
+    encoder.bytes
+}
+
+fn decode_mouse(bytes: &[u8], mouse: &mut Mouse) -> Option<()> {
+    let mut decoder = Decoder { bytes };
+    let buttons = decoder.unsigned()?;
+    for (index, button) in MouseButton::ALL.into_iter().enumerate() {
+        let bits = buttons >> (3 * index);
+        *mouse.button_mut(button) = Button {
+            down: bits & 1 != 0,
+            pressed: bits & 2 != 0,
+            released: bits & 4 != 0,
+        };
+    }
+    mouse.wheel = decoder.i32()?;
+    mouse.delta_wheel = decoder.i32()?;
+    mouse.horizontal_wheel = decoder.i32()?;
+    mouse.delta_horizontal_wheel = decoder.i32()?;
+    mouse.position = (decoder.i32()?, decoder.i32()?);
+    mouse.delta_position = (decoder.i32()?, decoder.i32()?);
+    mouse.precise_delta_wheel = decoder.f32()?;
+    mouse.precise_delta_horizontal_wheel = decoder.f32()?;
+    Some(())
+}
+
+#[derive(Default)]
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn unsigned(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    fn signed(&mut self, value: i64) {
+        self.unsigned(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn event(&mut self, event: &Event) {
+        match event {
+            Event::KeyDown { key, repeat } => {
+                self.bytes
+                    .extend_from_slice(&[0, key.virtual_key() as u8, *repeat as u8])
+            }
+            Event::KeyUp { key } => self.bytes.extend_from_slice(&[1, key.virtual_key() as u8]),
+            Event::MouseButtonDown(button) => {
+                self.bytes.extend_from_slice(&[2, button_index(*button)])
+            }
+            Event::MouseButtonUp(button) => {
+                self.bytes.extend_from_slice(&[3, button_index(*button)])
+            }
+            Event::MouseWheel(horizontal, vertical) => {
+                self.bytes.push(4);
+                self.f32(*horizontal);
+                self.f32(*vertical);
+            }
+            Event::MouseMove(x, y) => {
+                self.bytes.push(5);
+                self.signed(*x as i64);
+                self.signed(*y as i64);
+            }
+            Event::Text(character) => {
+                self.bytes.push(6);
+                self.unsigned(*character as u64);
+            }
+            Event::FilesDropped { paths, position } => {
+                self.bytes.push(7);
+                self.signed(position.0 as i64);
+                self.signed(position.1 as i64);
+                self.unsigned(paths.len() as u64);
+                for path in paths {
+                    let path = path.to_string_lossy();
+                    self.unsigned(path.len() as u64);
+                    self.bytes.extend_from_slice(path.as_bytes());
+                }
+            }
+            Event::Resized(width, height) => {
+                self.bytes.push(8);
+                self.signed(*width as i64);
+                self.signed(*height as i64);
+            }
+            Event::ScaleFactorChanged(scale_factor) => {
+                self.bytes.push(9);
+                self.f64(*scale_factor);
+            }
+            Event::FocusGained => self.bytes.push(10),
+            Event::FocusLost => self.bytes.push(11),
+            Event::GamepadConnected(slot) => {
+                self.bytes.push(12);
+                self.unsigned(*slot as u64);
+            }
+            Event::GamepadDisconnected(slot) => {
+                self.bytes.push(13);
+                self.unsigned(*slot as u64);
+            }
+            Event::CloseRequested => self.bytes.push(14),
+        }
+    }
+}
+
+// Reads what `Encoder` wrote, `None` when the bytes run out or make no sense.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        if length > self.bytes.len() {
+            return None;
+        }
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn unsigned(&mut self) -> Option<u64> {
+        let first = self.u8()?;
+        read_unsigned_after(&mut self.bytes, first).ok()
+    }
+
+    fn signed(&mut self) -> Option<i64> {
+        self.unsigned().map(unzigzag)
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.signed()?.try_into().ok()
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn key(&mut self) -> Option<Key> {
+        Key::from_virtual_key(self.u8()?)
+    }
+
+    fn button(&mut self) -> Option<MouseButton> {
+        MouseButton::ALL.get(self.u8()? as usize).copied()
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        Some(match self.u8()? {
+            0 => Event::KeyDown {
+                key: self.key()?,
+                repeat: self.u8()? != 0,
+            },
+            1 => Event::KeyUp { key: self.key()? },
+            2 => Event::MouseButtonDown(self.button()?),
+            3 => Event::MouseButtonUp(self.button()?),
+            4 => Event::MouseWheel(self.f32()?, self.f32()?),
+            5 => Event::MouseMove(self.i32()?, self.i32()?),
+            6 => Event::Text(char::from_u32(self.unsigned()? as u32)?),
+            7 => {
+                let position = (self.i32()?, self.i32()?);
+                let mut paths = Vec::new();
+                for _ in 0..self.unsigned()? {
+                    let length = self.unsigned()? as usize;
+                    let path = std::str::from_utf8(self.take(length)?).ok()?;
+                    paths.push(PathBuf::from(path));
+                }
+                Event::FilesDropped { paths, position }
+            }
+            8 => Event::Resized(self.i32()?, self.i32()?),
+            9 => Event::ScaleFactorChanged(self.f64()?),
+            10 => Event::FocusGained,
+            11 => Event::FocusLost,
+            12 => Event::GamepadConnected(self.unsigned()? as usize),
+            13 => Event::GamepadDisconnected(self.unsigned()? as usize),
+            14 => Event::CloseRequested,
+            _ => return None,
+        })
+    }
+}
+
+fn button_index(button: MouseButton) -> u8 {
+    MouseButton::ALL
+        .iter()
+        .position(|&other| other == button)
+        .unwrap() as u8
+}
@@ -0,0 +1,40 @@
+//! Text input decoding shared by the PiCa window backends.
+
+/// Assembles UTF-16 code units, as delivered one at a time by `WM_CHAR`, into characters.
+/// Unpaired surrogates decode to U+FFFD.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Utf16Decoder {
+    high_surrogate: Option<u16>,
+}
+
+impl Utf16Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one code unit, returns the characters it completes.
+    /// A high surrogate is held back until the next code unit arrives.
+    pub fn push(&mut self, unit: u16) -> impl Iterator<Item = char> {
+        let (units, count) = match (self.high_surrogate.take(), unit) {
+            (None, 0xD800..=0xDBFF) => {
+                self.high_surrogate = Some(unit);
+                ([0, 0], 0)
+            }
+            (None, _) => ([unit, 0], 1),
+            // A high surrogate following another one leaves the first unpaired.
+            (Some(high), 0xD800..=0xDBFF) => {
+                self.high_surrogate = Some(unit);
+                ([high, 0], 1)
+            }
+            (Some(high), _) => ([high, unit], 2),
+        };
+        char::decode_utf16(units.into_iter().take(count))
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    /// Decode a whole UTF-16 string, an unfinished surrogate pair at the end is dropped.
+    pub fn decode(units: &[u16]) -> String {
+        let mut decoder = Self::new();
+        units.iter().flat_map(|&unit| decoder.push(unit)).collect()
+    }
+}
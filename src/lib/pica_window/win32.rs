@@ -0,0 +1,1005 @@
+/// Win32 backend for the PiCa window
+use super::{
+    event::{Event, MouseButton},
+    text::Utf16Decoder,
+    Icon, Platform, Result, Window, WindowAttributes, WindowMode, WindowUpdate,
+};
+use crate::{
+    pica_keyboard::Key,
+    pica_mouse::{CursorIcon, CursorMode, Mouse},
+    utils::*,
+    win_error,
+};
+use std::{
+    cell::Cell,
+    ffi::{c_void, OsString},
+    mem::size_of,
+    os::windows::ffi::OsStringExt,
+    path::PathBuf,
+    ptr,
+    sync::Once,
+};
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Devices::HumanInterfaceDevice::{MOUSE_MOVE_ABSOLUTE, MOUSE_VIRTUAL_DESKTOP},
+        Foundation::{
+            GetLastError, SetLastError, BOOL, ERROR_CLASS_ALREADY_EXISTS, HANDLE, HWND, LPARAM,
+            LRESULT, POINT, RECT, WIN32_ERROR, WPARAM,
+        },
+        Graphics::Gdi::{
+            ClientToScreen, GetDC, GetMonitorInfoW, MonitorFromWindow, ReleaseDC, HDC, MONITORINFO,
+            MONITOR_DEFAULTTONEAREST,
+        },
+        System::{
+            DataExchange::{
+                CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData,
+            },
+            LibraryLoader::GetModuleHandleW,
+            Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+            SystemServices::CF_UNICODETEXT,
+            Threading::{
+                ConvertThreadToFiber, CreateFiber, DeleteFiber, GetCurrentThreadId, SwitchToFiber,
+            },
+        },
+        UI::{
+            HiDpi::{
+                AdjustWindowRectExForDpi, GetDpiForSystem, GetDpiForWindow,
+                SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+            },
+            Input::{
+                GetRawInputData,
+                KeyboardAndMouse::{GetKeyboardState, MapVirtualKeyW, VK_RSHIFT},
+                HRAWINPUT, RAWINPUT, RAWINPUTHEADER, RAWMOUSE, RID_INPUT, RIM_TYPEMOUSE,
+            },
+            Shell::{DragAcceptFiles, DragFinish, DragQueryFileW, DragQueryPoint, HDROP},
+            WindowsAndMessaging::{
+                ClipCursor, CreateIcon, CreateWindowExW, DefWindowProcW, DestroyIcon,
+                DestroyWindow, DispatchMessageW, EnumThreadWindows, GetClientRect, GetCursorPos,
+                GetSystemMetrics, GetWindowLongPtrW, GetWindowRect, KillTimer, LoadCursorW,
+                PeekMessageW, RegisterClassW, SendMessageW, SetCursor, SetTimer, SetWindowLongPtrW,
+                SetWindowPos, SetWindowTextW, TranslateMessage, CS_HREDRAW, CS_VREDRAW,
+                CW_USEDEFAULT, GWLP_USERDATA, GWLP_WNDPROC, GWL_STYLE, HCURSOR, HICON, HTCLIENT,
+                HWND_NOTOPMOST, HWND_TOPMOST, ICON_BIG, ICON_SMALL, IDC_ARROW, IDC_CROSS, IDC_HAND,
+                IDC_IBEAM, IDC_NO, IDC_SIZEALL, IDC_SIZENS, IDC_SIZEWE, IDC_WAIT,
+                MAPVK_VSC_TO_VK_EX, MINMAXINFO, MSG, PM_REMOVE, RI_MOUSE_BUTTON_4_DOWN,
+                RI_MOUSE_BUTTON_4_UP, RI_MOUSE_BUTTON_5_DOWN, RI_MOUSE_BUTTON_5_UP,
+                RI_MOUSE_HWHEEL, RI_MOUSE_LEFT_BUTTON_DOWN, RI_MOUSE_LEFT_BUTTON_UP,
+                RI_MOUSE_MIDDLE_BUTTON_DOWN, RI_MOUSE_MIDDLE_BUTTON_UP, RI_MOUSE_RIGHT_BUTTON_DOWN,
+                RI_MOUSE_RIGHT_BUTTON_UP, RI_MOUSE_WHEEL, SM_CXSCREEN, SM_CXVIRTUALSCREEN,
+                SM_CYSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+                SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER,
+                WHEEL_DELTA, WINDOW_STYLE, WM_CHAR, WM_CLOSE, WM_DESTROY, WM_DPICHANGED,
+                WM_DROPFILES, WM_ENTERMENULOOP, WM_ENTERSIZEMOVE, WM_EXITMENULOOP, WM_EXITSIZEMOVE,
+                WM_GETMINMAXINFO, WM_INPUT, WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS, WM_QUIT,
+                WM_SETCURSOR, WM_SETFOCUS, WM_SETICON, WM_SIZE, WM_SYSKEYDOWN, WM_SYSKEYUP,
+                WM_TIMER, WNDCLASSW, WS_MAXIMIZEBOX, WS_OVERLAPPEDWINDOW, WS_POPUP, WS_THICKFRAME,
+                WS_VISIBLE,
+            },
+        },
+    },
+};
+
+/// Wrapper type around [`Error`]
+use crate::error::Error;
+
+// Timer that switches back to the main fiber during modal message loops.
+const MODAL_LOOP_TIMER: usize = 1;
+// DPI of a scale factor of 1.0
+const DEFAULT_DPI: u32 = 96;
+
+thread_local! {
+    // The thread's own fiber, shared by all windows of the thread. Each window has a message fiber.
+    static MAIN_FIBER: Cell<*mut c_void> = const { Cell::new(ptr::null_mut()) };
+}
+
+// Convert the thread to a fiber, once for all windows on it.
+fn main_fiber() -> *mut c_void {
+    MAIN_FIBER.with(|main_fiber| {
+        if main_fiber.get().is_null() {
+            main_fiber.set(unsafe { ConvertThreadToFiber(ptr::null()) });
+        }
+        main_fiber.get()
+    })
+}
+
+// Opt the process into per-monitor DPI awareness, once for all windows. Without it Windows scales
+// the window contents up as a bitmap on HiDPI monitors. Fails on Windows before 10 1703, which
+// leaves the process unaware with a scale factor of 1.0.
+fn enable_dpi_awareness() {
+    static DPI_AWARENESS: Once = Once::new();
+    DPI_AWARENESS.call_once(|| unsafe {
+        SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+    });
+}
+
+#[derive(Debug)]
+pub struct Win32 {
+    main_fiber: *mut c_void,
+    message_fiber: *mut c_void,
+    utf16_decoder: Utf16Decoder,
+    focused: bool,
+    // Last position of a mouse that reports absolute positions, to turn them into motion.
+    absolute_mouse_position: Option<(i32, i32)>,
+    cursor_clipped: bool,
+    cursor: HCURSOR,
+    // Icon set with `Window::set_icon`, 0 for the default icon.
+    icon: HICON,
+    // Window rectangle to restore when leaving fullscreen.
+    windowed_rect: Option<RECT>,
+    pub win32_window_handle: HWND,
+    win32_device_context: HDC,
+}
+
+impl Win32 {
+    pub(super) fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+        let mut handle = raw_window_handle::Win32Handle::empty();
+        handle.hwnd = self.win32_window_handle.0 as *mut c_void;
+        handle.hinstance = unsafe { GetModuleHandleW(None).0 } as *mut c_void;
+        raw_window_handle::RawWindowHandle::Win32(handle)
+    }
+}
+
+impl Drop for Win32 {
+    fn drop(&mut self) {
+        let window_handle = self.win32_window_handle;
+        unsafe {
+            // Messages still sent to the window, also those of destroying it, must not reach the
+            // PiCa window that is being dropped.
+            SetWindowLongPtrW(window_handle, GWLP_USERDATA, 0);
+            if self.cursor_clipped {
+                ClipCursor(ptr::null());
+            }
+            ReleaseDC(window_handle, self.win32_device_context);
+            // Fails for a window that was already destroyed by closing it
+            DestroyWindow(window_handle);
+            if self.icon.0 != 0 {
+                DestroyIcon(self.icon);
+            }
+            if !self.message_fiber.is_null() {
+                DeleteFiber(self.message_fiber);
+            }
+        }
+    }
+}
+
+impl Window {
+    fn win32(&self) -> &Win32 {
+        match &self.platform {
+            Platform::Win32(win32) => win32,
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("not a win32 window"),
+        }
+    }
+
+    fn win32_mut(&mut self) -> &mut Win32 {
+        match &mut self.platform {
+            Platform::Win32(win32) => win32,
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("not a win32 window"),
+        }
+    }
+
+    // Create a Win32 window with provided window attributes.
+    pub(super) fn new_win32(window_attributes: WindowAttributes) -> Result<Box<Self>> {
+        let instance = unsafe { GetModuleHandleW(None) };
+        let window_class_name = "pica".to_wide();
+
+        let main_fiber = main_fiber();
+        assert!(!main_fiber.is_null());
+
+        enable_dpi_awareness();
+
+        // The window size around the requested client size, which is in logical pixels. The monitor
+        // the window opens on is not known yet, assume the DPI of the primary monitor.
+        let style = windowed_style(window_attributes.resizable);
+        let logical_size = window_attributes.size;
+        let system_dpi = unsafe { GetDpiForSystem() };
+        let window_size: (i32, i32) = if logical_size != (0, 0) {
+            outer_size(scale_size(logical_size, system_dpi), style, system_dpi)
+        } else {
+            (CW_USEDEFAULT, CW_USEDEFAULT)
+        };
+
+        let window_position: (i32, i32) = match window_attributes.position {
+            (0, 0) => (CW_USEDEFAULT, CW_USEDEFAULT),
+            _ => window_attributes.position,
+        };
+
+        let window_class = {
+            unsafe {
+                WNDCLASSW {
+                    hCursor: LoadCursorW(None, IDC_CROSS)
+                        .map_err(|e| Error::Win32Error(win_error!(e)))?,
+                    hInstance: instance,
+                    lpszClassName: PCWSTR(window_class_name),
+
+                    style: CS_HREDRAW | CS_VREDRAW,
+                    lpfnWndProc: Some(Self::wndproc),
+                    ..Default::default()
+                }
+            }
+        };
+
+        // All windows share the class, it is registered by the first one
+        if unsafe { RegisterClassW(&window_class) } == 0
+            && unsafe { GetLastError() } != ERROR_CLASS_ALREADY_EXISTS
+        {
+            return Err(Error::Window(
+                "Failed to register win32 window class.".to_owned(),
+            ));
+        }
+
+        let win32_window_handle = unsafe {
+            CreateWindowExW(
+                Default::default(),
+                PCWSTR(window_class_name),
+                PCWSTR((&window_attributes.title[..]).to_wide()),
+                style | WS_VISIBLE,
+                window_position.0,
+                window_position.1,
+                window_size.0,
+                window_size.1,
+                None,
+                None,
+                instance,
+                ptr::null(),
+            )
+        };
+        if win32_window_handle.0 == 0 {
+            println!("Failed to create window, with error code {:?}", unsafe {
+                GetLastError()
+            })
+        }
+        debug_assert!(win32_window_handle.0 != 0);
+
+        // Note Geert: Unsure if I can get a valid device context here, or shouild wait after showing the window?
+        let win32_device_context = unsafe { GetDC(win32_window_handle) };
+        if win32_device_context.0 == 0 {
+            return Err(Error::Window(
+                "Failed to get Device Context during window creation.".to_owned(),
+            ));
+        }
+
+        // Raw mouse input is registered for the thread without a target window, so WM_INPUT goes to
+        // whichever window of the thread has the keyboard focus. Only the focused window reports
+        // mouse motion and buttons, the others see none until they are focused.
+        let mouse = Mouse::new(HWND(0))?;
+
+        // Create PiCa window struct
+        let pica_window = Box::into_raw(Box::new(Self::with_platform(
+            Platform::Win32(Win32 {
+                win32_window_handle,
+                win32_device_context,
+                main_fiber,
+                message_fiber: ptr::null_mut(),
+                utf16_decoder: Utf16Decoder::new(),
+                focused: false,
+                absolute_mouse_position: None,
+                cursor_clipped: false,
+                cursor: load_cursor(CursorIcon::default()),
+                icon: HICON(0),
+                windowed_rect: None,
+            }),
+            window_attributes,
+            mouse,
+        )));
+
+        unsafe {
+            SetLastError(WIN32_ERROR(0));
+            if SetWindowLongPtrW(
+                (*pica_window).win32_mut().win32_window_handle,
+                GWLP_USERDATA,
+                pica_window as isize,
+            ) == 0
+                && GetLastError() != WIN32_ERROR(0)
+            {
+                let error = GetLastError();
+                println!(
+                    "Error settting userdata for window handle {}, error code: {:?}",
+                    (*pica_window).win32_mut().win32_window_handle.0,
+                    error
+                );
+            }
+
+            (*pica_window).win32_mut().message_fiber = CreateFiber(
+                0,
+                Some(Self::message_fiber_proc),
+                pica_window as *const c_void,
+            );
+
+            assert!(!(*pica_window).win32_mut().message_fiber.is_null());
+
+            // Note Geert: Unfortunately this pointer aliasing is undefined behavior.
+            // Should just return the raw pointer and live with undefined beh in application code...
+            // However, this wil behave as expected with current Rust compiler version, so I chose ergonomics.
+            let mut pica_window = Box::from_raw(pica_window);
+
+            // Opened on a monitor with another DPI than the primary one, size it for this one.
+            let dpi = GetDpiForWindow(win32_window_handle);
+            pica_window.scale_factor = dpi as f64 / DEFAULT_DPI as f64;
+            if logical_size != (0, 0) && dpi != system_dpi {
+                let (width, height) = outer_size(scale_size(logical_size, dpi), style, dpi);
+                SetWindowPos(
+                    win32_window_handle,
+                    None,
+                    0,
+                    0,
+                    width,
+                    height,
+                    SWP_NOACTIVATE | SWP_NOMOVE | SWP_NOZORDER,
+                );
+            }
+
+            DragAcceptFiles(win32_window_handle, true);
+
+            pica_window.apply_window_attributes();
+            pica_window.pull();
+            Ok(pica_window)
+        }
+    }
+
+    pub(super) fn win32_update(&mut self, update: WindowUpdate) {
+        let window_handle = self.win32().win32_window_handle;
+        match update {
+            WindowUpdate::Title => {
+                let title: Vec<u16> = self
+                    .window_attributes
+                    .title
+                    .encode_utf16()
+                    .chain(Some(0))
+                    .collect();
+                unsafe { SetWindowTextW(window_handle, PCWSTR(title.as_ptr())) };
+            }
+
+            WindowUpdate::Icon => {
+                let icon = match &self.window_attributes.icon {
+                    Some(icon) => create_icon(icon),
+                    None => HICON(0),
+                };
+                unsafe {
+                    for size in [ICON_SMALL, ICON_BIG] {
+                        SendMessageW(
+                            window_handle,
+                            WM_SETICON,
+                            WPARAM(size as usize),
+                            LPARAM(icon.0),
+                        );
+                    }
+                }
+                let previous = std::mem::replace(&mut self.win32_mut().icon, icon);
+                if previous.0 != 0 {
+                    unsafe { DestroyIcon(previous) };
+                }
+            }
+
+            WindowUpdate::Mode => {
+                let style = window_style(&self.window_attributes) | WS_VISIBLE;
+                let (insert_after, rect) = match self.window_attributes.mode {
+                    WindowMode::Windowed => {
+                        let rect = self.win32_mut().windowed_rect.take();
+                        (HWND_NOTOPMOST, rect)
+                    }
+                    mode => {
+                        if self.win32().windowed_rect.is_none() {
+                            let mut windowed_rect = RECT::default();
+                            unsafe { GetWindowRect(window_handle, &mut windowed_rect) };
+                            self.win32_mut().windowed_rect = Some(windowed_rect);
+                        }
+                        let mut monitor_info = MONITORINFO {
+                            cbSize: size_of::<MONITORINFO>() as u32,
+                            ..Default::default()
+                        };
+                        unsafe {
+                            let monitor =
+                                MonitorFromWindow(window_handle, MONITOR_DEFAULTTONEAREST);
+                            GetMonitorInfoW(monitor, &mut monitor_info);
+                        }
+                        let insert_after = match mode {
+                            WindowMode::Fullscreen => HWND_TOPMOST,
+                            _ => HWND_NOTOPMOST,
+                        };
+                        (insert_after, Some(monitor_info.rcMonitor))
+                    }
+                };
+                unsafe {
+                    SetWindowLongPtrW(window_handle, GWL_STYLE, style.0 as isize);
+                    match rect {
+                        Some(rect) => SetWindowPos(
+                            window_handle,
+                            insert_after,
+                            rect.left,
+                            rect.top,
+                            rect.right - rect.left,
+                            rect.bottom - rect.top,
+                            SWP_FRAMECHANGED | SWP_NOACTIVATE,
+                        ),
+                        None => SetWindowPos(
+                            window_handle,
+                            insert_after,
+                            0,
+                            0,
+                            0,
+                            0,
+                            SWP_FRAMECHANGED | SWP_NOACTIVATE | SWP_NOMOVE | SWP_NOSIZE,
+                        ),
+                    };
+                }
+            }
+
+            WindowUpdate::Resizable => {
+                if self.window_attributes.mode == WindowMode::Windowed {
+                    let style = window_style(&self.window_attributes) | WS_VISIBLE;
+                    unsafe {
+                        SetWindowLongPtrW(window_handle, GWL_STYLE, style.0 as isize);
+                        SetWindowPos(
+                            window_handle,
+                            None,
+                            0,
+                            0,
+                            0,
+                            0,
+                            SWP_FRAMECHANGED
+                                | SWP_NOACTIVATE
+                                | SWP_NOMOVE
+                                | SWP_NOSIZE
+                                | SWP_NOZORDER,
+                        );
+                    }
+                }
+            }
+
+            // Applied by WM_GETMINMAXINFO on the next resize
+            WindowUpdate::SizeLimits => {}
+
+            WindowUpdate::Cursor => {
+                self.win32_mut().cursor = load_cursor(self.cursor_icon);
+                // Windows only asks for the cursor when the mouse moves, set it now when it is over us.
+                let (x, y) = self.mouse.position;
+                let (width, height) = self.window_attributes.size;
+                if (0..width).contains(&x) && (0..height).contains(&y) {
+                    unsafe { SetCursor(self.win32_cursor()) };
+                }
+            }
+        }
+    }
+
+    pub(super) fn win32_clipboard_text(&mut self) -> Option<String> {
+        let window_handle = self.win32().win32_window_handle;
+        unsafe {
+            if !OpenClipboard(window_handle).as_bool() {
+                return None;
+            }
+            let text = GetClipboardData(CF_UNICODETEXT.0).ok().and_then(|data| {
+                let wide = GlobalLock(data.0) as *const u16;
+                if wide.is_null() {
+                    return None;
+                }
+                let length = (0..).take_while(|&i| *wide.add(i) != 0).count();
+                let text = String::from_utf16_lossy(std::slice::from_raw_parts(wide, length));
+                GlobalUnlock(data.0);
+                Some(text)
+            });
+            CloseClipboard();
+            text
+        }
+    }
+
+    pub(super) fn win32_set_clipboard_text(&mut self, text: &str) -> Result<()> {
+        let window_handle = self.win32().win32_window_handle;
+        let wide: Vec<u16> = text.encode_utf16().chain(Some(0)).collect();
+        unsafe {
+            if !OpenClipboard(window_handle).as_bool() {
+                return Err(Error::Clipboard("Failed to open the clipboard.".to_owned()));
+            }
+            EmptyClipboard();
+            let memory = GlobalAlloc(GMEM_MOVEABLE, wide.len() * size_of::<u16>());
+            let result = if memory == 0 {
+                Err(Error::Clipboard(
+                    "Failed to allocate clipboard memory.".to_owned(),
+                ))
+            } else {
+                let destination = GlobalLock(memory) as *mut u16;
+                std::ptr::copy_nonoverlapping(wide.as_ptr(), destination, wide.len());
+                GlobalUnlock(memory);
+                // The clipboard owns the memory once it is set, only free it when it is not.
+                SetClipboardData(CF_UNICODETEXT.0, HANDLE(memory))
+                    .map(|_| ())
+                    .map_err(|e| {
+                        GlobalFree(memory);
+                        Error::Win32Error(win_error!(e))
+                    })
+            };
+            CloseClipboard();
+            result
+        }
+    }
+
+    // The cursor over the client area, none while hidden or locked.
+    fn win32_cursor(&self) -> HCURSOR {
+        match self.cursor_visible && self.cursor_mode != CursorMode::Locked {
+            true => self.win32().cursor,
+            false => HCURSOR(0),
+        }
+    }
+
+    pub(super) fn win32_window_pull(&mut self) {
+        unsafe {
+            SwitchToFiber(self.win32().message_fiber as *const c_void);
+        }
+
+        let mut client_rect = RECT::default();
+        unsafe { GetClientRect(self.win32().win32_window_handle, &mut client_rect) };
+
+        self.window_attributes.size.0 = client_rect.right - client_rect.left;
+        self.window_attributes.size.1 = client_rect.bottom - client_rect.top;
+
+        let mut window_position = POINT {
+            x: client_rect.left,
+            y: client_rect.top,
+        };
+        unsafe { ClientToScreen(self.win32().win32_window_handle, &mut window_position) };
+
+        self.window_attributes.position.0 = window_position.x;
+        self.window_attributes.position.1 = window_position.y;
+    }
+
+    pub(super) fn win32_keyboard_pull(&mut self) {
+        let keyboard_state: &mut [u8; 256] = &mut [0; 256];
+        unsafe { GetKeyboardState(keyboard_state) };
+        for (key, state) in self.keys.iter_mut().zip(keyboard_state.iter()) {
+            key.update_button((state >> 7) == 1);
+        }
+    }
+
+    pub(super) fn win32_mouse_pull(&mut self) {
+        let locked = self.cursor_mode == CursorMode::Locked && self.win32().focused;
+        let center = self.window_center();
+        if locked {
+            // The clip rectangle is global and dropped on focus changes, keep setting it.
+            let x = self.window_attributes.position.0 + center.0;
+            let y = self.window_attributes.position.1 + center.1;
+            let clip_rectangle = RECT {
+                left: x,
+                top: y,
+                right: x + 1,
+                bottom: y + 1,
+            };
+            unsafe { ClipCursor(&clip_rectangle) };
+            self.win32_mut().cursor_clipped = true;
+            self.mouse.position = center;
+            return;
+        } else if self.win32().cursor_clipped {
+            unsafe { ClipCursor(ptr::null()) };
+            self.win32_mut().cursor_clipped = false;
+        }
+
+        let mut mouse_position = POINT::default();
+        unsafe {
+            GetCursorPos(&mut mouse_position);
+        }
+        mouse_position.x -= self.window_attributes.position.0;
+        mouse_position.y -= self.window_attributes.position.1;
+        self.mouse.position.0 = mouse_position.x;
+        self.mouse.position.1 = mouse_position.y;
+    }
+
+    fn win32_raw_mouse(&mut self, raw_mouse: &RAWMOUSE) {
+        if raw_mouse.usFlags as u32 & MOUSE_MOVE_ABSOLUTE != 0 {
+            // Tablets and remote desktop sessions report positions, normalized to 0..65535 over the screen.
+            let (left, top, width, height) = unsafe {
+                if raw_mouse.usFlags as u32 & MOUSE_VIRTUAL_DESKTOP != 0 {
+                    (
+                        GetSystemMetrics(SM_XVIRTUALSCREEN),
+                        GetSystemMetrics(SM_YVIRTUALSCREEN),
+                        GetSystemMetrics(SM_CXVIRTUALSCREEN),
+                        GetSystemMetrics(SM_CYVIRTUALSCREEN),
+                    )
+                } else {
+                    (
+                        0,
+                        0,
+                        GetSystemMetrics(SM_CXSCREEN),
+                        GetSystemMetrics(SM_CYSCREEN),
+                    )
+                }
+            };
+            let position = (
+                left + (raw_mouse.lLastX as i64 * width as i64 / 65535) as i32,
+                top + (raw_mouse.lLastY as i64 * height as i64 / 65535) as i32,
+            );
+            if let Some(previous) = self.win32_mut().absolute_mouse_position.replace(position) {
+                self.mouse_move(position.0 - previous.0, position.1 - previous.1);
+            }
+        } else {
+            self.win32_mut().absolute_mouse_position = None;
+            self.mouse_move(raw_mouse.lLastX, raw_mouse.lLastY);
+        }
+
+        let button_flags = unsafe { raw_mouse.Anonymous.Anonymous.usButtonFlags } as u32;
+        for (button, down_flag, up_flag) in [
+            (
+                MouseButton::Left,
+                RI_MOUSE_LEFT_BUTTON_DOWN,
+                RI_MOUSE_LEFT_BUTTON_UP,
+            ),
+            (
+                MouseButton::Right,
+                RI_MOUSE_RIGHT_BUTTON_DOWN,
+                RI_MOUSE_RIGHT_BUTTON_UP,
+            ),
+            (
+                MouseButton::Middle,
+                RI_MOUSE_MIDDLE_BUTTON_DOWN,
+                RI_MOUSE_MIDDLE_BUTTON_UP,
+            ),
+            (
+                MouseButton::X1,
+                RI_MOUSE_BUTTON_4_DOWN,
+                RI_MOUSE_BUTTON_4_UP,
+            ),
+            (
+                MouseButton::X2,
+                RI_MOUSE_BUTTON_5_DOWN,
+                RI_MOUSE_BUTTON_5_UP,
+            ),
+        ] {
+            if button_flags & down_flag != 0 {
+                self.mouse_button(button, true);
+            }
+            if button_flags & up_flag != 0 {
+                self.mouse_button(button, false);
+            }
+        }
+
+        // Wheel data is signed and counts WHEEL_DELTA per notch, high-resolution wheels send less.
+        let wheel = unsafe { raw_mouse.Anonymous.Anonymous.usButtonData } as i16 as f32
+            / WHEEL_DELTA as f32;
+        if button_flags & RI_MOUSE_WHEEL != 0 {
+            self.mouse_wheel(0.0, wheel);
+        }
+        if button_flags & RI_MOUSE_HWHEEL != 0 {
+            self.mouse_wheel(wheel, 0.0);
+        }
+    }
+
+    // Win32 message handling
+    extern "system" fn wndproc(
+        window_handle: HWND,
+        message: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        unsafe {
+            let pica_window = GetWindowLongPtrW(window_handle, GWLP_USERDATA) as *mut Self;
+            if pica_window.is_null() {
+                return DefWindowProcW(window_handle, message, wparam, lparam);
+            }
+            let pica_window = &mut *pica_window;
+            match message {
+                WM_INPUT => {
+                    let mut size: u32 = 0;
+                    GetRawInputData(
+                        HRAWINPUT(lparam.0),
+                        RID_INPUT,
+                        ptr::null_mut(),
+                        &mut size as *mut u32,
+                        size_of::<RAWINPUTHEADER>() as u32,
+                    );
+                    let mut buffer: Vec<u8> = vec![0; size as usize];
+                    if GetRawInputData(
+                        HRAWINPUT(lparam.0),
+                        RID_INPUT,
+                        buffer[..].as_mut_ptr() as *mut c_void,
+                        &mut size as *mut u32,
+                        size_of::<RAWINPUTHEADER>() as u32,
+                    ) == size
+                    {
+                        let raw_input: RAWINPUT = *(buffer.as_ptr().cast::<RAWINPUT>());
+                        if raw_input.header.dwType == RIM_TYPEMOUSE.0 {
+                            pica_window.win32_raw_mouse(&raw_input.data.mouse);
+                        }
+                    }
+
+                    LRESULT(0)
+                }
+
+                WM_CHAR => {
+                    // Characters outside the BMP arrive as two WM_CHAR messages, one per surrogate.
+                    let characters = pica_window.win32_mut().utf16_decoder.push(wparam.0 as u16);
+                    characters.for_each(|character| pica_window.push_text(character));
+                    LRESULT(0)
+                }
+
+                WM_KEYDOWN | WM_SYSKEYDOWN => {
+                    if let Some(key) = win32_key(wparam, lparam) {
+                        // Bit 30 holds the previous key state, set for key repeats.
+                        let repeat = (lparam.0 >> 30) & 1 == 1;
+                        pica_window.events.push(Event::KeyDown { key, repeat });
+                    }
+                    // Let system keys like alt+F4 through
+                    DefWindowProcW(window_handle, message, wparam, lparam)
+                }
+
+                WM_KEYUP | WM_SYSKEYUP => {
+                    if let Some(key) = win32_key(wparam, lparam) {
+                        pica_window.events.push(Event::KeyUp { key });
+                    }
+                    DefWindowProcW(window_handle, message, wparam, lparam)
+                }
+
+                WM_SETFOCUS => {
+                    pica_window.win32_mut().focused = true;
+                    pica_window.events.push(Event::FocusGained);
+                    LRESULT(0)
+                }
+
+                WM_KILLFOCUS => {
+                    pica_window.win32_mut().focused = false;
+                    pica_window.events.push(Event::FocusLost);
+                    LRESULT(0)
+                }
+
+                // Our cursor over the client area, hidden while locked
+                WM_SETCURSOR if (lparam.0 & 0xFFFF) as u32 == HTCLIENT => {
+                    SetCursor(pica_window.win32_cursor());
+                    LRESULT(1)
+                }
+
+                // Size limits of the client area, as limits of the window with its frame
+                WM_GETMINMAXINFO => {
+                    let min_max_info = &mut *(lparam.0 as *mut MINMAXINFO);
+                    let dpi = GetDpiForWindow(window_handle);
+                    let attributes = &pica_window.window_attributes;
+                    let style = window_style(attributes);
+                    if let Some(min_size) = attributes.min_size {
+                        let (width, height) = outer_size(scale_size(min_size, dpi), style, dpi);
+                        min_max_info.ptMinTrackSize = POINT {
+                            x: width,
+                            y: height,
+                        };
+                    }
+                    if let Some(max_size) = attributes.max_size {
+                        let (width, height) = outer_size(scale_size(max_size, dpi), style, dpi);
+                        min_max_info.ptMaxTrackSize = POINT {
+                            x: width,
+                            y: height,
+                        };
+                    }
+                    LRESULT(0)
+                }
+
+                // Moved to a monitor with another DPI, or the user changed the scale. Windows suggests
+                // a window rectangle that keeps the logical size, the resize reports the new size.
+                WM_DPICHANGED => {
+                    let dpi = (wparam.0 & 0xFFFF) as u32;
+                    pica_window.set_scale_factor(dpi as f64 / DEFAULT_DPI as f64);
+                    let rect = &*(lparam.0 as *const RECT);
+                    SetWindowPos(
+                        window_handle,
+                        None,
+                        rect.left,
+                        rect.top,
+                        rect.right - rect.left,
+                        rect.bottom - rect.top,
+                        SWP_NOACTIVATE | SWP_NOZORDER,
+                    );
+                    LRESULT(0)
+                }
+
+                WM_DROPFILES => {
+                    let drop = HDROP(wparam.0 as isize);
+                    let paths = (0..DragQueryFileW(drop, u32::MAX, &mut []))
+                        .map(|index| {
+                            let length = DragQueryFileW(drop, index, &mut []) as usize;
+                            let mut path = vec![0u16; length + 1];
+                            DragQueryFileW(drop, index, &mut path);
+                            PathBuf::from(OsString::from_wide(&path[..length]))
+                        })
+                        .collect();
+                    let mut position = POINT::default();
+                    DragQueryPoint(drop, &mut position);
+                    DragFinish(drop);
+                    pica_window.drop_files(paths, (position.x, position.y));
+                    LRESULT(0)
+                }
+
+                WM_CLOSE => {
+                    pica_window.events.push(Event::CloseRequested);
+                    // Destroys the window, which ends up in WM_DESTROY
+                    DefWindowProcW(window_handle, message, wparam, lparam)
+                }
+
+                WM_DESTROY => {
+                    pica_window.quit = true;
+                    println!("WM_DESTROY");
+                    LRESULT(0)
+                }
+
+                // Moving, sizing and menus run a modal message loop that does not return to our
+                // fiber, a timer brings us back to the main fiber while it runs.
+                WM_ENTERSIZEMOVE | WM_ENTERMENULOOP => {
+                    SetTimer(window_handle, MODAL_LOOP_TIMER, 1, None);
+                    DefWindowProcW(window_handle, message, wparam, lparam)
+                }
+
+                WM_EXITSIZEMOVE | WM_EXITMENULOOP => {
+                    KillTimer(window_handle, MODAL_LOOP_TIMER);
+                    DefWindowProcW(window_handle, message, wparam, lparam)
+                }
+
+                /* WM_PAINT |*/
+                WM_TIMER => {
+                    // Required to break out recursive message loops, so our main thread gets time to run!
+                    SwitchToFiber(pica_window.win32().main_fiber);
+                    LRESULT(0)
+                }
+
+                WM_SIZE => {
+                    pica_window.window_attributes.resized = true;
+                    let width = (lparam.0 & 0xFFFF) as i32;
+                    let height = ((lparam.0 >> 16) & 0xFFFF) as i32;
+                    pica_window.events.push(Event::Resized(width, height));
+                    // println!("WM_SIZE");
+                    LRESULT(0)
+                }
+
+                _ => DefWindowProcW(window_handle, message, wparam, lparam),
+            }
+        }
+    }
+
+    // Win32 message loop
+    extern "system" fn message_fiber_proc(data: *mut c_void) {
+        // data is actually a pointer to our initialized pica_window::Window struct
+        let pica_window: *mut Self = data.cast::<Self>();
+        assert!(!pica_window.is_null());
+        let pica_window: &mut Self = unsafe { pica_window.as_mut().unwrap() };
+        println!(
+            "First entry into message fiber: Main Fiber pointer: {:?}",
+            (pica_window).win32().main_fiber
+        );
+        loop {
+            unsafe {
+                let mut message = MSG::default();
+                // This window's messages first, the other PiCa windows pull their own. Then those
+                // that are not for a PiCa window, wherever they are in the queue: thread messages
+                // and WM_QUIT, which HWND(-1) matches, and those of the other windows of the
+                // thread, like the IME windows. Messages left for PiCa windows that are not pulled
+                // anymore do not hold these up.
+                let window_handle = pica_window.win32().win32_window_handle;
+                let other_windows = thread_windows()
+                    .into_iter()
+                    .filter(|&window_handle| !is_pica_window(window_handle));
+                for filter in [window_handle, HWND(-1)].into_iter().chain(other_windows) {
+                    while PeekMessageW(&mut message, filter, 0, 0, PM_REMOVE).into() {
+                        if message.message == WM_QUIT {
+                            pica_window.quit = true;
+                        }
+                        TranslateMessage(&message);
+                        DispatchMessageW(&message);
+                    }
+                }
+                SwitchToFiber(pica_window.win32().main_fiber);
+            }
+        }
+    }
+}
+
+// Whether `window_handle` is a PiCa window, of which the messages are dispatched by its own pull.
+fn is_pica_window(window_handle: HWND) -> bool {
+    let wndproc: extern "system" fn(HWND, u32, WPARAM, LPARAM) -> LRESULT = Window::wndproc;
+    window_handle.0 != 0
+        && unsafe { GetWindowLongPtrW(window_handle, GWLP_WNDPROC) } as usize == wndproc as usize
+}
+
+// The top-level windows of this thread.
+fn thread_windows() -> Vec<HWND> {
+    unsafe extern "system" fn push(window_handle: HWND, windows: LPARAM) -> BOOL {
+        (*(windows.0 as *mut Vec<HWND>)).push(window_handle);
+        true.into()
+    }
+    let mut windows = Vec::new();
+    let windows_pointer = &mut windows as *mut Vec<HWND>;
+    unsafe {
+        EnumThreadWindows(
+            GetCurrentThreadId(),
+            Some(push),
+            LPARAM(windows_pointer as isize),
+        )
+    };
+    windows
+}
+
+// Style of a window with decorations, without the frame to resize it by when not resizable.
+fn windowed_style(resizable: bool) -> WINDOW_STYLE {
+    match resizable {
+        true => WS_OVERLAPPEDWINDOW,
+        false => WS_OVERLAPPEDWINDOW & !(WS_THICKFRAME | WS_MAXIMIZEBOX),
+    }
+}
+
+fn window_style(window_attributes: &WindowAttributes) -> WINDOW_STYLE {
+    match window_attributes.mode {
+        WindowMode::Windowed => windowed_style(window_attributes.resizable),
+        WindowMode::BorderlessFullscreen | WindowMode::Fullscreen => WS_POPUP,
+    }
+}
+
+// Size of a window with `style` around a client area of `client_size`, with the frame drawn at `dpi`.
+fn outer_size(client_size: (i32, i32), style: WINDOW_STYLE, dpi: u32) -> (i32, i32) {
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: client_size.0,
+        bottom: client_size.1,
+    };
+    unsafe { AdjustWindowRectExForDpi(&mut rect, style, None, Default::default(), dpi) };
+    (rect.right - rect.left, rect.bottom - rect.top)
+}
+
+// A size in logical pixels in physical pixels at `dpi`.
+fn scale_size(logical_size: (i32, i32), dpi: u32) -> (i32, i32) {
+    (
+        (logical_size.0 as i64 * dpi as i64 / DEFAULT_DPI as i64) as i32,
+        (logical_size.1 as i64 * dpi as i64 / DEFAULT_DPI as i64) as i32,
+    )
+}
+
+fn load_cursor(cursor_icon: CursorIcon) -> HCURSOR {
+    let name = match cursor_icon {
+        CursorIcon::Arrow => IDC_ARROW,
+        CursorIcon::Crosshair => IDC_CROSS,
+        CursorIcon::Hand => IDC_HAND,
+        CursorIcon::Text => IDC_IBEAM,
+        CursorIcon::Wait => IDC_WAIT,
+        CursorIcon::ResizeHorizontal => IDC_SIZEWE,
+        CursorIcon::ResizeVertical => IDC_SIZENS,
+        CursorIcon::Move => IDC_SIZEALL,
+        CursorIcon::NotAllowed => IDC_NO,
+    };
+    unsafe { LoadCursorW(None, name) }.unwrap_or_default()
+}
+
+// An icon with alpha from RGBA pixels, 0 when Windows can not create it.
+fn create_icon(icon: &Icon) -> HICON {
+    let bgra: Vec<u8> = icon
+        .rgba
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+        .collect();
+    // The AND mask is unused with alpha, but needs to be there: rows of 1 bit per pixel, word aligned.
+    let mask = vec![0u8; ((icon.width as usize).div_ceil(16) * 2) * icon.height as usize];
+    unsafe {
+        CreateIcon(
+            None,
+            icon.width as i32,
+            icon.height as i32,
+            1,
+            32,
+            mask.as_ptr(),
+            bgra.as_ptr(),
+        )
+    }
+    .unwrap_or_default()
+}
+
+// The key of a WM_KEYDOWN/WM_KEYUP message, the left or right one for modifiers.
+fn win32_key(wparam: WPARAM, lparam: LPARAM) -> Option<Key> {
+    let scan_code = ((lparam.0 >> 16) & 0xFF) as u32;
+    let extended = (lparam.0 >> 24) & 1 == 1;
+    let key = match Key::from_virtual_key(wparam.0 as u8)? {
+        Key::Shift => {
+            match unsafe { MapVirtualKeyW(scan_code, MAPVK_VSC_TO_VK_EX) } == VK_RSHIFT.0 as u32 {
+                true => Key::RightShift,
+                false => Key::LeftShift,
+            }
+        }
+        Key::Control if extended => Key::RightControl,
+        Key::Control => Key::LeftControl,
+        Key::Alt if extended => Key::RightAlt,
+        Key::Alt => Key::LeftAlt,
+        key => key,
+    };
+    Some(key)
+}
@@ -0,0 +1,1267 @@
+/// X11 backend for the PiCa window.
+///
+/// Xlib is loaded at runtime, so no X11 development libraries are needed to build PiCa.
+/// Wayland sessions are supported through XWayland.
+use super::{
+    event::{Event, MouseButton},
+    Platform, Result, Window, WindowAttributes, WindowMode, WindowUpdate,
+};
+use crate::{
+    error::Error,
+    pica_keyboard::Key,
+    pica_mouse::{CursorIcon, CursorMode, Mouse},
+};
+use std::{
+    ffi::{c_void, CString, OsString},
+    fmt,
+    os::{
+        raw::{c_char, c_int, c_long, c_uint, c_ulong},
+        unix::ffi::OsStringExt,
+    },
+    path::PathBuf,
+    ptr,
+    time::{Duration, Instant},
+};
+use x11_dl::{keysym::*, xlib};
+
+// Side buttons and horizontal wheel, which Xlib has no constants for.
+const BUTTON_WHEEL_LEFT: c_uint = 6;
+const BUTTON_WHEEL_RIGHT: c_uint = 7;
+const BUTTON_BACK: c_uint = 8;
+const BUTTON_FORWARD: c_uint = 9;
+// Locale category from locale.h
+const LC_CTYPE: c_int = 0;
+// Version of the XDND drag-and-drop protocol we speak
+const XDND_VERSION: c_ulong = 5;
+// How long `clipboard_text` waits for the owner of the clipboard to answer.
+const CLIPBOARD_TIMEOUT: Duration = Duration::from_millis(500);
+// _NET_WM_STATE actions from the EWMH specification
+const NET_WM_STATE_REMOVE: c_long = 0;
+const NET_WM_STATE_ADD: c_long = 1;
+
+extern "C" {
+    fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char;
+}
+
+pub struct X11 {
+    xlib: xlib::Xlib,
+    pub display: *mut xlib::Display,
+    pub window: c_ulong,
+    root: c_ulong,
+    wm_delete_window: xlib::Atom,
+    // Root window property with the Xft.dpi resource, watched for scale factor changes.
+    resource_manager: xlib::Atom,
+    // Input method context for UTF-8 text, null when no input method is available.
+    input_method: xlib::XIM,
+    input_context: xlib::XIC,
+    // Win32 virtual-key codes per X11 keycode, 0 where a keycode has no virtual-key.
+    virtual_keys: [[u8; 2]; 256],
+    // Keycodes that went down while focused, to tell key repeats from presses.
+    keycodes_down: [bool; 256],
+    focused: bool,
+    pointer_position: Option<(i32, i32)>,
+    // Invisible cursor shown while the pointer is grabbed for `CursorMode::Locked`.
+    blank_cursor: xlib::Cursor,
+    pointer_grabbed: bool,
+    // Font cursors created so far, freed with the window.
+    cursors: Vec<(CursorIcon, xlib::Cursor)>,
+    // Text we own the CLIPBOARD selection with, handed to other clients on request.
+    clipboard: Option<String>,
+    xdnd: XdndAtoms,
+    // Window of the drag that is over us, 0 when there is none.
+    drag_source: c_ulong,
+    // Client area position of that drag.
+    drag_position: (i32, i32),
+}
+
+// Atoms of the XDND protocol, interned once as every pointer move of a drag sends a message.
+#[derive(Debug)]
+struct XdndAtoms {
+    enter: xlib::Atom,
+    position: xlib::Atom,
+    status: xlib::Atom,
+    leave: xlib::Atom,
+    drop: xlib::Atom,
+    finished: xlib::Atom,
+    selection: xlib::Atom,
+    action_copy: xlib::Atom,
+    uri_list: xlib::Atom,
+}
+
+impl fmt::Debug for X11 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("X11")
+            .field("display", &self.display)
+            .field("window", &self.window)
+            .field("focused", &self.focused)
+            .finish()
+    }
+}
+
+impl X11 {
+    pub(super) fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+        let mut handle = raw_window_handle::XlibHandle::empty();
+        handle.window = self.window;
+        handle.display = self.display as *mut c_void;
+        raw_window_handle::RawWindowHandle::Xlib(handle)
+    }
+
+    // The key of a keycode, the left or right one for modifiers.
+    fn key(&self, keycode: c_uint) -> Option<Key> {
+        let [virtual_key, sided_virtual_key] = self.virtual_keys[keycode as usize & 0xFF];
+        match sided_virtual_key {
+            0 => Key::from_virtual_key(virtual_key),
+            _ => Key::from_virtual_key(sided_virtual_key),
+        }
+    }
+
+    // The text a key press produces, composed by the input method when there is one.
+    fn lookup_text(&self, key_event: &mut xlib::XKeyEvent) -> String {
+        let mut buffer: Vec<u8> = vec![0; 32];
+        if self.input_context.is_null() {
+            let length = unsafe {
+                (self.xlib.XLookupString)(
+                    key_event,
+                    buffer.as_mut_ptr() as *mut c_char,
+                    buffer.len() as c_int,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                )
+            };
+            // XLookupString produces Latin-1, which maps one to one onto the first unicode code points.
+            return buffer[..length.max(0) as usize]
+                .iter()
+                .map(|&byte| byte as char)
+                .collect();
+        }
+
+        loop {
+            let mut status: c_int = 0;
+            let length = unsafe {
+                (self.xlib.Xutf8LookupString)(
+                    self.input_context,
+                    key_event,
+                    buffer.as_mut_ptr() as *mut c_char,
+                    buffer.len() as c_int,
+                    ptr::null_mut(),
+                    &mut status,
+                )
+            };
+            match status {
+                // Returns the required length, try again with a buffer that fits.
+                xlib::XBufferOverflow => buffer.resize(length as usize, 0),
+                xlib::XLookupChars | xlib::XLookupBoth => {
+                    return String::from_utf8_lossy(&buffer[..length as usize]).into_owned()
+                }
+                _ => return String::new(),
+            }
+        }
+    }
+
+    // Confine the pointer to the window and hide it, returns whether the grab succeeded.
+    fn grab_pointer(&self) -> bool {
+        let mask = xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::PointerMotionMask;
+        unsafe {
+            (self.xlib.XGrabPointer)(
+                self.display,
+                self.window,
+                xlib::True,
+                mask as c_uint,
+                xlib::GrabModeAsync,
+                xlib::GrabModeAsync,
+                self.window,
+                self.blank_cursor,
+                xlib::CurrentTime,
+            ) == xlib::GrabSuccess
+        }
+    }
+
+    fn atom(&self, name: &str) -> xlib::Atom {
+        let name = CString::new(name).unwrap();
+        unsafe { (self.xlib.XInternAtom)(self.display, name.as_ptr(), xlib::False) }
+    }
+
+    // The cursor for `cursor_icon` from the cursor font, created on first use.
+    fn font_cursor(&mut self, cursor_icon: CursorIcon) -> xlib::Cursor {
+        if let Some(&(_, cursor)) = self.cursors.iter().find(|(icon, _)| *icon == cursor_icon) {
+            return cursor;
+        }
+        let cursor =
+            unsafe { (self.xlib.XCreateFontCursor)(self.display, cursor_shape(cursor_icon)) };
+        self.cursors.push((cursor_icon, cursor));
+        cursor
+    }
+
+    fn change_property<T>(&self, property: &str, kind: xlib::Atom, format: c_int, data: &[T]) {
+        unsafe {
+            (self.xlib.XChangeProperty)(
+                self.display,
+                self.window,
+                self.atom(property),
+                kind,
+                format,
+                xlib::PropModeReplace,
+                data.as_ptr() as *const u8,
+                data.len() as c_int,
+            );
+        }
+    }
+
+    // Send a 32 bit client message with `data` to `window`.
+    fn send_client_message(&self, window: c_ulong, message_type: xlib::Atom, data: [c_long; 5]) {
+        let mut client_message: xlib::XClientMessageEvent = unsafe { std::mem::zeroed() };
+        client_message.type_ = xlib::ClientMessage;
+        client_message.window = window;
+        client_message.message_type = message_type;
+        client_message.format = 32;
+        for (index, value) in data.into_iter().enumerate() {
+            client_message.data.set_long(index, value);
+        }
+        let mut event = xlib::XEvent::from(client_message);
+        unsafe {
+            (self.xlib.XSendEvent)(self.display, window, xlib::False, 0, &mut event);
+        }
+    }
+
+    // Hand our clipboard text to a client that asked for it, or tell it there is none.
+    fn answer_selection_request(&self, request: &xlib::XSelectionRequestEvent) {
+        let targets = self.atom("TARGETS");
+        let utf8_string = self.atom("UTF8_STRING");
+        // Obsolete clients leave out the property, answer in the target itself.
+        let property = match request.property {
+            0 => request.target,
+            property => property,
+        };
+        let answered = match &self.clipboard {
+            Some(_) if request.target == targets => {
+                let supported: [c_ulong; 2] = [targets, utf8_string];
+                unsafe {
+                    (self.xlib.XChangeProperty)(
+                        self.display,
+                        request.requestor,
+                        property,
+                        xlib::XA_ATOM,
+                        32,
+                        xlib::PropModeReplace,
+                        supported.as_ptr() as *const u8,
+                        supported.len() as c_int,
+                    );
+                }
+                true
+            }
+            Some(text) if request.target == utf8_string => {
+                unsafe {
+                    (self.xlib.XChangeProperty)(
+                        self.display,
+                        request.requestor,
+                        property,
+                        utf8_string,
+                        8,
+                        xlib::PropModeReplace,
+                        text.as_ptr(),
+                        text.len() as c_int,
+                    );
+                }
+                true
+            }
+            _ => false,
+        };
+
+        let mut notify: xlib::XSelectionEvent = unsafe { std::mem::zeroed() };
+        notify.type_ = xlib::SelectionNotify;
+        notify.requestor = request.requestor;
+        notify.selection = request.selection;
+        notify.target = request.target;
+        notify.property = if answered { property } else { 0 };
+        notify.time = request.time;
+        let mut event = xlib::XEvent::from(notify);
+        unsafe {
+            (self.xlib.XSendEvent)(self.display, request.requestor, xlib::False, 0, &mut event);
+        }
+    }
+
+    // Ask the window manager to add or remove a _NET_WM_STATE of a mapped window.
+    fn change_state(&self, state: &str, add: bool) {
+        let mut client_message: xlib::XClientMessageEvent = unsafe { std::mem::zeroed() };
+        client_message.type_ = xlib::ClientMessage;
+        client_message.window = self.window;
+        client_message.message_type = self.atom("_NET_WM_STATE");
+        client_message.format = 32;
+        client_message.data.set_long(
+            0,
+            match add {
+                true => NET_WM_STATE_ADD,
+                false => NET_WM_STATE_REMOVE,
+            },
+        );
+        client_message.data.set_long(1, self.atom(state) as c_long);
+        // Source indication: a normal application
+        client_message.data.set_long(3, 1);
+        let mut event = xlib::XEvent::from(client_message);
+        unsafe {
+            (self.xlib.XSendEvent)(
+                self.display,
+                self.root,
+                xlib::False,
+                xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+                &mut event,
+            );
+        }
+    }
+
+    // Takes all pending events off the X11 event queue.
+    fn drain_events(&mut self) -> Vec<xlib::XEvent> {
+        let mut events = Vec::new();
+        unsafe {
+            while (self.xlib.XPending)(self.display) > 0 {
+                let mut event: xlib::XEvent = std::mem::zeroed();
+                (self.xlib.XNextEvent)(self.display, &mut event);
+                events.push(event);
+            }
+        }
+        events
+    }
+}
+
+impl Drop for X11 {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.input_context.is_null() {
+                (self.xlib.XDestroyIC)(self.input_context);
+            }
+            if !self.input_method.is_null() {
+                (self.xlib.XCloseIM)(self.input_method);
+            }
+            (self.xlib.XFreeCursor)(self.display, self.blank_cursor);
+            for &(_, cursor) in &self.cursors {
+                (self.xlib.XFreeCursor)(self.display, cursor);
+            }
+            (self.xlib.XDestroyWindow)(self.display, self.window);
+            (self.xlib.XCloseDisplay)(self.display);
+        }
+    }
+}
+
+impl Window {
+    fn x11(&self) -> &X11 {
+        match &self.platform {
+            Platform::X11(x11) => x11,
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("not an x11 window"),
+        }
+    }
+
+    fn x11_mut(&mut self) -> &mut X11 {
+        match &mut self.platform {
+            Platform::X11(x11) => x11,
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("not an x11 window"),
+        }
+    }
+
+    // Create an X11 window with provided window attributes.
+    pub(super) fn new_x11(window_attributes: WindowAttributes) -> Result<Box<Self>> {
+        let xlib =
+            xlib::Xlib::open().map_err(|e| Error::Window(format!("Failed to load Xlib: {}", e)))?;
+
+        let display = unsafe { (xlib.XOpenDisplay)(ptr::null()) };
+        if display.is_null() {
+            return Err(Error::Window("Failed to open X11 display.".to_owned()));
+        }
+
+        let screen = unsafe { (xlib.XDefaultScreen)(display) };
+        let root = unsafe { (xlib.XRootWindow)(display, screen) };
+
+        let resource_manager =
+            unsafe { (xlib.XInternAtom)(display, c"RESOURCE_MANAGER".as_ptr(), xlib::False) };
+        let scale_factor = unsafe { read_scale_factor(&xlib, display, root, resource_manager) };
+
+        // The requested size is the client area size in logical pixels, X11 leaves decorations to the
+        // window manager. Defaults to half the screen when no size is given.
+        let window_size: (i32, i32) = if window_attributes.size != (0, 0) {
+            (
+                (window_attributes.size.0 as f64 * scale_factor).round() as i32,
+                (window_attributes.size.1 as f64 * scale_factor).round() as i32,
+            )
+        } else {
+            unsafe {
+                (
+                    (xlib.XDisplayWidth)(display, screen) / 2,
+                    (xlib.XDisplayHeight)(display, screen) / 2,
+                )
+            }
+        };
+
+        let window = unsafe {
+            let black = (xlib.XBlackPixel)(display, screen);
+            (xlib.XCreateSimpleWindow)(
+                display,
+                root,
+                window_attributes.position.0,
+                window_attributes.position.1,
+                window_size.0.max(1) as c_uint,
+                window_size.1.max(1) as c_uint,
+                0,
+                black,
+                black,
+            )
+        };
+        if window == 0 {
+            unsafe { (xlib.XCloseDisplay)(display) };
+            return Err(Error::Window("Failed to create X11 window.".to_owned()));
+        }
+
+        let mut wm_delete_window = unsafe {
+            (xlib.XSelectInput)(
+                display,
+                window,
+                xlib::KeyPressMask
+                    | xlib::KeyReleaseMask
+                    | xlib::ButtonPressMask
+                    | xlib::ButtonReleaseMask
+                    | xlib::PointerMotionMask
+                    | xlib::StructureNotifyMask
+                    | xlib::FocusChangeMask,
+            );
+
+            // Ask the window manager to send us a message instead of killing the connection on close.
+            (xlib.XInternAtom)(display, c"WM_DELETE_WINDOW".as_ptr(), xlib::False)
+        };
+        unsafe {
+            (xlib.XSetWMProtocols)(display, window, &mut wm_delete_window, 1);
+            // Desktops change Xft.dpi on the root window when the user changes the scale.
+            (xlib.XSelectInput)(display, root, xlib::PropertyChangeMask);
+            // Report held keys as repeated presses without the releases in between.
+            (xlib.XkbSetDetectableAutoRepeat)(display, xlib::True, ptr::null_mut());
+            (xlib.XMapWindow)(display, window);
+            // Most window managers ignore the position of an unmapped window.
+            if window_attributes.position != (0, 0) {
+                (xlib.XMoveWindow)(
+                    display,
+                    window,
+                    window_attributes.position.0,
+                    window_attributes.position.1,
+                );
+            }
+            (xlib.XFlush)(display);
+        }
+
+        // A cursor from an empty 1x1 bitmap
+        let blank_cursor = unsafe {
+            let bitmap =
+                (xlib.XCreateBitmapFromData)(display, window, [0 as c_char].as_ptr(), 1, 1);
+            let mut black: xlib::XColor = std::mem::zeroed();
+            let cursor =
+                (xlib.XCreatePixmapCursor)(display, bitmap, bitmap, &mut black, &mut black, 0, 0);
+            (xlib.XFreePixmap)(display, bitmap);
+            cursor
+        };
+
+        let (input_method, input_context) = unsafe { open_input_context(&xlib, display, window) };
+
+        let intern = |name: &[u8]| unsafe {
+            (xlib.XInternAtom)(display, name.as_ptr() as *const c_char, xlib::False)
+        };
+        let xdnd = XdndAtoms {
+            enter: intern(b"XdndEnter\0"),
+            position: intern(b"XdndPosition\0"),
+            status: intern(b"XdndStatus\0"),
+            leave: intern(b"XdndLeave\0"),
+            drop: intern(b"XdndDrop\0"),
+            finished: intern(b"XdndFinished\0"),
+            selection: intern(b"XdndSelection\0"),
+            action_copy: intern(b"XdndActionCopy\0"),
+            uri_list: intern(b"text/uri-list\0"),
+        };
+
+        let mut virtual_keys = [[0u8; 2]; 256];
+        for keycode in 8..=255u8 {
+            let mut keysym = unsafe { (xlib.XKeycodeToKeysym)(display, keycode, 0) } as c_uint;
+            // Keypad keys report their navigation keysym first, use the digit instead.
+            if (XK_KP_Home..=XK_KP_Delete).contains(&keysym) {
+                keysym = unsafe { (xlib.XKeycodeToKeysym)(display, keycode, 1) } as c_uint;
+            }
+            virtual_keys[keycode as usize] = keysym_to_virtual_keys(keysym);
+        }
+
+        let x11 = X11 {
+            xlib,
+            display,
+            window,
+            root,
+            wm_delete_window,
+            resource_manager,
+            input_method,
+            input_context,
+            virtual_keys,
+            keycodes_down: [false; 256],
+            focused: false,
+            pointer_position: None,
+            blank_cursor,
+            pointer_grabbed: false,
+            cursors: Vec::new(),
+            clipboard: None,
+            xdnd,
+            drag_source: 0,
+            drag_position: (0, 0),
+        };
+        // Accept files dragged onto the window
+        x11.change_property("XdndAware", xlib::XA_ATOM, 32, &[XDND_VERSION]);
+
+        let mut pica_window = Box::new(Self::with_platform(
+            Platform::X11(Box::new(x11)),
+            window_attributes,
+            Mouse::default(),
+        ));
+        pica_window.scale_factor = scale_factor;
+        pica_window.x11_update(WindowUpdate::Title);
+        pica_window.x11_update(WindowUpdate::Cursor);
+        pica_window.apply_window_attributes();
+        pica_window.pull();
+        Ok(pica_window)
+    }
+
+    pub(super) fn x11_update(&mut self, update: WindowUpdate) {
+        match update {
+            WindowUpdate::Title => {
+                let title = self.window_attributes.title.replace('\0', "");
+                let x11 = self.x11();
+                let c_title = CString::new(title.as_str()).unwrap();
+                unsafe { (x11.xlib.XStoreName)(x11.display, x11.window, c_title.as_ptr()) };
+                // WM_NAME is Latin-1, window managers prefer the UTF-8 _NET_WM_NAME.
+                x11.change_property("_NET_WM_NAME", x11.atom("UTF8_STRING"), 8, title.as_bytes());
+            }
+
+            WindowUpdate::Icon => {
+                let x11 = self.x11();
+                match &self.window_attributes.icon {
+                    // Width, height and ARGB pixels, each in a long even where longs are 64 bits.
+                    Some(icon) => {
+                        let data: Vec<c_ulong> = [icon.width as c_ulong, icon.height as c_ulong]
+                            .into_iter()
+                            .chain(icon.rgba.chunks_exact(4).map(|pixel| {
+                                u32::from_be_bytes([pixel[3], pixel[0], pixel[1], pixel[2]])
+                                    as c_ulong
+                            }))
+                            .collect();
+                        x11.change_property("_NET_WM_ICON", xlib::XA_CARDINAL, 32, &data);
+                    }
+                    None => unsafe {
+                        (x11.xlib.XDeleteProperty)(
+                            x11.display,
+                            x11.window,
+                            x11.atom("_NET_WM_ICON"),
+                        );
+                    },
+                }
+            }
+
+            WindowUpdate::Mode => {
+                let mode = self.window_attributes.mode;
+                let x11 = self.x11();
+                // Both fullscreen modes are borderless on X11, the window manager restores the
+                // windowed geometry. Exclusive fullscreen asks the compositor to step aside.
+                x11.change_state("_NET_WM_STATE_FULLSCREEN", mode != WindowMode::Windowed);
+                let bypass_compositor: [c_ulong; 1] = [(mode == WindowMode::Fullscreen) as c_ulong];
+                x11.change_property(
+                    "_NET_WM_BYPASS_COMPOSITOR",
+                    xlib::XA_CARDINAL,
+                    32,
+                    &bypass_compositor,
+                );
+                // Size hints of a fixed size window would keep it from covering the screen.
+                self.x11_size_hints();
+            }
+
+            WindowUpdate::Resizable | WindowUpdate::SizeLimits => self.x11_size_hints(),
+
+            WindowUpdate::Cursor => {
+                let visible = self.cursor_visible;
+                let cursor_icon = self.cursor_icon;
+                let x11 = self.x11_mut();
+                let cursor = match visible {
+                    true => x11.font_cursor(cursor_icon),
+                    false => x11.blank_cursor,
+                };
+                unsafe { (x11.xlib.XDefineCursor)(x11.display, x11.window, cursor) };
+            }
+        }
+        let x11 = self.x11();
+        unsafe { (x11.xlib.XFlush)(x11.display) };
+    }
+
+    pub(super) fn x11_clipboard_text(&mut self) -> Option<String> {
+        let x11 = self.x11();
+        let clipboard = x11.atom("CLIPBOARD");
+        match unsafe { (x11.xlib.XGetSelectionOwner)(x11.display, clipboard) } {
+            0 => return None,
+            owner if owner == x11.window => return x11.clipboard.clone(),
+            _ => {}
+        }
+        let property = x11.atom("PICA_SELECTION");
+        unsafe {
+            (x11.xlib.XConvertSelection)(
+                x11.display,
+                clipboard,
+                x11.atom("UTF8_STRING"),
+                property,
+                x11.window,
+                xlib::CurrentTime,
+            );
+            (x11.xlib.XFlush)(x11.display);
+        }
+
+        // The owner answers with a SelectionNotify, wait for it and leave other events queued.
+        let deadline = Instant::now() + CLIPBOARD_TIMEOUT;
+        while Instant::now() < deadline {
+            let x11 = self.x11();
+            let mut event: xlib::XEvent = unsafe { std::mem::zeroed() };
+            if unsafe {
+                (x11.xlib.XCheckTypedWindowEvent)(
+                    x11.display,
+                    x11.window,
+                    xlib::SelectionNotify,
+                    &mut event,
+                )
+            } == xlib::False
+            {
+                std::thread::sleep(Duration::from_millis(1));
+                continue;
+            }
+            let selection = unsafe { event.selection };
+            if selection.selection != clipboard {
+                // A drop that arrived in the meantime
+                self.x11_event(&mut event);
+                continue;
+            }
+            // No property when the owner has no text
+            if selection.property == 0 {
+                return None;
+            }
+            let text =
+                unsafe { read_property(&x11.xlib, x11.display, x11.window, property, true) }?;
+            return Some(String::from_utf8_lossy(&text).into_owned());
+        }
+        None
+    }
+
+    pub(super) fn x11_set_clipboard_text(&mut self, text: &str) -> Result<()> {
+        let x11 = self.x11_mut();
+        x11.clipboard = Some(text.to_owned());
+        let clipboard = x11.atom("CLIPBOARD");
+        unsafe {
+            (x11.xlib.XSetSelectionOwner)(x11.display, clipboard, x11.window, xlib::CurrentTime);
+            if (x11.xlib.XGetSelectionOwner)(x11.display, clipboard) != x11.window {
+                x11.clipboard = None;
+                return Err(Error::Clipboard(
+                    "Failed to own the X11 clipboard.".to_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // XDND messages of a drag over the window, from https://freedesktop.org/wiki/Specifications/XDND
+    fn x11_drag_and_drop(&mut self, message: &xlib::XClientMessageEvent) {
+        let position = self.window_attributes.position;
+        let x11 = self.x11_mut();
+        let source = message.data.get_long(0) as c_ulong;
+        if message.message_type == x11.xdnd.enter {
+            x11.drag_source = source;
+        } else if message.message_type == x11.xdnd.position && source == x11.drag_source {
+            // Root coordinates packed in one long
+            let root_position = message.data.get_long(2);
+            x11.drag_position = (
+                ((root_position >> 16) & 0xFFFF) as i32 - position.0,
+                (root_position & 0xFFFF) as i32 - position.1,
+            );
+            // Accept the drop anywhere in the window, as a copy.
+            let status = [
+                x11.window as c_long,
+                1,
+                0,
+                0,
+                x11.xdnd.action_copy as c_long,
+            ];
+            x11.send_client_message(source, x11.xdnd.status, status);
+        } else if message.message_type == x11.xdnd.drop && source == x11.drag_source {
+            // The file list comes as a selection, its SelectionNotify finishes the drop.
+            unsafe {
+                (x11.xlib.XConvertSelection)(
+                    x11.display,
+                    x11.xdnd.selection,
+                    x11.xdnd.uri_list,
+                    x11.xdnd.selection,
+                    x11.window,
+                    message.data.get_long(2) as c_ulong,
+                );
+            }
+        } else if message.message_type == x11.xdnd.drop {
+            let finished = [x11.window as c_long, 0, 0, 0, 0];
+            x11.send_client_message(source, x11.xdnd.finished, finished);
+        } else if message.message_type == x11.xdnd.leave {
+            x11.drag_source = 0;
+        }
+    }
+
+    // The file list of a drop arrived, report the files and tell the source we are done.
+    fn x11_drop(&mut self, selection: &xlib::XSelectionEvent) {
+        let x11 = self.x11_mut();
+        let source = std::mem::replace(&mut x11.drag_source, 0);
+        let uri_list = match selection.property {
+            0 => None,
+            property => unsafe {
+                read_property(&x11.xlib, x11.display, x11.window, property, true)
+            },
+        };
+        let accepted = uri_list.is_some() as c_long;
+        let finished = [
+            x11.window as c_long,
+            accepted,
+            accepted * x11.xdnd.action_copy as c_long,
+            0,
+            0,
+        ];
+        x11.send_client_message(source, x11.xdnd.finished, finished);
+
+        let paths = uri_list
+            .map(|uri_list| {
+                String::from_utf8_lossy(&uri_list)
+                    .lines()
+                    .filter(|line| !line.starts_with('#'))
+                    .filter_map(file_uri_to_path)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let position = x11.drag_position;
+        self.drop_files(paths, position);
+    }
+
+    // Tell the window manager which sizes the user can resize the window to.
+    fn x11_size_hints(&mut self) {
+        let attributes = &self.window_attributes;
+        let mut hints: xlib::XSizeHints = unsafe { std::mem::zeroed() };
+        if attributes.mode == WindowMode::Windowed {
+            let (min_size, max_size) = match attributes.resizable {
+                true => (
+                    attributes.min_size.map(|size| self.to_physical(size)),
+                    attributes.max_size.map(|size| self.to_physical(size)),
+                ),
+                false => (Some(attributes.size), Some(attributes.size)),
+            };
+            if let Some((width, height)) = min_size {
+                hints.flags |= xlib::PMinSize;
+                hints.min_width = width;
+                hints.min_height = height;
+            }
+            if let Some((width, height)) = max_size {
+                hints.flags |= xlib::PMaxSize;
+                hints.max_width = width;
+                hints.max_height = height;
+            }
+        }
+        let x11 = self.x11();
+        unsafe { (x11.xlib.XSetWMNormalHints)(x11.display, x11.window, &mut hints) };
+    }
+
+    pub(super) fn x11_window_pull(&mut self) {
+        let events = self.x11_mut().drain_events();
+        for mut event in events {
+            // The input method swallows the key presses it composes text from, like dead keys.
+            let x11 = self.x11();
+            if !x11.input_context.is_null()
+                && unsafe { (x11.xlib.XFilterEvent)(&mut event, 0) } == xlib::True
+            {
+                continue;
+            }
+            self.x11_event(&mut event);
+        }
+
+        let x11 = self.x11();
+        let mut window_attributes: xlib::XWindowAttributes = unsafe { std::mem::zeroed() };
+        let mut position: (c_int, c_int) = (0, 0);
+        let mut child: c_ulong = 0;
+        unsafe {
+            (x11.xlib.XGetWindowAttributes)(x11.display, x11.window, &mut window_attributes);
+            (x11.xlib.XTranslateCoordinates)(
+                x11.display,
+                x11.window,
+                x11.root,
+                0,
+                0,
+                &mut position.0,
+                &mut position.1,
+                &mut child,
+            );
+        }
+
+        self.window_attributes.size.0 = window_attributes.width;
+        self.window_attributes.size.1 = window_attributes.height;
+        self.window_attributes.position.0 = position.0;
+        self.window_attributes.position.1 = position.1;
+    }
+
+    // X11 event handling
+    fn x11_event(&mut self, event: &mut xlib::XEvent) {
+        match event.get_type() {
+            xlib::KeyPress => {
+                let keycode = unsafe { event.key.keycode };
+                let x11 = self.x11_mut();
+                let repeat =
+                    std::mem::replace(&mut x11.keycodes_down[keycode as usize & 0xFF], true);
+                if let Some(key) = x11.key(keycode) {
+                    self.events.push(Event::KeyDown { key, repeat });
+                }
+
+                let text = self.x11().lookup_text(unsafe { &mut event.key });
+                text.chars().for_each(|character| self.push_text(character));
+            }
+
+            xlib::KeyRelease => {
+                let keycode = unsafe { event.key.keycode };
+                let x11 = self.x11_mut();
+                x11.keycodes_down[keycode as usize & 0xFF] = false;
+                if let Some(key) = x11.key(keycode) {
+                    self.events.push(Event::KeyUp { key });
+                }
+            }
+
+            xlib::ButtonPress | xlib::ButtonRelease => {
+                let is_down = event.get_type() == xlib::ButtonPress;
+                let button = match unsafe { event.button.button } {
+                    xlib::Button1 => MouseButton::Left,
+                    xlib::Button2 => MouseButton::Middle,
+                    xlib::Button3 => MouseButton::Right,
+                    BUTTON_BACK => MouseButton::X1,
+                    BUTTON_FORWARD => MouseButton::X2,
+                    // X11 reports every wheel notch as a press and release of buttons 4 to 7.
+                    xlib::Button4 if is_down => return self.mouse_wheel(0.0, 1.0),
+                    xlib::Button5 if is_down => return self.mouse_wheel(0.0, -1.0),
+                    BUTTON_WHEEL_LEFT if is_down => return self.mouse_wheel(-1.0, 0.0),
+                    BUTTON_WHEEL_RIGHT if is_down => return self.mouse_wheel(1.0, 0.0),
+                    _ => return,
+                };
+                self.mouse_button(button, is_down);
+            }
+
+            xlib::MotionNotify => {
+                let motion = unsafe { event.motion };
+                let position = (motion.x_root, motion.y_root);
+                if let Some(previous) = self.x11_mut().pointer_position.replace(position) {
+                    self.mouse_move(position.0 - previous.0, position.1 - previous.1);
+                }
+            }
+
+            xlib::ConfigureNotify => {
+                let configure = unsafe { event.configure };
+                if (configure.width, configure.height) != self.window_attributes.size {
+                    self.window_attributes.resized = true;
+                    self.events
+                        .push(Event::Resized(configure.width, configure.height));
+                }
+            }
+
+            xlib::FocusIn => {
+                let x11 = self.x11_mut();
+                x11.focused = true;
+                if !x11.input_context.is_null() {
+                    unsafe { (x11.xlib.XSetICFocus)(x11.input_context) };
+                }
+                self.events.push(Event::FocusGained);
+            }
+            xlib::FocusOut => {
+                let x11 = self.x11_mut();
+                x11.focused = false;
+                x11.pointer_position = None;
+                if !x11.input_context.is_null() {
+                    unsafe { (x11.xlib.XUnsetICFocus)(x11.input_context) };
+                }
+                // Keys released while unfocused send no release.
+                x11.keycodes_down = [false; 256];
+                self.events.push(Event::FocusLost);
+            }
+
+            xlib::ClientMessage => {
+                let message = unsafe { event.client_message };
+                if message.data.get_long(0) as xlib::Atom == self.x11().wm_delete_window {
+                    self.quit = true;
+                    self.events.push(Event::CloseRequested);
+                } else {
+                    self.x11_drag_and_drop(&message);
+                }
+            }
+
+            // Another client asks for the clipboard text we own
+            xlib::SelectionRequest => {
+                let request = unsafe { event.selection_request };
+                self.x11().answer_selection_request(&request);
+            }
+
+            // Another client took over the clipboard
+            xlib::SelectionClear => {
+                let x11 = self.x11_mut();
+                if unsafe { event.selection_clear.selection } == x11.atom("CLIPBOARD") {
+                    x11.clipboard = None;
+                }
+            }
+
+            xlib::SelectionNotify => {
+                let selection = unsafe { event.selection };
+                if selection.selection == self.x11().xdnd.selection {
+                    self.x11_drop(&selection);
+                }
+            }
+
+            xlib::PropertyNotify => {
+                let property = unsafe { event.property };
+                let x11 = self.x11();
+                if property.window == x11.root && property.atom == x11.resource_manager {
+                    let scale_factor = unsafe {
+                        read_scale_factor(&x11.xlib, x11.display, x11.root, x11.resource_manager)
+                    };
+                    self.x11_scale_factor_changed(scale_factor);
+                }
+            }
+
+            xlib::DestroyNotify => {
+                self.quit = true;
+            }
+
+            _ => {}
+        }
+    }
+
+    // Keep the logical size at the new scale factor, like Windows does.
+    fn x11_scale_factor_changed(&mut self, scale_factor: f64) {
+        if scale_factor == self.scale_factor {
+            return;
+        }
+        let (width, height) = self.logical_size();
+        self.set_scale_factor(scale_factor);
+        if self.window_attributes.mode == WindowMode::Windowed {
+            let x11 = self.x11();
+            unsafe {
+                (x11.xlib.XResizeWindow)(
+                    x11.display,
+                    x11.window,
+                    (width * scale_factor).round().max(1.0) as c_uint,
+                    (height * scale_factor).round().max(1.0) as c_uint,
+                );
+            }
+            // Limits in logical pixels are other physical sizes now.
+            self.x11_size_hints();
+        }
+    }
+
+    pub(super) fn x11_keyboard_pull(&mut self) {
+        let mut keys_down = [false; 256];
+
+        let x11 = self.x11();
+        // The keymap is global, only report keys while our window has the keyboard focus.
+        if x11.focused {
+            let mut keymap: [c_char; 32] = [0; 32];
+            unsafe { (x11.xlib.XQueryKeymap)(x11.display, keymap.as_mut_ptr()) };
+            for keycode in 0..256 {
+                if (keymap[keycode / 8] as u8 >> (keycode % 8)) & 1 == 1 {
+                    for &virtual_key in &x11.virtual_keys[keycode] {
+                        if virtual_key != 0 {
+                            keys_down[virtual_key as usize] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.mouse_buttons_to_keys(&mut keys_down);
+
+        for (key, down) in self.keys.iter_mut().zip(keys_down) {
+            key.update_button(down);
+        }
+    }
+
+    pub(super) fn x11_mouse_pull(&mut self) {
+        let locked = self.cursor_mode == CursorMode::Locked && self.x11().focused;
+        let center = self.window_center();
+        let root_center = (
+            self.window_attributes.position.0 + center.0,
+            self.window_attributes.position.1 + center.1,
+        );
+
+        let x11 = self.x11_mut();
+        if locked != x11.pointer_grabbed {
+            x11.pointer_grabbed = locked && x11.grab_pointer();
+            if !locked {
+                unsafe { (x11.xlib.XUngrabPointer)(x11.display, xlib::CurrentTime) };
+            }
+        }
+        if x11.pointer_grabbed {
+            unsafe {
+                (x11.xlib.XWarpPointer)(x11.display, 0, x11.window, 0, 0, 0, 0, center.0, center.1);
+                (x11.xlib.XFlush)(x11.display);
+            }
+            // The warp shows up as motion, measure the next motion from the center.
+            x11.pointer_position = Some(root_center);
+            self.mouse.position = center;
+            return;
+        }
+
+        let x11 = self.x11();
+        let mut root: c_ulong = 0;
+        let mut child: c_ulong = 0;
+        let mut root_position: (c_int, c_int) = (0, 0);
+        let mut window_position: (c_int, c_int) = (0, 0);
+        let mut mask: c_uint = 0;
+        unsafe {
+            (x11.xlib.XQueryPointer)(
+                x11.display,
+                x11.window,
+                &mut root,
+                &mut child,
+                &mut root_position.0,
+                &mut root_position.1,
+                &mut window_position.0,
+                &mut window_position.1,
+                &mut mask,
+            );
+        }
+        self.mouse.position.0 = window_position.0;
+        self.mouse.position.1 = window_position.1;
+    }
+}
+
+// Scale factor from the Xft.dpi resource that desktops set for HiDPI screens, 1.0 without it.
+unsafe fn read_scale_factor(
+    xlib: &xlib::Xlib,
+    display: *mut xlib::Display,
+    root: c_ulong,
+    resource_manager: xlib::Atom,
+) -> f64 {
+    let resources = match read_property(xlib, display, root, resource_manager, false) {
+        Some(resources) => resources,
+        None => return 1.0,
+    };
+    String::from_utf8_lossy(&resources)
+        .lines()
+        .filter_map(|line| line.strip_prefix("Xft.dpi:"))
+        .find_map(|dpi| dpi.trim().parse::<f64>().ok())
+        .filter(|&dpi| dpi > 0.0)
+        .map_or(1.0, |dpi| dpi / 96.0)
+}
+
+// The bytes of an 8 bit property of `window`, of any type. `delete` removes the property, which
+// tells the owner of a selection that its value was received.
+unsafe fn read_property(
+    xlib: &xlib::Xlib,
+    display: *mut xlib::Display,
+    window: c_ulong,
+    property: xlib::Atom,
+    delete: bool,
+) -> Option<Vec<u8>> {
+    let mut kind: xlib::Atom = 0;
+    let mut format: c_int = 0;
+    let mut length: c_ulong = 0;
+    let mut remaining: c_ulong = 0;
+    let mut data: *mut u8 = ptr::null_mut();
+    let status = (xlib.XGetWindowProperty)(
+        display,
+        window,
+        property,
+        0,
+        // In 32 bit units, more than any text or resource database we read
+        1 << 24,
+        delete as c_int,
+        xlib::AnyPropertyType as xlib::Atom,
+        &mut kind,
+        &mut format,
+        &mut length,
+        &mut remaining,
+        &mut data,
+    );
+    if status != xlib::Success as c_int || data.is_null() {
+        return None;
+    }
+    let bytes = match format {
+        8 => Some(std::slice::from_raw_parts(data, length as usize).to_vec()),
+        _ => None,
+    };
+    (xlib.XFree)(data as *mut c_void);
+    bytes
+}
+
+// The path of a file:// URI from a text/uri-list, `None` for other schemes.
+fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.trim().strip_prefix("file://")?;
+    // Skip the host, file URIs on the local machine have none or its name.
+    let path = &path[path.find('/')?..];
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = match (byte, tail) {
+            (b'%', [high, low, ..]) => std::str::from_utf8(&[*high, *low])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(escaped) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    Some(PathBuf::from(OsString::from_vec(bytes)))
+}
+
+// Shape from X11/cursorfont.h for a cursor icon.
+fn cursor_shape(cursor_icon: CursorIcon) -> c_uint {
+    match cursor_icon {
+        CursorIcon::Arrow => 68,             // XC_left_ptr
+        CursorIcon::Crosshair => 34,         // XC_crosshair
+        CursorIcon::Hand => 60,              // XC_hand2
+        CursorIcon::Text => 152,             // XC_xterm
+        CursorIcon::Wait => 150,             // XC_watch
+        CursorIcon::ResizeHorizontal => 108, // XC_sb_h_double_arrow
+        CursorIcon::ResizeVertical => 116,   // XC_sb_v_double_arrow
+        CursorIcon::Move => 52,              // XC_fleur
+        CursorIcon::NotAllowed => 24,        // XC_circle
+    }
+}
+
+// Open an input method and a context for `window`, for text input in the user's locale.
+// Both are null when no input method is available, text input then falls back to Latin-1.
+unsafe fn open_input_context(
+    xlib: &xlib::Xlib,
+    display: *mut xlib::Display,
+    window: c_ulong,
+) -> (xlib::XIM, xlib::XIC) {
+    // Input methods only produce UTF-8 with the character type of the environment's locale.
+    if setlocale(LC_CTYPE, c"".as_ptr()).is_null() {
+        return (ptr::null_mut(), ptr::null_mut());
+    }
+    (xlib.XSetLocaleModifiers)(c"".as_ptr());
+
+    let input_method = (xlib.XOpenIM)(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+    if input_method.is_null() {
+        return (ptr::null_mut(), ptr::null_mut());
+    }
+
+    let input_context = (xlib.XCreateIC)(
+        input_method,
+        xlib::XNInputStyle_0.as_ptr() as *const c_char,
+        (xlib::XIMPreeditNothing | xlib::XIMStatusNothing) as c_long,
+        xlib::XNClientWindow_0.as_ptr() as *const c_char,
+        window,
+        xlib::XNFocusWindow_0.as_ptr() as *const c_char,
+        window,
+        ptr::null_mut::<c_void>(),
+    );
+    if input_context.is_null() {
+        (xlib.XCloseIM)(input_method);
+        return (ptr::null_mut(), ptr::null_mut());
+    }
+    (input_method, input_context)
+}
+
+/// Map an X11 keysym onto the Win32 virtual-key codes PiCa uses to index `Window::keys`.
+/// Modifiers map onto both the generic and the left/right specific virtual-key, like on Win32.
+#[allow(non_upper_case_globals)]
+fn keysym_to_virtual_keys(keysym: c_uint) -> [u8; 2] {
+    let virtual_key = match keysym {
+        XK_a..=XK_z => (keysym - XK_a) as u8 + b'A',
+        XK_A..=XK_Z => (keysym - XK_A) as u8 + b'A',
+        XK_0..=XK_9 => (keysym - XK_0) as u8 + b'0',
+        XK_KP_0..=XK_KP_9 => (keysym - XK_KP_0) as u8 + 0x60,
+        XK_F1..=XK_F24 => (keysym - XK_F1) as u8 + 0x70,
+
+        XK_Shift_L => return [0x10, 0xA0],
+        XK_Shift_R => return [0x10, 0xA1],
+        XK_Control_L => return [0x11, 0xA2],
+        XK_Control_R => return [0x11, 0xA3],
+        XK_Alt_L | XK_Meta_L => return [0x12, 0xA4],
+        XK_Alt_R | XK_Meta_R | XK_ISO_Level3_Shift => return [0x12, 0xA5],
+
+        XK_BackSpace => 0x08,
+        XK_Tab => 0x09,
+        XK_Return | XK_KP_Enter => 0x0D,
+        XK_Pause => 0x13,
+        XK_Caps_Lock => 0x14,
+        XK_Escape => 0x1B,
+        XK_space => 0x20,
+        XK_Page_Up => 0x21,
+        XK_Page_Down => 0x22,
+        XK_End => 0x23,
+        XK_Home => 0x24,
+        XK_Left => 0x25,
+        XK_Up => 0x26,
+        XK_Right => 0x27,
+        XK_Down => 0x28,
+        XK_Print => 0x2C,
+        XK_Insert => 0x2D,
+        XK_Delete => 0x2E,
+        XK_Super_L => 0x5B,
+        XK_Super_R => 0x5C,
+        XK_Menu => 0x5D,
+        XK_KP_Multiply => 0x6A,
+        XK_KP_Add => 0x6B,
+        XK_KP_Separator => 0x6C,
+        XK_KP_Subtract => 0x6D,
+        XK_KP_Decimal | XK_KP_Delete => 0x6E,
+        XK_KP_Divide => 0x6F,
+        XK_Num_Lock => 0x90,
+        XK_Scroll_Lock => 0x91,
+        XK_semicolon => 0xBA,
+        XK_equal => 0xBB,
+        XK_comma => 0xBC,
+        XK_minus => 0xBD,
+        XK_period => 0xBE,
+        XK_slash => 0xBF,
+        XK_grave => 0xC0,
+        XK_bracketleft => 0xDB,
+        XK_backslash => 0xDC,
+        XK_bracketright => 0xDD,
+        XK_apostrophe => 0xDE,
+        _ => 0,
+    };
+    [virtual_key, 0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::keysym_to_virtual_keys;
+    use crate::pica_keyboard::Key;
+    use x11_dl::keysym::*;
+
+    #[test]
+    fn modifier_keysyms_hold_the_generic_and_their_side() {
+        for (keysym, generic, side) in [
+            (XK_Shift_L, Key::Shift, Key::LeftShift),
+            (XK_Shift_R, Key::Shift, Key::RightShift),
+            (XK_Control_L, Key::Control, Key::LeftControl),
+            (XK_Control_R, Key::Control, Key::RightControl),
+            (XK_Alt_L, Key::Alt, Key::LeftAlt),
+            (XK_Alt_R, Key::Alt, Key::RightAlt),
+        ] {
+            assert_eq!(keysym_to_virtual_keys(keysym), [generic as u8, side as u8]);
+        }
+    }
+
+    #[test]
+    fn keysyms_map_onto_keys() {
+        for (keysym, key) in [
+            (XK_a, Key::A),
+            (XK_Z, Key::Z),
+            (XK_7, Key::Digit7),
+            (XK_KP_3, Key::Numpad3),
+            (XK_F12, Key::F12),
+            (XK_Return, Key::Enter),
+            (XK_Super_R, Key::RightSuper),
+            (XK_bracketright, Key::RightBracket),
+        ] {
+            assert_eq!(keysym_to_virtual_keys(keysym), [key as u8, 0]);
+        }
+    }
+}
@@ -2,8 +2,9 @@ use std::f32::consts::{FRAC_PI_2, PI};
 
 use glam::{Mat4, Vec3};
 
+use crate::pica_keyboard::Key;
+use crate::pica_mouse::CursorMode;
 use crate::pica_window::Window;
-use crate::pica_window::SPACE;
 
 pub const OPENGL_TO_WGPU_MATRIX: &[f32; 16] = &[
     1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.5, 1.0,
@@ -11,7 +12,7 @@ pub const OPENGL_TO_WGPU_MATRIX: &[f32; 16] = &[
 
 const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct CameraController {
     amount_left: f32,
     amount_right: f32,
@@ -55,48 +56,44 @@ impl CameraController {
         self.scroll = 0.0;
     }
 
-    #[cfg(target_arch = "x86_64")]
     pub fn update(&mut self, window: &Window) {
         self.reset();
         // Process Keyboard
-        if window.keys['W' as u8 as usize].down {
+        if window.key(Key::W).down {
             self.amount_forward = 0.1;
             println!("forward");
         }
-        if window.keys['S' as u8 as usize].down {
+        if window.key(Key::S).down {
             self.amount_backward = 0.1;
             println!("backward");
         }
-        if window.keys['D' as u8 as usize].down {
+        if window.key(Key::D).down {
             self.amount_right = 0.1;
             println!("right");
         }
-        if window.keys['A' as u8 as usize].down {
+        if window.key(Key::A).down {
             self.amount_left = 0.1;
             println!("left");
         }
-        if window.keys[SPACE].down {
+        if window.key(Key::Space).down {
             self.amount_up = 0.1;
             println!("up");
         }
-        if window.keys[CTR].down {
+        if window.key(Key::Control).down {
             self.amount_up = 0.1;
             println!("down");
         }
 
-        // Process Mouse
-        if window.mouse.left_button.down {
+        // Process Mouse, a locked cursor always looks around
+        if window.mouse.left_button.down || window.cursor_mode() == CursorMode::Locked {
             let mousex = window.mouse.delta_position.0 as f32;
             let mousey = window.mouse.delta_position.1 as f32;
-            self.rotate_horizontal = mousex as f32;
-            self.rotate_vertical = mousey as f32;
+            self.rotate_horizontal = mousex;
+            self.rotate_vertical = mousey;
         }
 
         // Process Mouse Scroll
-        // SCROLL is currently broken (values between 1.0 and 545), needs fixing
-
-        use crate::pica_window::CTR;
-        self.scroll = -(window.mouse.delta_wheel as f32);
+        self.scroll = -window.mouse.precise_delta_wheel;
         if self.scroll != 0.0 {
             println!("{:?}", self.scroll);
         }
@@ -121,7 +118,7 @@ impl Default for CameraController {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub position: Vec3,
     yaw: f32,   //rads
@@ -152,12 +149,10 @@ impl Camera {
         )
     }
 
-    // Note that this is a WIN32 specific function at this stage...
-    #[cfg(target_arch = "x86_64")]
     pub fn update_camera(&mut self, window: &mut Window) {
         self.camera_controller.update(window);
 
-        let dt = window.time.seconds;
+        let dt = window.time.seconds as f32;
 
         // Move forward/backward and left/right
         let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
@@ -214,6 +209,20 @@ impl Default for Camera {
     }
 }
 
+/// The view a renderer starts with: a camera at `position` that faces the negative z axis and looks
+/// down a little, with a vertical field of view of 45 degrees.
+pub(crate) fn default_view(position: Vec3, width: u32, height: u32) -> (Camera, Projection) {
+    let camera = Camera::new(
+        position,
+        -90.0_f32.to_radians(),
+        -20.0_f32.to_radians(),
+        0.1,
+        0.001,
+    );
+    let projection = Projection::new(width, height, 45.0_f32.to_radians(), 0.1, 100.0);
+    (camera, projection)
+}
+
 pub struct Projection {
     aspect: f32,
     fovy: f32, // rads
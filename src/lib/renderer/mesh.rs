@@ -0,0 +1,148 @@
+//! Vertices, indices and instances of meshes, the same for every render backend.
+use crate::error::Error;
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Quat, Vec3};
+use std::ops::Range;
+use wgpu::PrimitiveTopology;
+
+pub struct Instance {
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+impl Instance {
+    pub fn to_raw(&self) -> InstanceRaw {
+        InstanceRaw {
+            model: (Mat4::from_translation(self.position) * Mat4::from_quat(self.rotation))
+                .to_cols_array_2d(),
+        }
+    }
+}
+
+/// The model matrix of an [`Instance`], as uploaded to the GPU.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct InstanceRaw {
+    model: [[f32; 4]; 4],
+}
+
+impl InstanceRaw {
+    pub(crate) fn model(&self) -> Mat4 {
+        Mat4::from_cols_array_2d(&self.model)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 4],
+    pub color: [f32; 4],
+}
+
+impl Vertex {
+    /// A vertex from integer coordinates and color channels, for meshes written out by hand.
+    // Named after what it builds rather than `new`, the examples and tests call it by this name.
+    #[allow(clippy::self_named_constructors)]
+    pub fn vertex(p: [i8; 3], c: [i8; 3]) -> Vertex {
+        Vertex {
+            position: [p[0] as f32, p[1] as f32, p[2] as f32, 1.0],
+            color: [c[0] as f32, c[1] as f32, c[2] as f32, 1.0],
+        }
+    }
+}
+
+/// Indices of a mesh. 16 bit indices address up to 65536 vertices, larger meshes need 32 bit ones.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The largest index of the format, which restarts a strip.
+    pub fn restart_index(&self) -> u32 {
+        match self {
+            Indices::U16(_) => u16::MAX as u32,
+            Indices::U32(_) => u32::MAX,
+        }
+    }
+
+    /// The indices in 32 bit.
+    pub fn to_u32(&self) -> Vec<u32> {
+        match self {
+            Indices::U16(indices) => indices.iter().map(|&index| index as u32).collect(),
+            Indices::U32(indices) => indices.clone(),
+        }
+    }
+}
+
+/// Fails when `range` reaches past the indices, or the vertices without indices, or when a drawn
+/// index plus `base_vertex` fetches past the vertices. Strips restart at the largest index, which
+/// fetches no vertex.
+pub(crate) fn validate_draw(
+    topology: PrimitiveTopology,
+    vertices: usize,
+    indices: Option<&Indices>,
+    range: &Range<u32>,
+    base_vertex: i32,
+) -> crate::Result<()> {
+    let (elements, kind) = match indices {
+        Some(indices) => (indices.len(), "indices"),
+        None => (vertices, "vertices"),
+    };
+    if range.start > range.end || range.end as usize > elements {
+        return Err(Error::Render(format!(
+            "The range {:?} of a mesh is not within its {} {}",
+            range, elements, kind
+        )));
+    }
+    let indices = match indices {
+        Some(indices) => indices,
+        None if base_vertex != 0 => {
+            return Err(Error::Render(format!(
+                "A mesh without indices has the base vertex {}",
+                base_vertex
+            )));
+        }
+        None => return Ok(()),
+    };
+    let restart = match topology.is_strip() {
+        true => Some(indices.restart_index()),
+        false => None,
+    };
+    let fetched = indices.to_u32()[range.start as usize..range.end as usize]
+        .iter()
+        .filter(|&&index| Some(index) != restart)
+        .map(|&index| index as i64 + base_vertex as i64)
+        .find(|&vertex| vertex < 0 || vertex >= vertices as i64);
+    match fetched {
+        Some(vertex) => Err(Error::Render(format!(
+            "A mesh fetches the vertex {} but has {} vertices",
+            vertex, vertices
+        ))),
+        None => Ok(()),
+    }
+}
+
+impl From<Vec<u16>> for Indices {
+    fn from(indices: Vec<u16>) -> Self {
+        Indices::U16(indices)
+    }
+}
+
+impl From<Vec<u32>> for Indices {
+    fn from(indices: Vec<u32>) -> Self {
+        Indices::U32(indices)
+    }
+}
@@ -0,0 +1,78 @@
+//! The interface every PiCa render backend implements.
+//!
+//! Applications that only use [`Renderer`] can swap backends without code changes. Attach the
+//! renderer to a window with [`Window::set_renderer`], and [`Window::push`] presents its frames:
+//!
+//! ```no_run
+//! # use pica::pica_window::Window;
+//! # use pica::renderer::Vertex;
+//! # use pica::wgpu_renderer::WGPURenderer;
+//! # fn main() -> pica::Result<()> {
+//! let mut window = Window::new()?;
+//! let renderer = pollster::block_on(WGPURenderer::new(&window));
+//! window.set_renderer(Box::new(renderer));
+//!
+//! let vertices = [
+//!     Vertex::vertex([-1, -1, 0], [1, 0, 0]),
+//!     Vertex::vertex([1, -1, 0], [0, 1, 0]),
+//!     Vertex::vertex([0, 1, 0], [0, 0, 1]),
+//! ];
+//! let triangle = window.renderer().unwrap().create_mesh(&vertices, None)?;
+//! while window.pull() {
+//!     window.renderer().unwrap().draw(triangle);
+//!     window.push()?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::pica_window::Window;
+use std::fmt;
+
+pub mod camera;
+pub mod mesh;
+
+use camera::Camera;
+pub use mesh::{Indices, Instance, InstanceRaw, Vertex};
+
+/// Handle of a mesh created by a [`Renderer`], only valid with the renderer that created it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshId(pub usize);
+
+pub trait Renderer {
+    /// Upload a mesh of vertices, drawn in the order of `indices` when given. A new mesh is drawn
+    /// once without transformation, until [`Renderer::set_instances`]. Fails when an index
+    /// fetches past the vertices.
+    fn create_mesh(
+        &mut self,
+        vertices: &[Vertex],
+        indices: Option<Indices>,
+    ) -> crate::Result<MeshId>;
+
+    /// Draw `mesh` once for every instance from now on. Fails when the mesh has per-instance data
+    /// for a different number of instances.
+    fn set_instances(&mut self, mesh: MeshId, instances: &[Instance]) -> crate::Result<()>;
+
+    /// View the scene through `camera`.
+    fn set_camera(&mut self, camera: &Camera);
+
+    /// Draw `mesh` in the next frame. Draws are submitted until [`Renderer::present`].
+    fn draw(&mut self, mesh: MeshId);
+
+    /// Render the submitted draws and show the frame.
+    fn present(&mut self) -> crate::Result<()>;
+
+    /// Resize the frame to a physical size. Rendering pauses at a zero width or height.
+    fn resize(&mut self, width: u32, height: u32);
+
+    /// Resize the frame to the physical size of `window`, nothing happens while its size stays.
+    fn resize_to_window(&mut self, window: &Window) {
+        let (width, height) = window.window_attributes.size;
+        self.resize(width.max(0) as u32, height.max(0) as u32);
+    }
+}
+
+impl fmt::Debug for dyn Renderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("Renderer")
+    }
+}
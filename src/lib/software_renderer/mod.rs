@@ -0,0 +1,391 @@
+//! A renderer that rasterizes on the CPU, for machines without a GPU driver.
+//!
+//! [`SoftwareRenderer`] takes the same [`RendererAttributes`] and has the same API as
+//! [`WGPURenderer`](crate::wgpu_renderer::WGPURenderer), and renders what the PiCa shaders do:
+//! vertices are transformed by the camera, the model uniform and their instance, and vertex colors
+//! are interpolated over the primitives. The WGSL of [`RendererAttributes::source`] is not run.
+//!
+//! Frames go to a [`raster::Framebuffer`] of RGBA8 pixels in sRGB, like those read back from an
+//! offscreen GPU renderer, to compare both with [`crate::wgpu_renderer::golden`]. Frames do not
+//! depend on the machine, which makes them references of their own.
+//!
+//! The renderer is offscreen only, frames are not shown in a window. [`Renderer::present`] renders
+//! into the framebuffer, from which frames are taken with [`SoftwareRenderer::read_pixels`],
+//! [`SoftwareRenderer::save_screenshot`] or a recording.
+use crate::pica_window::Window;
+use crate::renderer::camera::{self, Camera};
+use crate::renderer::{mesh, Indices, Instance, InstanceRaw, MeshId, Renderer, Vertex};
+use crate::wgpu_renderer::{capture, CameraUniform, RendererAttributes};
+use glam::{Mat4, Quat, Vec3, Vec4};
+use std::path::Path;
+use wgpu::PrimitiveTopology;
+
+pub mod raster;
+
+use raster::{ClipVertex, Framebuffer};
+
+/// A mesh in memory, drawn once for every instance.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub topology: PrimitiveTopology,
+    pub vertices: Vec<Vertex>,
+    pub indices: Option<Indices>,
+    pub instances: Vec<InstanceRaw>,
+}
+
+impl Mesh {
+    /// A mesh with a single instance at the origin.
+    pub fn new(topology: PrimitiveTopology, vertices: &[Vertex], indices: Option<Indices>) -> Mesh {
+        Mesh {
+            topology,
+            vertices: vertices.to_vec(),
+            indices,
+            instances: vec![origin()],
+        }
+    }
+}
+
+pub struct SoftwareRenderer {
+    pub framebuffer: Framebuffer,
+    pub clear_color: wgpu::Color,
+    /// Topology of the meshes created through [`Renderer::create_mesh`] from now on, that of the
+    /// renderer attributes at first. Meshes keep the topology they were created with.
+    pub topology: PrimitiveTopology,
+    /// The mesh of the renderer attributes, drawn in every frame.
+    pub mesh: Option<Mesh>,
+    pub model_mat: Mat4,
+    pub size: (i32, i32),
+    pub instances: Option<Vec<Instance>>,
+    pub camera: Camera,
+    pub projection: camera::Projection,
+    pub camera_uniform: CameraUniform,
+    pub recording: Option<capture::Recording>,
+    /// The window has no area to render to, frames are skipped until it is resized.
+    pub minimized: bool,
+    /// Physical pixels per logical pixel of the window, see `WGPURenderer::scale_factor`.
+    pub scale_factor: f64,
+    /// Meshes created through [`Renderer::create_mesh`].
+    pub meshes: Vec<Mesh>,
+    /// Meshes to draw in the next frame, after the mesh of the renderer attributes.
+    pub draws: Vec<MeshId>,
+}
+
+impl SoftwareRenderer {
+    /// Create a renderer of the given size.
+    pub fn new(size: (i32, i32), renderer_attributes: RendererAttributes) -> SoftwareRenderer {
+        let (width, height) = (size.0.max(1) as u32, size.1.max(1) as u32);
+        let (camera, projection) =
+            camera::default_view(renderer_attributes.camera_position, width, height);
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update_view_proj(&camera, &projection);
+
+        let instances = renderer_attributes.instances;
+        let mesh = renderer_attributes.vertices.map(|vertices| Mesh {
+            topology: renderer_attributes.topology,
+            instances: match &instances {
+                Some(instances) => instances.iter().map(Instance::to_raw).collect(),
+                None => vec![origin()],
+            },
+            vertices,
+            indices: renderer_attributes.indices.map(Indices::U16),
+        });
+
+        SoftwareRenderer {
+            framebuffer: Framebuffer::new(width, height),
+            clear_color: wgpu::Color {
+                r: 0.03,
+                g: 0.01,
+                b: 0.1,
+                a: 1.0,
+            },
+            topology: renderer_attributes.topology,
+            mesh,
+            model_mat: crate::math::create_transforms(
+                [0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0],
+                [1.0, 1.0, 1.0],
+            ),
+            size: (width as i32, height as i32),
+            instances,
+            camera,
+            projection,
+            camera_uniform,
+            recording: None,
+            minimized: false,
+            scale_factor: 1.0,
+            meshes: Vec::new(),
+            draws: Vec::new(),
+        }
+    }
+
+    /// Create a renderer of the physical size of `window`.
+    pub fn new_for_window(
+        window: &Window,
+        renderer_attributes: RendererAttributes,
+    ) -> SoftwareRenderer {
+        let mut renderer = Self::new(window.window_attributes.size, renderer_attributes);
+        renderer.scale_factor = window.scale_factor();
+        renderer
+    }
+
+    pub fn write_uniform(&mut self, data: &[f32; 16]) {
+        self.model_mat = Mat4::from_cols_array(data);
+    }
+
+    /// Replace the instances of the mesh of the renderer attributes.
+    pub fn write_instances(&mut self, data: Vec<InstanceRaw>) {
+        if let Some(mesh) = &mut self.mesh {
+            mesh.instances = data;
+        }
+    }
+
+    pub fn write_camera(&mut self, data: &[CameraUniform]) {
+        self.camera_uniform = data[0];
+    }
+
+    /// Resize the frame and camera projection to the physical size of the window, see
+    /// `WGPURenderer::resize_to_window`.
+    pub fn resize_to_window(&mut self, window: &Window) {
+        self.scale_factor = window.scale_factor();
+        let (width, height) = window.window_attributes.size;
+        self.resize(width.max(0) as u32, height.max(0) as u32);
+    }
+
+    /// Resize the frame and camera projection.
+    /// A zero width or height, like that of a minimized window, pauses rendering until the next resize.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            self.minimized = true;
+            return;
+        }
+        self.minimized = false;
+        if (width, height) == (self.framebuffer.width, self.framebuffer.height) {
+            return;
+        }
+        self.framebuffer = Framebuffer::new(width, height);
+        self.size = (width as i32, height as i32);
+        self.projection.resize(width, height);
+        self.camera_uniform
+            .update_view_proj(&self.camera, &self.projection);
+    }
+
+    /// Render a frame into the framebuffer.
+    pub fn render(&mut self) {
+        if self.minimized {
+            return;
+        }
+        self.draw_frame();
+        self.record_frame();
+        self.draws.clear();
+    }
+
+    /// The last rendered frame as RGBA8 pixels, row by row from the top.
+    pub fn read_pixels(&self) -> &[u8] {
+        &self.framebuffer.pixels
+    }
+
+    /// Render the current scene and return its RGBA8 pixels, row by row from the top.
+    pub fn capture_frame(&mut self) -> Vec<u8> {
+        self.draw_frame();
+        self.framebuffer.pixels.clone()
+    }
+
+    /// Render the current scene to a PNG file.
+    pub fn save_screenshot<P: AsRef<Path>>(&mut self, path: P) -> crate::Result<()> {
+        let pixels = self.capture_frame();
+        capture::write_png(
+            path,
+            self.framebuffer.width,
+            self.framebuffer.height,
+            &pixels,
+        )
+    }
+
+    /// Write every `every_nth_frame`-th rendered frame to `directory` as a numbered PNG sequence,
+    /// until [`SoftwareRenderer::stop_recording`] is called.
+    pub fn start_recording<P: AsRef<Path>>(
+        &mut self,
+        directory: P,
+        every_nth_frame: u32,
+    ) -> crate::Result<()> {
+        self.recording = Some(capture::Recording::new(directory, every_nth_frame)?);
+        Ok(())
+    }
+
+    /// Stop recording frames, returns the finished recording.
+    pub fn stop_recording(&mut self) -> Option<capture::Recording> {
+        self.recording.take()
+    }
+
+    fn record_frame(&mut self) {
+        let path = match self
+            .recording
+            .as_mut()
+            .and_then(capture::Recording::next_frame)
+        {
+            Some(path) => path,
+            None => return,
+        };
+        let framebuffer = &self.framebuffer;
+        if let Err(error) = capture::write_png(
+            &path,
+            framebuffer.width,
+            framebuffer.height,
+            &framebuffer.pixels,
+        ) {
+            log::error!("{:?}, recording stopped", error);
+            self.recording = None;
+        }
+    }
+
+    // Clear the frame and draw the mesh of the renderer attributes and the submitted meshes.
+    fn draw_frame(&mut self) {
+        let color = self.clear_color;
+        self.framebuffer.clear(Vec4::new(
+            color.r as f32,
+            color.g as f32,
+            color.b as f32,
+            color.a as f32,
+        ));
+        let view_proj = self.camera_uniform.view_proj() * self.model_mat;
+        let draws = self.draws.iter().map(|mesh| &self.meshes[mesh.0]);
+        for mesh in self.mesh.iter().chain(draws) {
+            draw_mesh(&mut self.framebuffer, mesh, view_proj);
+        }
+    }
+}
+
+fn origin() -> InstanceRaw {
+    Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+    }
+    .to_raw()
+}
+
+fn draw_mesh(framebuffer: &mut Framebuffer, mesh: &Mesh, view_proj: Mat4) {
+    let topology = mesh.topology;
+    let strip = matches!(
+        topology,
+        PrimitiveTopology::LineStrip | PrimitiveTopology::TriangleStrip
+    );
+    // Strips restart at the largest index
+    let runs: Vec<Vec<usize>> = match &mesh.indices {
+        Some(indices) => {
+            let restart = match strip {
+                true => Some(indices.restart_index()),
+                false => None,
+            };
+            indices
+                .to_u32()
+                .split(|&index| Some(index) == restart)
+                .map(|run| run.iter().map(|&index| index as usize).collect())
+                .collect()
+        }
+        None => vec![(0..mesh.vertices.len()).collect()],
+    };
+
+    for instance in &mesh.instances {
+        let model_view_proj = view_proj * instance.model();
+        let vertices: Vec<ClipVertex> = mesh
+            .vertices
+            .iter()
+            .map(|vertex| ClipVertex {
+                position: model_view_proj * Vec4::from(vertex.position),
+                color: Vec4::from(vertex.color),
+            })
+            .collect();
+        // Primitives with an index out of range are skipped, like on the GPU.
+        let fetch = |indices: &[usize]| -> Option<Vec<ClipVertex>> {
+            indices
+                .iter()
+                .map(|&index| vertices.get(index).copied())
+                .collect()
+        };
+
+        for run in &runs {
+            match topology {
+                PrimitiveTopology::PointList => {
+                    for vertex in run.iter().filter_map(|&index| vertices.get(index)) {
+                        framebuffer.point(*vertex);
+                    }
+                }
+                PrimitiveTopology::LineList | PrimitiveTopology::LineStrip => {
+                    let lines: Vec<&[usize]> = match topology {
+                        PrimitiveTopology::LineList => run.chunks_exact(2).collect(),
+                        _ => run.windows(2).collect(),
+                    };
+                    for line in lines.into_iter().filter_map(&fetch) {
+                        framebuffer.line([line[0], line[1]]);
+                    }
+                }
+                PrimitiveTopology::TriangleList => {
+                    for triangle in run.chunks_exact(3).filter_map(&fetch) {
+                        framebuffer.triangle([triangle[0], triangle[1], triangle[2]], true);
+                    }
+                }
+                PrimitiveTopology::TriangleStrip => {
+                    for (i, triangle) in run.windows(3).enumerate() {
+                        // Every other triangle of a strip is wound the other way around
+                        let triangle = match i % 2 {
+                            0 => fetch(triangle),
+                            _ => fetch(&[triangle[1], triangle[0], triangle[2]]),
+                        };
+                        if let Some(triangle) = triangle {
+                            framebuffer.triangle([triangle[0], triangle[1], triangle[2]], true);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn create_mesh(
+        &mut self,
+        vertices: &[Vertex],
+        indices: Option<Indices>,
+    ) -> crate::Result<MeshId> {
+        let elements = indices.as_ref().map_or(vertices.len(), Indices::len);
+        mesh::validate_draw(
+            self.topology,
+            vertices.len(),
+            indices.as_ref(),
+            &(0..elements as u32),
+            0,
+        )?;
+        self.meshes
+            .push(Mesh::new(self.topology, vertices, indices));
+        Ok(MeshId(self.meshes.len() - 1))
+    }
+
+    fn set_instances(&mut self, mesh: MeshId, instances: &[Instance]) -> crate::Result<()> {
+        self.meshes[mesh.0].instances = instances.iter().map(Instance::to_raw).collect();
+        Ok(())
+    }
+
+    fn set_camera(&mut self, camera: &Camera) {
+        self.camera = camera.clone();
+        self.camera_uniform
+            .update_view_proj(&self.camera, &self.projection);
+    }
+
+    fn draw(&mut self, mesh: MeshId) {
+        self.draws.push(mesh);
+    }
+
+    /// Render the submitted draws into the framebuffer, nothing is shown in a window.
+    fn present(&mut self) -> crate::Result<()> {
+        self.render();
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        SoftwareRenderer::resize(self, width, height);
+    }
+
+    fn resize_to_window(&mut self, window: &Window) {
+        SoftwareRenderer::resize_to_window(self, window);
+    }
+}
@@ -0,0 +1,319 @@
+//! Rasterization of clip space points, lines and triangles into a color and depth buffer.
+//!
+//! Follows the rules of the GPU pipeline: primitives are clipped to the depth range and to a guard
+//! band far outside the frame, pixels are sampled at their centers, triangle edges follow the
+//! top-left rule, depth is interpolated linearly in screen space with a less-or-equal test, and
+//! colors are interpolated perspective correct. Triangle coverage is decided in fixed point, so a
+//! frame comes out the same on every machine.
+use glam::{Vec2, Vec4};
+
+// Sub-pixel precision of triangle coverage, in bits.
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL: f64 = (1 << SUBPIXEL_BITS) as f64;
+// Primitives are clipped to this many pixels around the frame, so edge functions fit in an i64.
+const GUARD_BAND: f64 = (1 << 20) as f64;
+// Primitives are clipped to a positive w, in front of the eye.
+const MIN_W: f32 = 1e-6;
+
+/// A vertex after the vertex stage, in clip space with a linear color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipVertex {
+    pub position: Vec4,
+    pub color: Vec4,
+}
+
+impl ClipVertex {
+    fn lerp(self, other: ClipVertex, t: f32) -> ClipVertex {
+        ClipVertex {
+            position: self.position + (other.position - self.position) * t,
+            color: self.color + (other.color - self.color) * t,
+        }
+    }
+}
+
+// A vertex in pixels, with the attributes interpolated over the screen.
+#[derive(Clone, Copy)]
+struct ScreenVertex {
+    x: f64,
+    y: f64,
+    z: f64,
+    // 1 / w, to interpolate colors perspective correct.
+    inv_w: f64,
+    color: Vec4,
+}
+
+/// The color and depth of a frame. Colors are RGBA8 in sRGB, row by row from the top, like the
+/// pixels read back from a GPU frame.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    pub depth: Vec<f32>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        let len = width as usize * height as usize;
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![0; 4 * len],
+            depth: vec![1.0; len],
+        }
+    }
+
+    /// Fill the frame with a linear color, and the depth buffer with the far plane.
+    pub fn clear(&mut self, color: Vec4) {
+        let rgba = encode_color(color);
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+        self.depth.fill(1.0);
+    }
+
+    pub fn point(&mut self, vertex: ClipVertex) {
+        if !inside_depth_range(vertex.position) {
+            return;
+        }
+        let vertex = self.to_screen(vertex);
+        let (x, y) = (vertex.x.floor(), vertex.y.floor());
+        if x >= 0.0 && y >= 0.0 && x < self.width as f64 && y < self.height as f64 {
+            self.shade(x as u32, y as u32, vertex.z, vertex.color);
+        }
+    }
+
+    /// Draw a line, without its last pixel so the lines of a strip do not overlap.
+    pub fn line(&mut self, vertices: [ClipVertex; 2]) {
+        let [a, b] = match clip_line(vertices, self.guard_band()) {
+            Some(vertices) => vertices.map(|vertex| self.to_screen(vertex)),
+            None => return,
+        };
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        // Step along the major axis, from pixel center to pixel center.
+        let x_major = dx.abs() >= dy.abs();
+        let (start, end, delta) = match x_major {
+            true => (a.x, b.x, dx),
+            false => (a.y, b.y, dy),
+        };
+        if delta == 0.0 {
+            return;
+        }
+        let (first, last) = (start.min(end), start.max(end));
+        let mut center = (first - 0.5).ceil() + 0.5;
+        while center < last {
+            let t = (center - start) / delta;
+            let (x, y) = match x_major {
+                true => (center, a.y + t * dy),
+                false => (a.x + t * dx, center),
+            };
+            let (x, y) = (x.floor(), y.floor());
+            if x >= 0.0 && y >= 0.0 && x < self.width as f64 && y < self.height as f64 {
+                let z = a.z + (b.z - a.z) * t;
+                let color = perspective_color([a, b], [1.0 - t, t]);
+                self.shade(x as u32, y as u32, z, color);
+            }
+            center += 1.0;
+        }
+    }
+
+    /// Draw a triangle, unless it faces away and `cull_back` is set. Triangles with their vertices
+    /// counter-clockwise on screen face the viewer.
+    pub fn triangle(&mut self, vertices: [ClipVertex; 3], cull_back: bool) {
+        let polygon = clip_polygon(vertices.to_vec(), self.guard_band());
+        if polygon.len() < 3 {
+            return;
+        }
+        let polygon: Vec<ScreenVertex> = polygon.into_iter().map(|v| self.to_screen(v)).collect();
+        for i in 1..polygon.len() - 1 {
+            self.screen_triangle([polygon[0], polygon[i], polygon[i + 1]], cull_back);
+        }
+    }
+
+    fn screen_triangle(&mut self, mut vertices: [ScreenVertex; 3], cull_back: bool) {
+        let mut fixed = vertices.map(|vertex| (to_fixed(vertex.x), to_fixed(vertex.y)));
+        let mut area = edge(fixed[0], fixed[1], fixed[2]);
+        // Counter-clockwise on screen is a negative area, with y pointing down.
+        if area == 0 || (cull_back && area > 0) {
+            return;
+        }
+        if area < 0 {
+            vertices.swap(1, 2);
+            fixed.swap(1, 2);
+            area = -area;
+        }
+
+        let min_x = fixed.iter().map(|p| p.0).min().unwrap();
+        let max_x = fixed.iter().map(|p| p.0).max().unwrap();
+        let min_y = fixed.iter().map(|p| p.1).min().unwrap();
+        let max_y = fixed.iter().map(|p| p.1).max().unwrap();
+        let pixels = |min: i64, max: i64, size: u32| {
+            let first = (min >> SUBPIXEL_BITS).max(0);
+            let last = (max >> SUBPIXEL_BITS).min(size as i64 - 1);
+            first..=last
+        };
+        // Each edge, opposite to the vertex it weighs.
+        let edges = [(1, 2), (2, 0), (0, 1)].map(|(from, to)| (fixed[from], fixed[to]));
+        let top_left = edges.map(|(from, to)| is_top_left(from, to));
+
+        let half = 1 << (SUBPIXEL_BITS - 1);
+        for y in pixels(min_y, max_y, self.height) {
+            for x in pixels(min_x, max_x, self.width) {
+                let center = ((x << SUBPIXEL_BITS) + half, (y << SUBPIXEL_BITS) + half);
+                let weights = edges.map(|(from, to)| edge(from, to, center));
+                let covered = weights
+                    .iter()
+                    .zip(top_left)
+                    .all(|(&weight, top_left)| weight > 0 || (weight == 0 && top_left));
+                if !covered {
+                    continue;
+                }
+                let barycentric = weights.map(|weight| weight as f64 / area as f64);
+                let z = (0..3).map(|i| barycentric[i] * vertices[i].z).sum();
+                let color = perspective_color(vertices, barycentric);
+                self.shade(x as u32, y as u32, z, color);
+            }
+        }
+    }
+
+    // Depth test and write one pixel.
+    fn shade(&mut self, x: u32, y: u32, z: f64, color: Vec4) {
+        let index = (y * self.width + x) as usize;
+        let z = z as f32;
+        if z > self.depth[index] {
+            return;
+        }
+        self.depth[index] = z;
+        self.pixels[4 * index..4 * index + 4].copy_from_slice(&encode_color(color));
+    }
+
+    // The largest x and y in normalized device coordinates that stay within the guard band, for
+    // either sign.
+    fn guard_band(&self) -> Vec2 {
+        let limit = |size: u32| (2.0 * GUARD_BAND / size.max(1) as f64 - 1.0) as f32;
+        Vec2::new(limit(self.width), limit(self.height))
+    }
+
+    fn to_screen(&self, vertex: ClipVertex) -> ScreenVertex {
+        let inv_w = 1.0 / vertex.position.w as f64;
+        let ndc = (
+            vertex.position.x as f64 * inv_w,
+            vertex.position.y as f64 * inv_w,
+        );
+        ScreenVertex {
+            x: (ndc.0 * 0.5 + 0.5) * self.width as f64,
+            y: (0.5 - ndc.1 * 0.5) * self.height as f64,
+            z: vertex.position.z as f64 * inv_w,
+            inv_w,
+            color: vertex.color,
+        }
+    }
+}
+
+fn to_fixed(coordinate: f64) -> i64 {
+    (coordinate * SUBPIXEL).round() as i64
+}
+
+// Twice the signed area of the triangle a, b, p. Positive when p lies clockwise on screen.
+fn edge(a: (i64, i64), b: (i64, i64), p: (i64, i64)) -> i64 {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+}
+
+// Pixels exactly on an edge belong to the triangle when it is a top or left edge, so triangles
+// sharing an edge never draw a pixel twice.
+fn is_top_left(from: (i64, i64), to: (i64, i64)) -> bool {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    (dy == 0 && dx > 0) || dy < 0
+}
+
+fn perspective_color<const N: usize>(vertices: [ScreenVertex; N], barycentric: [f64; N]) -> Vec4 {
+    let weights: [f64; N] = std::array::from_fn(|i| barycentric[i] * vertices[i].inv_w);
+    let total: f64 = weights.iter().sum();
+    let mut color = [0.0f64; 4];
+    for (vertex, weight) in vertices.iter().zip(weights) {
+        for (channel, value) in color.iter_mut().zip(vertex.color.to_array()) {
+            *channel += weight / total * value as f64;
+        }
+    }
+    Vec4::from(color.map(|channel| channel as f32))
+}
+
+// Distances to the planes primitives are clipped to, positive on the inside. The depth range
+// comes first, then the guard band.
+fn plane_distances(position: Vec4, guard_band: Vec2) -> [f32; 7] {
+    let [x, y, z, w] = position.to_array();
+    [
+        w - MIN_W,
+        z,
+        w - z,
+        guard_band.x * w - x,
+        guard_band.x * w + x,
+        guard_band.y * w - y,
+        guard_band.y * w + y,
+    ]
+}
+
+fn inside_depth_range(position: Vec4) -> bool {
+    plane_distances(position, Vec2::ZERO)[..3]
+        .iter()
+        .all(|&distance| distance >= 0.0)
+}
+
+fn clip_line([mut a, mut b]: [ClipVertex; 2], guard_band: Vec2) -> Option<[ClipVertex; 2]> {
+    for plane in 0..7 {
+        let distance_a = plane_distances(a.position, guard_band)[plane];
+        let distance_b = plane_distances(b.position, guard_band)[plane];
+        match (distance_a >= 0.0, distance_b >= 0.0) {
+            (true, true) => {}
+            (false, false) => return None,
+            (true, false) => b = a.lerp(b, distance_a / (distance_a - distance_b)),
+            (false, true) => a = a.lerp(b, distance_a / (distance_a - distance_b)),
+        }
+    }
+    Some([a, b])
+}
+
+// Sutherland-Hodgman clipping of a convex polygon to the depth range and the guard band.
+fn clip_polygon(mut polygon: Vec<ClipVertex>, guard_band: Vec2) -> Vec<ClipVertex> {
+    for plane in 0..7 {
+        if polygon
+            .iter()
+            .all(|v| plane_distances(v.position, guard_band)[plane] >= 0.0)
+        {
+            continue;
+        }
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for (i, &current) in polygon.iter().enumerate() {
+            let next = polygon[(i + 1) % polygon.len()];
+            let distance_current = plane_distances(current.position, guard_band)[plane];
+            let distance_next = plane_distances(next.position, guard_band)[plane];
+            if distance_current >= 0.0 {
+                clipped.push(current);
+            }
+            if (distance_current >= 0.0) != (distance_next >= 0.0) {
+                let t = distance_current / (distance_current - distance_next);
+                clipped.push(current.lerp(next, t));
+            }
+        }
+        polygon = clipped;
+    }
+    polygon
+}
+
+/// A linear color as RGBA8, with the color channels encoded to sRGB.
+pub fn encode_color(color: Vec4) -> [u8; 4] {
+    let quantize = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let srgb = |value: f32| {
+        let value = (value as f64).clamp(0.0, 1.0);
+        match value <= 0.0031308 {
+            true => 12.92 * value,
+            false => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+        }
+    };
+    [
+        quantize(srgb(color.x)),
+        quantize(srgb(color.y)),
+        quantize(srgb(color.z)),
+        quantize(color.w as f64),
+    ]
+}
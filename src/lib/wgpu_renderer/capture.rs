@@ -0,0 +1,136 @@
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use crate::error::Error;
+
+/// Writes every N-th rendered frame of a [`super::WGPURenderer`] to a directory,
+/// as a numbered PNG sequence: `frame_000000.png`, `frame_000001.png`, ...
+#[derive(Debug)]
+pub struct Recording {
+    pub directory: PathBuf,
+    pub every_nth_frame: u32,
+    frames_rendered: u64,
+    frames_written: u64,
+}
+
+impl Recording {
+    /// Start a recording in `directory`, creating it when it does not exist yet.
+    pub fn new<P: AsRef<Path>>(directory: P, every_nth_frame: u32) -> crate::Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory).map_err(|e| {
+            Error::Capture(format!(
+                "Failed to create recording directory {:?}: {}",
+                directory, e
+            ))
+        })?;
+        Ok(Self {
+            directory,
+            every_nth_frame: every_nth_frame.max(1),
+            frames_rendered: 0,
+            frames_written: 0,
+        })
+    }
+
+    /// Number of PNG files written so far.
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+
+    // Count a rendered frame, returns the path to write it to when it is to be recorded.
+    pub(crate) fn next_frame(&mut self) -> Option<PathBuf> {
+        let is_recorded = self
+            .frames_rendered
+            .is_multiple_of(self.every_nth_frame as u64);
+        self.frames_rendered += 1;
+        if is_recorded {
+            let path = self
+                .directory
+                .join(format!("frame_{:06}.png", self.frames_written));
+            self.frames_written += 1;
+            Some(path)
+        } else {
+            None
+        }
+    }
+}
+
+/// Write tightly packed RGBA8 pixels, row by row from the top, to a PNG file.
+pub fn write_png<P: AsRef<Path>>(
+    path: P,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> crate::Result<()> {
+    let path = path.as_ref();
+    let capture_error = |e: &dyn std::fmt::Display| {
+        Error::Capture(format!("Failed to write PNG {:?}: {}", path, e))
+    };
+
+    let file = File::create(path).map_err(|e| capture_error(&e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| capture_error(&e))?;
+    writer
+        .write_image_data(pixels)
+        .map_err(|e| capture_error(&e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Recording;
+    use std::path::PathBuf;
+
+    fn recorded_frames(every_nth_frame: u32, frames: usize) -> Vec<Option<PathBuf>> {
+        let directory = std::env::temp_dir().join(format!(
+            "pica_recording_{}_{}",
+            std::process::id(),
+            every_nth_frame
+        ));
+        let mut recording = Recording::new(&directory, every_nth_frame).unwrap();
+        let paths = (0..frames).map(|_| recording.next_frame()).collect();
+        std::fs::remove_dir_all(&directory).unwrap();
+        paths
+    }
+
+    fn frame_name(path: &Option<PathBuf>) -> Option<&str> {
+        path.as_ref()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+    }
+
+    #[test]
+    fn records_every_nth_frame_from_the_first() {
+        let frames = recorded_frames(3, 7);
+        let names = frames.iter().map(frame_name).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                Some("frame_000000.png"),
+                None,
+                None,
+                Some("frame_000001.png"),
+                None,
+                None,
+                Some("frame_000002.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn records_every_frame_when_n_is_zero() {
+        let frames = recorded_frames(0, 3);
+        let names = frames.iter().map(frame_name).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                Some("frame_000000.png"),
+                Some("frame_000001.png"),
+                Some("frame_000002.png"),
+            ]
+        );
+    }
+}
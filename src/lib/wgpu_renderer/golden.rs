@@ -0,0 +1,188 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use super::{capture, RendererAttributes, WGPURenderer};
+use crate::error::Error;
+
+/// Set this environment variable to overwrite the reference images with the rendered frames.
+pub const UPDATE_GOLDEN_ENV: &str = "PICA_UPDATE_GOLDEN";
+
+/// How far a rendered frame may deviate from its reference image.
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    /// Largest difference per color channel for a pixel to still count as equal.
+    pub per_channel: u8,
+    /// Number of pixels that may differ more than `per_channel` before the comparison fails.
+    pub max_mismatched_pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        // Software adapters do not rasterize bit exact across versions, allow some rounding
+        Self {
+            per_channel: 2,
+            max_mismatched_pixels: 0,
+        }
+    }
+}
+
+/// Result of comparing two RGBA8 images of the same size.
+#[derive(Debug)]
+pub struct ImageDiff {
+    pub mismatched_pixels: usize,
+    pub max_difference: u8,
+    /// Visualisation of the comparison: differing pixels in red, equal pixels as faded grey.
+    pub diff: Vec<u8>,
+}
+
+/// Compare two RGBA8 images pixel by pixel.
+pub fn compare_images(expected: &[u8], actual: &[u8], per_channel: u8) -> ImageDiff {
+    assert_eq!(expected.len(), actual.len(), "Images differ in size");
+
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    let mut diff = Vec::with_capacity(expected.len());
+    for (expected, actual) in expected.chunks(4).zip(actual.chunks(4)) {
+        let difference = expected
+            .iter()
+            .zip(actual)
+            .map(|(e, a)| e.abs_diff(*a))
+            .max()
+            .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        if difference > per_channel {
+            mismatched_pixels += 1;
+            diff.extend_from_slice(&[255, 255 - difference, 255 - difference, 255]);
+        } else {
+            let grey = (expected[0] as u16 + expected[1] as u16 + expected[2] as u16) / 3;
+            let faded = (64 + grey / 4) as u8;
+            diff.extend_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+
+    ImageDiff {
+        mismatched_pixels,
+        max_difference,
+        diff,
+    }
+}
+
+/// Read a PNG file as RGBA8 pixels, returns `(width, height, pixels)`.
+pub fn read_png<P: AsRef<Path>>(path: P) -> crate::Result<(u32, u32, Vec<u8>)> {
+    let path = path.as_ref();
+    let golden_error =
+        |e: &dyn std::fmt::Display| Error::Golden(format!("Failed to read PNG {:?}: {}", path, e));
+
+    let file = File::open(path).map_err(|e| golden_error(&e))?;
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| golden_error(&e))?;
+    let mut buffer = vec![0; reader.output_buffer_size().unwrap_or(0)];
+    let info = reader.next_frame(&mut buffer).map_err(|e| golden_error(&e))?;
+    buffer.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        color_type => {
+            return Err(golden_error(&format!(
+                "unsupported color type {:?}",
+                color_type
+            )))
+        }
+    };
+    Ok((info.width, info.height, pixels))
+}
+
+/// Compare a rendered RGBA8 frame against the reference PNG at `reference`.
+///
+/// A missing reference is an error. When [`UPDATE_GOLDEN_ENV`] is set the reference is written from
+/// the frame instead, whether it exists or not.
+/// On a mismatch the frame and a diff image are written next to the reference,
+/// as `<name>.actual.png` and `<name>.diff.png`.
+pub fn check_frame<P: AsRef<Path>>(
+    pixels: &[u8],
+    size: (u32, u32),
+    reference: P,
+    tolerance: Tolerance,
+) -> crate::Result<()> {
+    let reference = reference.as_ref();
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        if let Some(directory) = reference.parent() {
+            std::fs::create_dir_all(directory).map_err(|e| {
+                Error::Golden(format!("Failed to create {:?}: {}", directory, e))
+            })?;
+        }
+        log::warn!("Writing golden reference image {:?}", reference);
+        return capture::write_png(reference, size.0, size.1, pixels);
+    }
+
+    if !reference.exists() {
+        return Err(Error::Golden(format!(
+            "Reference {:?} is missing, set {}=1 to write it from the rendered frame",
+            reference, UPDATE_GOLDEN_ENV
+        )));
+    }
+
+    let (width, height, expected) = read_png(reference)?;
+    if (width, height) != size {
+        return Err(Error::Golden(format!(
+            "Frame is {}x{}, but reference {:?} is {}x{}",
+            size.0, size.1, reference, width, height
+        )));
+    }
+
+    let image_diff = compare_images(&expected, pixels, tolerance.per_channel);
+    if image_diff.mismatched_pixels > tolerance.max_mismatched_pixels {
+        capture::write_png(sibling(reference, "actual"), size.0, size.1, pixels)?;
+        capture::write_png(sibling(reference, "diff"), size.0, size.1, &image_diff.diff)?;
+        return Err(Error::Golden(format!(
+            "{} pixels differ from reference {:?} (tolerance {} pixels), largest channel difference {}",
+            image_diff.mismatched_pixels,
+            reference,
+            tolerance.max_mismatched_pixels,
+            image_diff.max_difference
+        )));
+    }
+    Ok(())
+}
+
+/// Render one frame offscreen on a software adapter and compare it with the reference PNG,
+/// see [`check_frame`]. Fails when no software adapter is available.
+pub fn check_scene<P: AsRef<Path>>(
+    size: (i32, i32),
+    renderer_attributes: RendererAttributes,
+    setup: impl FnOnce(&mut WGPURenderer),
+    reference: P,
+    tolerance: Tolerance,
+) -> crate::Result<()> {
+    let renderer_attributes = RendererAttributes {
+        force_fallback_adapter: true,
+        ..renderer_attributes
+    };
+    let mut renderer =
+        pollster::block_on(WGPURenderer::try_new_offscreen(size, renderer_attributes))?;
+    setup(&mut renderer);
+    let pixels = renderer.capture_frame();
+    check_frame(
+        &pixels,
+        (renderer.config.width, renderer.config.height),
+        reference,
+        tolerance,
+    )
+}
+
+// `dir/name.png` -> `dir/name.<suffix>.png`
+fn sibling(reference: &Path, suffix: &str) -> PathBuf {
+    let stem = reference
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    reference.with_file_name(format!("{}.{}.png", stem, suffix))
+}
@@ -0,0 +1,75 @@
+use crate::error::Error;
+use crate::pica_window::{Platform, Window};
+use std::sync::Arc;
+
+/// A GPU device and its queue, shared by the renderers of several windows.
+///
+/// Create it once and pass it to [`super::WGPURenderer::new_with_gpu`] for every window. Buffers
+/// and textures created on the device can be used by all of them. Cloning shares the device.
+#[derive(Clone)]
+pub struct Gpu {
+    pub instance: Arc<wgpu::Instance>,
+    pub adapter: Arc<wgpu::Adapter>,
+    pub device: Arc<wgpu::Device>,
+    pub queue: Arc<wgpu::Queue>,
+}
+
+impl Gpu {
+    /// Pick an adapter that can present to `window`, or any adapter without a window. Fails when
+    /// `window` is headless, there is no suitable adapter or its device can not be opened.
+    pub async fn new(window: Option<&Window>, force_fallback_adapter: bool) -> crate::Result<Gpu> {
+        if let Some(window) = window {
+            check_presentable(window)?;
+        }
+        // WGPU_BACKEND picks the backend, e.g. the GL adapter the golden images were rendered on.
+        let backends = wgpu::util::backend_bits_from_env().unwrap_or_else(wgpu::Backends::all);
+        let instance = wgpu::Instance::new(backends);
+        // Only to find a compatible adapter, every renderer creates its own surface.
+        let surface = window.map(|window| unsafe { instance.create_surface(window) });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: surface.as_ref(),
+                force_fallback_adapter,
+            })
+            .await
+            .ok_or_else(|| Error::Render("No suitable adapter found".to_string()))?;
+        drop(surface);
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("Device Features"),
+                    // https://docs.rs/wgpu/0.12.0/wgpu/struct.Features.html
+                    features: wgpu::Features::empty(),
+                    // https://docs.rs/wgpu/0.12.0/wgpu/struct.Limits.html
+                    limits: if cfg!(target_arch = "wasm32") {
+                        // This is a set of limits that is lower even than the [downlevel_defaults()],
+                        // configured to be low enough to support running in the browser using WebGL2.
+                        wgpu::Limits::downlevel_webgl2_defaults()
+                    } else {
+                        wgpu::Limits::default()
+                    },
+                },
+                None,
+            )
+            .await
+            .map_err(|e| Error::Render(format!("Failed to open a device: {}", e)))?;
+        Ok(Gpu {
+            instance: Arc::new(instance),
+            adapter: Arc::new(adapter),
+            device: Arc::new(device),
+            queue: Arc::new(queue),
+        })
+    }
+}
+
+// Only windows of the OS have a surface, wgpu panics on the raw window handle of a headless one.
+pub(crate) fn check_presentable(window: &Window) -> crate::Result<()> {
+    match window.platform {
+        Platform::Headless(_) => Err(Error::Render(
+            "A headless window has no surface to present to, render offscreen instead".to_string(),
+        )),
+        #[allow(unreachable_patterns)]
+        _ => Ok(()),
+    }
+}
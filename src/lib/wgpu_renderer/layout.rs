@@ -0,0 +1,285 @@
+//! How the elements of vertex and instance buffers are laid out, and which shader inputs they feed.
+//!
+//! Meshes are not bound to [`Vertex`]: any `#[repr(C)]` struct can be drawn with a [`VertexLayout`]
+//! that lists its fields in order. A vertex with a position, a packed normal and tangent, two UV
+//! sets and a packed color:
+//!
+//! ```
+//! # use pica::wgpu_renderer::VertexLayout;
+//! use wgpu::VertexFormat;
+//!
+//! #[repr(C)]
+//! #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+//! struct LitVertex {
+//!     position: [f32; 3],
+//!     normal: [i8; 4],
+//!     tangent: [i8; 4],
+//!     uv: [f32; 2],
+//!     lightmap_uv: [u16; 2],
+//!     color: [u8; 4],
+//! }
+//!
+//! let layout = VertexLayout::per_vertex()
+//!     .with_attribute(0, VertexFormat::Float32x3)
+//!     .with_attribute(1, VertexFormat::Snorm8x4)
+//!     .with_attribute(2, VertexFormat::Snorm8x4)
+//!     .with_attribute(3, VertexFormat::Float32x2)
+//!     .with_attribute(4, VertexFormat::Unorm16x2)
+//!     .with_attribute(9, VertexFormat::Unorm8x4);
+//! assert_eq!(layout.array_stride, std::mem::size_of::<LitVertex>() as u64);
+//! ```
+//!
+//! Shader locations 5 to 8 are taken by the model matrix of the instances.
+use super::Vertex;
+use crate::error::Error;
+use bytemuck::Pod;
+use std::ops::RangeInclusive;
+use wgpu::{BufferAddress, VertexAttribute, VertexFormat, VertexStepMode};
+
+/// Shader locations of the model matrix of the instances, no layout may use them.
+pub const MODEL_LOCATIONS: RangeInclusive<u32> = 5..=8;
+
+/// Shader locations must be below this, the `max_vertex_attributes` of the default device limits.
+pub const MAX_VERTEX_ATTRIBUTES: u32 = 16;
+
+/// Largest size of an element, the `max_vertex_buffer_array_stride` of the default device limits.
+pub const MAX_VERTEX_BUFFER_ARRAY_STRIDE: BufferAddress = 2048;
+
+/// The attributes of one element of a vertex buffer, tightly packed in the order they are added.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    pub step_mode: VertexStepMode,
+    pub array_stride: BufferAddress,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    /// A layout of which the next element is read for every vertex.
+    pub fn per_vertex() -> VertexLayout {
+        VertexLayout::new(VertexStepMode::Vertex)
+    }
+
+    /// A layout of which the next element is read for every instance.
+    pub fn per_instance() -> VertexLayout {
+        VertexLayout::new(VertexStepMode::Instance)
+    }
+
+    fn new(step_mode: VertexStepMode) -> VertexLayout {
+        VertexLayout {
+            step_mode,
+            array_stride: 0,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Append an attribute that feeds the shader input at `shader_location`.
+    pub fn with_attribute(mut self, shader_location: u32, format: VertexFormat) -> VertexLayout {
+        self.attributes.push(VertexAttribute {
+            format,
+            offset: self.array_stride,
+            shader_location,
+        });
+        self.array_stride += format.size();
+        self
+    }
+
+    /// Skip bytes the shader does not read. Elements, padding included, must be a multiple of 4
+    /// bytes in size.
+    pub fn with_padding(mut self, bytes: BufferAddress) -> VertexLayout {
+        self.array_stride += bytes;
+        self
+    }
+
+    /// Fails when the size of an element is not a multiple of 4 bytes or above
+    /// [`MAX_VERTEX_BUFFER_ARRAY_STRIDE`], or when an attribute feeds one of the
+    /// [`MODEL_LOCATIONS`], a location from [`MAX_VERTEX_ATTRIBUTES`] on, or the location of an
+    /// earlier attribute.
+    pub fn validate(&self) -> crate::Result<()> {
+        if !self
+            .array_stride
+            .is_multiple_of(wgpu::VERTEX_STRIDE_ALIGNMENT)
+        {
+            return Err(Error::Render(format!(
+                "The stride {} of a vertex layout is not a multiple of {} bytes",
+                self.array_stride,
+                wgpu::VERTEX_STRIDE_ALIGNMENT
+            )));
+        }
+        if self.array_stride > MAX_VERTEX_BUFFER_ARRAY_STRIDE {
+            return Err(Error::Render(format!(
+                "The stride {} of a vertex layout is above {} bytes",
+                self.array_stride, MAX_VERTEX_BUFFER_ARRAY_STRIDE
+            )));
+        }
+        for (i, attribute) in self.attributes.iter().enumerate() {
+            let location = attribute.shader_location;
+            if MODEL_LOCATIONS.contains(&location) {
+                return Err(Error::Render(format!(
+                    "Shader location {} of a vertex layout is taken by the model matrix",
+                    location
+                )));
+            }
+            if location >= MAX_VERTEX_ATTRIBUTES {
+                return Err(Error::Render(format!(
+                    "Shader location {} of a vertex layout is not below {}",
+                    location, MAX_VERTEX_ATTRIBUTES
+                )));
+            }
+            if self.attributes[..i]
+                .iter()
+                .any(|earlier| earlier.shader_location == location)
+            {
+                return Err(Error::Render(format!(
+                    "Shader location {} is fed twice by a vertex layout",
+                    location
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails when this layout and `other` feed the same shader location, as the vertex and
+    /// instance layouts of one pipeline would.
+    pub fn validate_disjoint(&self, other: &VertexLayout) -> crate::Result<()> {
+        match self.attributes.iter().find(|attribute| {
+            other
+                .attributes
+                .iter()
+                .any(|theirs| theirs.shader_location == attribute.shader_location)
+        }) {
+            Some(attribute) => Err(Error::Render(format!(
+                "Shader location {} is fed by both the vertex and the instance layout",
+                attribute.shader_location
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn desc(&self) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.array_stride,
+            step_mode: self.step_mode,
+            attributes: &self.attributes,
+        }
+    }
+}
+
+/// The elements of a vertex buffer as bytes, together with their layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexData {
+    pub layout: VertexLayout,
+    pub bytes: Vec<u8>,
+}
+
+impl VertexData {
+    /// Copy `elements`, which are laid out as described by `layout`. Fails when the layout is not
+    /// valid, or its stride differs from the size of an element.
+    pub fn new<T: Pod>(layout: VertexLayout, elements: &[T]) -> crate::Result<VertexData> {
+        layout.validate()?;
+        let size = std::mem::size_of::<T>() as BufferAddress;
+        if size != layout.array_stride {
+            return Err(Error::Render(format!(
+                "The size {} of an element differs from the stride {} of its layout",
+                size, layout.array_stride
+            )));
+        }
+        Ok(VertexData {
+            layout,
+            bytes: bytemuck::cast_slice(elements).to_vec(),
+        })
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        match self.layout.array_stride {
+            0 => 0,
+            stride => self.bytes.len() / stride as usize,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for VertexData {
+    fn default() -> Self {
+        VertexData::from(&[] as &[Vertex])
+    }
+}
+
+impl From<&[Vertex]> for VertexData {
+    fn from(vertices: &[Vertex]) -> Self {
+        VertexData {
+            layout: Vertex::layout(),
+            bytes: bytemuck::cast_slice(vertices).to_vec(),
+        }
+    }
+}
+
+impl From<Vec<Vertex>> for VertexData {
+    fn from(vertices: Vec<Vertex>) -> Self {
+        VertexData::from(vertices.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VertexData, VertexLayout};
+    use wgpu::VertexFormat;
+
+    #[test]
+    fn elements_are_a_multiple_of_4_bytes() {
+        let layout = VertexLayout::per_vertex().with_attribute(0, VertexFormat::Unorm8x2);
+        assert!(layout.validate().is_err());
+        assert!(layout.clone().with_padding(1).validate().is_err());
+        assert!(layout.with_padding(2).validate().is_ok());
+    }
+
+    #[test]
+    fn model_matrix_locations_are_taken() {
+        for location in [5, 8] {
+            let layout =
+                VertexLayout::per_instance().with_attribute(location, VertexFormat::Float32x4);
+            assert!(layout.validate().is_err());
+        }
+        let layout = VertexLayout::per_instance().with_attribute(9, VertexFormat::Float32x4);
+        assert!(layout.validate().is_ok());
+    }
+
+    #[test]
+    fn locations_are_below_the_limit_and_unique() {
+        let layout = VertexLayout::per_vertex().with_attribute(15, VertexFormat::Float32);
+        assert!(layout.validate().is_ok());
+        let layout = VertexLayout::per_vertex().with_attribute(16, VertexFormat::Float32);
+        assert!(layout.validate().is_err());
+        let layout = VertexLayout::per_vertex()
+            .with_attribute(0, VertexFormat::Float32x3)
+            .with_attribute(0, VertexFormat::Float32);
+        assert!(layout.validate().is_err());
+    }
+
+    #[test]
+    fn strides_are_within_the_limit() {
+        let layout = VertexLayout::per_vertex().with_attribute(0, VertexFormat::Float32x4);
+        assert!(layout.clone().with_padding(2032).validate().is_ok());
+        assert!(layout.with_padding(2036).validate().is_err());
+    }
+
+    #[test]
+    fn vertex_and_instance_locations_are_disjoint() {
+        let vertices = VertexLayout::per_vertex().with_attribute(9, VertexFormat::Float32x4);
+        let instances = VertexLayout::per_instance().with_attribute(10, VertexFormat::Float32x4);
+        assert!(vertices.validate_disjoint(&instances).is_ok());
+        let instances = instances.with_attribute(9, VertexFormat::Float32);
+        assert!(vertices.validate_disjoint(&instances).is_err());
+    }
+
+    #[test]
+    fn elements_match_the_stride() {
+        let layout = VertexLayout::per_vertex().with_attribute(0, VertexFormat::Float32x3);
+        assert!(VertexData::new(layout.clone(), &[[0.0_f32; 4]]).is_err());
+        let data = VertexData::new(layout, &[[0.0_f32; 3]; 2]).unwrap();
+        assert_eq!(data.len(), 2);
+    }
+}
@@ -1,18 +1,33 @@
-use std::borrow::Cow;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use crate::error::Error;
 use crate::pica_window::Window;
+use crate::renderer::{mesh, MeshId, Renderer};
 use crate::utils;
-use crate::{math, wgpu_renderer::camera::Camera};
-use bytemuck::{Pod, Zeroable};
-use glam::{Mat4, Quat, Vec3, Vec4};
-use wgpu::ShaderModule;
-use wgpu::{util::DeviceExt, IndexFormat, PrimitiveTopology, ShaderSource};
-
-pub mod camera;
+use crate::{math, renderer::camera::Camera};
+use glam::{Mat4, Vec3, Vec4};
+use wgpu::{util::DeviceExt, IndexFormat, PrimitiveTopology};
+
+pub mod capture;
+pub mod golden;
+pub mod gpu;
+pub mod layout;
+pub mod pipeline;
 pub mod scene;
+pub mod target;
+
+pub use crate::renderer::{camera, Indices, Instance, InstanceRaw, Vertex};
+pub use gpu::Gpu;
+pub use layout::{VertexData, VertexLayout};
+use pipeline::{Pipeline, PipelineKey};
+use target::RenderTarget;
 
 // #[repr(C)]
 // #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 
+const DEFAULT_SHADER: &str = include_str!("../../../assets/cube_face_color.wgsl");
+
 
 
 #[repr(C)]
@@ -23,7 +38,7 @@ pub struct CameraUniform {
 }
 
 impl CameraUniform {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             view_position: [0.0; 4],
             view_proj: Mat4::IDENTITY.to_cols_array_2d(),
@@ -33,28 +48,12 @@ impl CameraUniform {
         self.view_position = Vec4::from((camera.position, 0.0)).to_array(); // Check if this is correct
         self.view_proj = (projection.calc_matrix() * camera.calc_matrix()).to_cols_array_2d();
     }
-}
 
-pub struct Instance {
-    pub position: Vec3,
-    pub rotation: Quat,
-}
-
-impl Instance {
-    pub fn to_raw(&self) -> InstanceRaw {
-        InstanceRaw {
-            model: (Mat4::from_translation(self.position) * Mat4::from_quat(self.rotation))
-                .to_cols_array_2d(),
-        }
+    pub(crate) fn view_proj(&self) -> Mat4 {
+        Mat4::from_cols_array_2d(&self.view_proj)
     }
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Pod, Zeroable)]
-pub struct InstanceRaw {
-    model: [[f32; 4]; 4],
-}
-
 impl InstanceRaw {
     fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         use std::mem;
@@ -92,30 +91,27 @@ impl InstanceRaw {
     }
 }
 
-#[repr(C)]
-#[derive(Clone, Copy, Debug)]
-pub struct Vertex {
-    pub position: [f32; 4],
-    pub color: [f32; 4],
+impl Vertex {
+    /// The position at shader location 0 and the color at location 1.
+    pub fn layout() -> VertexLayout {
+        VertexLayout::per_vertex()
+            .with_attribute(0, wgpu::VertexFormat::Float32x4)
+            .with_attribute(1, wgpu::VertexFormat::Float32x4)
+    }
 }
 
-impl Vertex {
-    pub fn vertex(p: [i8; 3], c: [i8; 3]) -> Vertex {
-        Vertex {
-            position: [p[0] as f32, p[1] as f32, p[2] as f32, 1.0],
-            color: [c[0] as f32, c[1] as f32, c[2] as f32, 1.0],
+impl Indices {
+    pub fn format(&self) -> IndexFormat {
+        match self {
+            Indices::U16(_) => IndexFormat::Uint16,
+            Indices::U32(_) => IndexFormat::Uint32,
         }
     }
-}
 
-impl Vertex {
-    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
-        wgpu::vertex_attr_array![0=>Float32x4, 1=>Float32x4];
-    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => bytemuck::cast_slice(indices),
+            Indices::U32(indices) => bytemuck::cast_slice(indices),
         }
     }
 }
@@ -128,48 +124,128 @@ pub struct RendererAttributes {
     pub indices: Option<Vec<u16>>,
     pub camera_position: Vec3,
     pub instances: Option<Vec<Instance>>,
+    /// Use a software adapter, for rendering on machines without a GPU.
+    pub force_fallback_adapter: bool,
+    /// Wait for the vertical blank to present, which caps the frame rate at the refresh rate of the
+    /// display. Without it frames are presented immediately and may tear.
+    pub vsync: bool,
 }
 
 impl Default for RendererAttributes {
     fn default() -> Self {
         Self {
-            source: include_str!(
-            "../../../assets/cube_face_color.wgsl"
-        ),
+            source: DEFAULT_SHADER,
             topology: Default::default(),
             strip_index_format: Default::default(),
             vertices: Default::default(),
             indices: Default::default(),
             camera_position: Vec3::new(0.5, 0.5, 0.5),
             instances: Default::default(),
+            force_fallback_adapter: false,
+            vsync: false,
+        }
+    }
+}
+
+/// A mesh for [`WGPURenderer::add_mesh`], with the shader and topology it is drawn with.
+pub struct MeshAttributes {
+    pub source: &'static str,
+    pub topology: PrimitiveTopology,
+    /// The vertices in any layout, the pipeline of the mesh is built for it.
+    pub vertices: VertexData,
+    /// Draw the vertices in the order of the indices, or in their own order when `None`.
+    pub indices: Option<Indices>,
+    /// The part of the indices to draw, or of the vertices without indices. All of them when `None`.
+    pub range: Option<Range<u32>>,
+    /// Added to every index before its vertex is fetched.
+    pub base_vertex: i32,
+    /// Draw the mesh once for every instance, or once without transformation when `None`.
+    pub instances: Option<Vec<Instance>>,
+    /// Custom attributes of every instance, in a layout of [`VertexLayout::per_instance`].
+    pub instance_attributes: Option<VertexData>,
+}
+
+impl Default for MeshAttributes {
+    fn default() -> Self {
+        Self {
+            source: DEFAULT_SHADER,
+            topology: Default::default(),
+            vertices: Default::default(),
+            indices: Default::default(),
+            range: Default::default(),
+            base_vertex: 0,
+            instances: Default::default(),
+            instance_attributes: Default::default(),
+        }
+    }
+}
+
+impl MeshAttributes {
+    // Invalid layouts make wgpu panic, and draws that fetch past the indices, vertices or instance
+    // attributes are not caught by it.
+    fn validate(&self) -> crate::Result<()> {
+        self.vertices.layout.validate()?;
+        if let Some(instance_attributes) = &self.instance_attributes {
+            instance_attributes.layout.validate()?;
+            self.vertices.layout.validate_disjoint(&instance_attributes.layout)?;
+            let instances = self.instances.as_ref().map_or(1, Vec::len);
+            if instance_attributes.len() != instances {
+                return Err(Error::Render(format!(
+                    "A mesh has {} instances but attributes for {}",
+                    instances,
+                    instance_attributes.len()
+                )));
+            }
         }
+        let elements = self.indices.as_ref().map_or(self.vertices.len(), Indices::len);
+        mesh::validate_draw(
+            self.topology,
+            self.vertices.len(),
+            self.indices.as_ref(),
+            &self.range.clone().unwrap_or(0..elements as u32),
+            self.base_vertex,
+        )
     }
 }
 
 pub struct WGPURenderer {
-    pub device: wgpu::Device,
-    pub surface: wgpu::Surface,
-    pub queue: wgpu::Queue,
+    pub device: Arc<wgpu::Device>,
+    pub target: RenderTarget,
+    pub queue: Arc<wgpu::Queue>,
     pub config: wgpu::SurfaceConfiguration,
     pub clear_color: wgpu::Color,
-    pub shader: wgpu::ShaderModule,
-    pub render_pipeline: wgpu::RenderPipeline,
-    pub vertex_buffer: Option<wgpu::Buffer>,
-    pub vertices_len: usize,
-    pub index_buffer: Option<wgpu::Buffer>,
-    pub indices_len: usize,
+    pub pipeline_layout: wgpu::PipelineLayout,
+    /// Render pipelines by shader and topology, shared by the meshes drawn the same way. The first
+    /// one is that of the renderer attributes.
+    pub pipelines: Vec<Pipeline>,
     pub uniform_buffer: wgpu::Buffer,
     pub uniform_bind_group: wgpu::BindGroup,
     pub model_mat: Mat4,
     pub size: (i32, i32),
-    pub num_instances: Option<u32>,
+    /// Instances of the mesh of the renderer attributes, uploaded with [`WGPURenderer::write_instances`].
     pub instances: Option<Vec<Instance>>,
-    pub instance_buffer: Option<wgpu::Buffer>,
+    // The mesh of the renderer attributes, if they have vertices.
+    scene_mesh: Option<MeshId>,
     pub camera: Camera,
     pub projection: camera::Projection,
     pub camera_uniform: CameraUniform,
     pub camera_buffer: wgpu::Buffer,
     pub camera_bind_group: wgpu::BindGroup,
+    pub recording: Option<capture::Recording>,
+    // Draws recorded frames to the window, created with the first one.
+    blit: Option<target::Blit>,
+    pub depth_texture: wgpu::Texture,
+    pub depth_view: wgpu::TextureView,
+    /// The window has no area to render to, frames are skipped until it is resized.
+    pub minimized: bool,
+    /// Physical pixels per logical pixel of the window, to size text and UI drawn in logical pixels.
+    /// The render target itself is always in physical pixels.
+    pub scale_factor: f64,
+    /// Every mesh of the renderer. Visible meshes are drawn each frame, the others only when queued
+    /// with [`Renderer::draw`].
+    pub meshes: Vec<scene::Mesh>,
+    /// Meshes to draw in the next frame, after the visible meshes.
+    pub draws: Vec<MeshId>,
 }
 
 impl WGPURenderer {
@@ -182,48 +258,110 @@ impl WGPURenderer {
         window: &Window,
         renderer_attributes: RendererAttributes,
     ) -> WGPURenderer {
-        let size = window.window_attributes.size;
-        let width = size.0;
-        let height = size.1;
-        let instance = wgpu::Instance::new(wgpu::Backends::all());
-        let surface = unsafe { instance.create_surface(window) };
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
+        let gpu = Gpu::new(Some(window), renderer_attributes.force_fallback_adapter)
             .await
-            .expect("Failed to find an appropriate adapter");
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: Some("Device Features"),
-                    // https://docs.rs/wgpu/0.12.0/wgpu/struct.Features.html
-                    features: wgpu::Features::empty(),
-                    // https://docs.rs/wgpu/0.12.0/wgpu/struct.Limits.html
-                    limits: if cfg!(target_arch = "wasm32") {
-                        // This is a set of limits that is lower even than the [downlevel_defaults()],
-                        // configured to be low enough to support running in the browser using WebGL2.
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                },
-                None,
-            )
-            .await
-            .expect("Failed to create device");
+            .expect("Failed to find an appropriate adapter for the window");
+        Self::new_with_gpu(&gpu, window, renderer_attributes)
+            .expect("Failed to create a surface for the window")
+    }
+
+    /// Create a renderer for `window` on a shared [`Gpu`], to render to several windows from one device.
+    /// Fails for a headless window, which has no surface.
+    pub fn new_with_gpu(
+        gpu: &Gpu,
+        window: &Window,
+        renderer_attributes: RendererAttributes,
+    ) -> crate::Result<WGPURenderer> {
+        gpu::check_presentable(window)?;
+        let size = window.window_attributes.size;
+        // A swap chain can not be configured without area, a minimized window is resized later
+        let width = size.0.max(1);
+        let height = size.1.max(1);
+        let surface = unsafe { gpu.instance.create_surface(window) };
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_preferred_format(&adapter).unwrap(),
+            format: surface.get_preferred_format(&gpu.adapter).unwrap(),
             width: width as u32,
             height: height as u32,
             // https://docs.rs/wgpu/0.12.0/wgpu/enum.PresentMode.html
-            present_mode: wgpu::PresentMode::Immediate,
+            present_mode: match renderer_attributes.vsync {
+                true => wgpu::PresentMode::Fifo,
+                false => wgpu::PresentMode::Immediate,
+            },
         };
         // main window swap chain
-        surface.configure(&device, &config);
+        surface.configure(&gpu.device, &config);
+
+        let mut renderer = Self::new_with_target(
+            gpu.device.clone(),
+            gpu.queue.clone(),
+            config,
+            RenderTarget::Surface(surface),
+            renderer_attributes,
+        );
+        renderer.scale_factor = window.scale_factor();
+        Ok(renderer)
+    }
+
+    /// Create a renderer without a window, that renders into an owned RGBA8 texture of the given size.
+    /// Frames are read back with [`WGPURenderer::read_pixels`].
+    pub async fn new_offscreen(
+        size: (i32, i32),
+        renderer_attributes: RendererAttributes,
+    ) -> WGPURenderer {
+        Self::try_new_offscreen(size, renderer_attributes)
+            .await
+            .expect("Failed to find an appropriate adapter")
+    }
+
+    /// Like [`WGPURenderer::new_offscreen`], but returns an error when no suitable adapter is available.
+    pub async fn try_new_offscreen(
+        size: (i32, i32),
+        renderer_attributes: RendererAttributes,
+    ) -> crate::Result<WGPURenderer> {
+        let gpu = Gpu::new(None, renderer_attributes.force_fallback_adapter).await?;
+        Ok(Self::new_offscreen_with_gpu(&gpu, size, renderer_attributes))
+    }
+
+    /// Create an offscreen renderer on a shared [`Gpu`].
+    pub fn new_offscreen_with_gpu(
+        gpu: &Gpu,
+        size: (i32, i32),
+        renderer_attributes: RendererAttributes,
+    ) -> WGPURenderer {
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: target::OFFSCREEN_FORMAT,
+            width: size.0 as u32,
+            height: size.1 as u32,
+            present_mode: wgpu::PresentMode::Immediate,
+        };
+        let texture = target::create_offscreen_texture(
+            &gpu.device,
+            config.width,
+            config.height,
+            config.format,
+        );
+
+        Self::new_with_target(
+            gpu.device.clone(),
+            gpu.queue.clone(),
+            config,
+            RenderTarget::Offscreen(texture),
+            renderer_attributes,
+        )
+    }
+
+    fn new_with_target(
+        device: Arc<wgpu::Device>,
+        queue: Arc<wgpu::Queue>,
+        config: wgpu::SurfaceConfiguration,
+        target: RenderTarget,
+        renderer_attributes: RendererAttributes,
+    ) -> WGPURenderer {
+        let size = (config.width as i32, config.height as i32);
+        let (depth_texture, depth_view) =
+            target::create_depth_texture(&device, config.width, config.height);
 
         // TODO: Zig also defines a buffer_pool, texture_pool, and render_pipeline_pool
 
@@ -235,67 +373,6 @@ impl WGPURenderer {
             a: 1.0,
         };
 
-        // LOAD SHADER ///////////////////////////////////////////////////////////////////////////////////////
-        
-        let shader_source = wgpu::ShaderSource::Wgsl(Cow::Borrowed(renderer_attributes.source));
-        
-        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: shader_source,
-        });
-
-
-        
-        ///////////////////////////////////////////////////////////////////////////////////////////////////////
-        // VERTEX BUFFER
-
-        let mut vertex_buffer = None;
-        let mut vertices_len: usize = 9;
-        if let Some(vertices) = renderer_attributes.vertices {
-            vertex_buffer = Some(
-                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Vertex Buffer"),
-                    contents: crate::utils::as_bytes(vertices.as_slice()), // Everybody uses the Bytemuck crate here
-                    usage: wgpu::BufferUsages::VERTEX,
-                }),
-            );
-            vertices_len = vertices.len();
-        }
-
-
-        ////////////////////////////////////////////////////////////////////////////////////////////////////////
-        // INDEX BUFFER
-
-        let mut index_buffer = None;
-        let mut indices_len = 0;
-
-        if let Some(indices) = renderer_attributes.indices {
-            index_buffer = Some(
-                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Index Buffer"),
-                    contents: crate::utils::as_bytes(indices.as_slice()),
-                    usage: wgpu::BufferUsages::INDEX,
-                }),
-            );
-            indices_len = indices.len();
-        }
-
-        ///////////////////////////////////////////////////////////////////////////////////////////////////////////
-        // INSTANCE BUFFER
-        let mut num_instances = None;
-        let mut instance_buffer = None;
-        if let Some(instances) = renderer_attributes.instances.as_ref() {
-            num_instances = Some(instances.len() as u32);
-            let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
-            instance_buffer = Some(
-                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Instance Buffer"),
-                    contents: bytemuck::cast_slice(&instance_data),
-                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                }),
-            );
-        }
-
         /////////////////////////////////////////////////////////////////////////////////////////////////////
         // uniform buffer - global transformation
         let model_mat = math::create_transforms([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
@@ -333,21 +410,8 @@ impl WGPURenderer {
 
         ////////////////////////////////////////////////////////////////////////////////////////////////////////////////
         // uniform buffer - Camera
-        let camera = Camera::new(
-            renderer_attributes.camera_position,
-            -90.0_f32.to_radians(),
-            -20.0_f32.to_radians(),
-            0.1,
-            0.001,
-        );
-
-        let projection = camera::Projection::new(
-            config.width,
-            config.height,
-            45.0_f32.to_radians(),
-            0.1,
-            100.0,
-        );
+        let (camera, projection) =
+            camera::default_view(renderer_attributes.camera_position, config.width, config.height);
 
         let mut camera_uniform = CameraUniform::new();
         camera_uniform.update_view_proj(&camera, &projection);
@@ -389,69 +453,59 @@ impl WGPURenderer {
             push_constant_ranges: &[],
         });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc(), InstanceRaw::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                }],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: renderer_attributes.topology,
-                strip_index_format: renderer_attributes.strip_index_format,
-                cull_mode: Some(wgpu::Face::Back),
-                ..Default::default()
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth24Plus,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::LessEqual,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-        });
-
-        let wgpu_renderer = WGPURenderer {
+        let mut wgpu_renderer = WGPURenderer {
             device,
-            surface,
+            target,
             queue,
-            shader,
-            render_pipeline,
             config,
-            vertex_buffer,
-            vertices_len,
-            index_buffer,
-            indices_len,
+            pipeline_layout,
+            pipelines: Vec::new(),
             uniform_buffer,
             uniform_bind_group,
             model_mat,
             clear_color,
             size,
-            num_instances,
-            instance_buffer,
             instances: renderer_attributes.instances,
+            scene_mesh: None,
             camera,
             projection,
             camera_uniform,
             camera_buffer,
             camera_bind_group,
+            recording: None,
+            blit: None,
+            depth_texture,
+            depth_view,
+            minimized: false,
+            scale_factor: 1.0,
+            meshes: Vec::new(),
+            draws: Vec::new(),
         };
 
+        // The pipeline of the renderer attributes comes first, meshes of the `Renderer` trait use it
+        wgpu_renderer.pipeline(PipelineKey {
+            source: renderer_attributes.source,
+            topology: renderer_attributes.topology,
+            strip_index_format: renderer_attributes.strip_index_format,
+            vertex_layout: Vertex::layout(),
+            instance_layout: None,
+        });
+        if let Some(vertices) = renderer_attributes.vertices {
+            let mut mesh = scene::Mesh::new(
+                &wgpu_renderer.device,
+                0,
+                &vertices.into(),
+                renderer_attributes.indices.map(Indices::U16).as_ref(),
+            );
+            if let Some(instances) = &wgpu_renderer.instances {
+                mesh.set_instances(&wgpu_renderer.device, &wgpu_renderer.queue, instances)
+                    .expect("The mesh of the renderer attributes has no instance attributes");
+            }
+            mesh.visible = true;
+            wgpu_renderer.meshes.push(mesh);
+            wgpu_renderer.scene_mesh = Some(MeshId(0));
+        }
+
         wgpu_renderer
     }
 
@@ -463,12 +517,95 @@ impl WGPURenderer {
         );
     }
 
-    pub fn write_instances(&mut self, data: Vec<InstanceRaw>) {
-        self.queue.write_buffer(
-            &self.instance_buffer.as_ref().unwrap(),
-            0,
-            bytemuck::cast_slice(&data),
-        );
+    pub fn write_instances(&mut self, data: Vec<InstanceRaw>) -> crate::Result<()> {
+        match self.scene_mesh {
+            Some(mesh) => self.meshes[mesh.0].write_instances(&self.device, &self.queue, &data),
+            None => Ok(()),
+        }
+    }
+
+    /// Draw only `range` of the indices of `mesh`, or of its vertices without indices. Fails when
+    /// the range reaches past them, or draws an index that fetches past the vertices.
+    pub fn set_range(&mut self, mesh: MeshId, range: Range<u32>) -> crate::Result<()> {
+        let topology = self.pipelines[self.meshes[mesh.0].pipeline].key.topology;
+        let mesh = &mut self.meshes[mesh.0];
+        mesh::validate_draw(
+            topology,
+            mesh.vertices_len,
+            mesh.indices.as_ref(),
+            &range,
+            mesh.base_vertex,
+        )?;
+        mesh.range = range;
+        Ok(())
+    }
+
+    /// Replace the instances of `mesh` together with their custom attributes, which may change
+    /// their number. Fails when the attributes are not one for every instance.
+    pub fn set_instances_with_attributes(
+        &mut self,
+        mesh: MeshId,
+        instances: &[Instance],
+        instance_attributes: &VertexData,
+    ) -> crate::Result<()> {
+        self.meshes[mesh.0].set_instances_with_attributes(
+            &self.device,
+            &self.queue,
+            instances,
+            instance_attributes,
+        )
+    }
+
+    /// Register a mesh that is drawn every frame, with the pipeline of its shader and topology.
+    /// Meshes are drawn in the order they were added. Fails when a layout of the mesh is not valid,
+    /// its instance attributes do not match its instances, or its range or base vertex reach past
+    /// its indices or vertices.
+    pub fn add_mesh(&mut self, mesh_attributes: MeshAttributes) -> crate::Result<MeshId> {
+        mesh_attributes.validate()?;
+        let indices = mesh_attributes.indices.as_ref();
+        // Strips restart at the largest index, which depends on the index format
+        let strip_index_format = match mesh_attributes.topology.is_strip() {
+            true => indices.map(Indices::format),
+            false => None,
+        };
+        let pipeline = self.pipeline(PipelineKey {
+            source: mesh_attributes.source,
+            topology: mesh_attributes.topology,
+            strip_index_format,
+            vertex_layout: mesh_attributes.vertices.layout.clone(),
+            instance_layout: mesh_attributes
+                .instance_attributes
+                .as_ref()
+                .map(|instance_attributes| instance_attributes.layout.clone()),
+        });
+        let mut mesh = scene::Mesh::new(&self.device, pipeline, &mesh_attributes.vertices, indices);
+        if let Some(range) = mesh_attributes.range {
+            mesh.range = range;
+        }
+        mesh.base_vertex = mesh_attributes.base_vertex;
+        if let Some(instances) = &mesh_attributes.instances {
+            mesh.set_instances(&self.device, &self.queue, instances)?;
+        }
+        if let Some(instance_attributes) = &mesh_attributes.instance_attributes {
+            mesh.set_instance_attributes(&self.device, instance_attributes)?;
+        }
+        mesh.visible = true;
+        self.meshes.push(mesh);
+        Ok(MeshId(self.meshes.len() - 1))
+    }
+
+    // The index of the pipeline for `key`, created on first use.
+    fn pipeline(&mut self, key: PipelineKey) -> usize {
+        if let Some(index) = self.pipelines.iter().position(|pipeline| pipeline.key == key) {
+            return index;
+        }
+        self.pipelines.push(Pipeline::new(
+            &self.device,
+            &self.pipeline_layout,
+            self.config.format,
+            key,
+        ));
+        self.pipelines.len() - 1
     }
 
     pub fn write_camera(&mut self, data: &[CameraUniform]) {
@@ -479,27 +616,114 @@ impl WGPURenderer {
         );
     }
 
+    /// Resize the render target, depth buffer and camera projection when the window size changed,
+    /// to the physical size of the window. Call this every frame, it does nothing while the window
+    /// keeps its size.
+    pub fn resize_to_window(&mut self, window: &Window) {
+        self.scale_factor = window.scale_factor();
+        let (width, height) = window.window_attributes.size;
+        self.resize(width.max(0) as u32, height.max(0) as u32);
+    }
+
+    /// Resize the render target, depth buffer and camera projection.
+    /// A zero width or height, like that of a minimized window, pauses rendering until the next resize.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            self.minimized = true;
+            return;
+        }
+        self.minimized = false;
+        if (width, height) == (self.config.width, self.config.height) {
+            return;
+        }
+
+        self.config.width = width;
+        self.config.height = height;
+        self.size = (width as i32, height as i32);
+        match &mut self.target {
+            RenderTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+            RenderTarget::Offscreen(texture) => {
+                *texture =
+                    target::create_offscreen_texture(&self.device, width, height, self.config.format)
+            }
+        }
+        let (depth_texture, depth_view) = target::create_depth_texture(&self.device, width, height);
+        self.depth_texture = depth_texture;
+        self.depth_view = depth_view;
+        self.projection.resize(width, height);
+        self.camera_uniform.update_view_proj(&self.camera, &self.projection);
+        self.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera_uniform]),
+        );
+    }
+
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        // Later should just take a closure
-        let frame = self.surface.get_current_texture()?;
-        let view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-        let depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            size: wgpu::Extent3d {
-                width: self.config.width,
-                height: self.config.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth24Plus,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            label: None,
-        });
-        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        if self.minimized {
+            return Ok(());
+        }
+        let recorded = self
+            .recording
+            .as_mut()
+            .and_then(capture::Recording::next_frame);
+        if recorded.is_some() && self.blit.is_none() {
+            if let RenderTarget::Surface(_) = self.target {
+                self.blit = Some(target::Blit::new(&self.device, self.config.format));
+            }
+        }
 
+        let pixels = match &self.target {
+            RenderTarget::Surface(surface) => {
+                let frame = match surface.get_current_texture() {
+                    // The swap chain no longer matches the window, reconfigure it and try once more
+                    Err(wgpu::SurfaceError::Lost) | Err(wgpu::SurfaceError::Outdated) => {
+                        surface.configure(&self.device, &self.config);
+                        surface.get_current_texture()?
+                    }
+                    frame => frame?,
+                };
+                let view = frame
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                let pixels = match (&recorded, &self.blit) {
+                    // The swap chain can not be copied from, render into a texture and draw it to the window
+                    (Some(_), Some(blit)) => {
+                        let texture = target::create_offscreen_texture(
+                            &self.device,
+                            self.config.width,
+                            self.config.height,
+                            self.config.format,
+                        );
+                        let texture_view =
+                            texture.create_view(&wgpu::TextureViewDescriptor::default());
+                        self.render_to(&texture_view);
+                        blit.draw(&self.device, &self.queue, &texture, &view);
+                        Some(self.read_texture(&texture))
+                    }
+                    _ => {
+                        self.render_to(&view);
+                        None
+                    }
+                };
+                frame.present();
+                pixels
+            }
+            RenderTarget::Offscreen(texture) => {
+                self.render_to(&texture.create_view(&wgpu::TextureViewDescriptor::default()));
+                recorded.as_ref().map(|_| self.read_texture(texture))
+            }
+        };
+        if let (Some(path), Some(pixels)) = (recorded, pixels) {
+            self.record_frame(&path, &pixels);
+        }
+        self.draws.clear();
+
+        Ok(())
+    }
+
+    // Render the scene into `view` and submit it to the queue.
+    fn render_to(&self, view: &wgpu::TextureView) {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -511,7 +735,7 @@ impl WGPURenderer {
                 label: Some("Render Pass"),
                 // This is what [[location(0)]] in the fragment shader targets
                 color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(self.clear_color),
@@ -519,7 +743,7 @@ impl WGPURenderer {
                     },
                 }],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &depth_view,
+                    view: &self.depth_view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: false,
@@ -528,35 +752,229 @@ impl WGPURenderer {
                 }),
             });
 
-            // RENDER PIPELINE
-            render_pass.set_pipeline(&self.render_pipeline);
+            // The bind groups are shared by all pipelines
             render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
             render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
 
-            if let Some(vertex_buffer) = &self.vertex_buffer {
-                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            let visible = self.meshes.iter().filter(|mesh| mesh.visible);
+            let queued = self.draws.iter().map(|mesh| &self.meshes[mesh.0]);
+            for mesh in visible.chain(queued) {
+                render_pass.set_pipeline(&self.pipelines[mesh.pipeline].render_pipeline);
+                mesh.draw(&mut render_pass);
             }
-            if let Some(instance_buffer) = &self.instance_buffer {
-                render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        }
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Copy the last rendered frame back to the CPU as RGBA8 pixels, row by row from the top.
+    /// Returns `None` when rendering to a window surface, which can not be read back.
+    pub fn read_pixels(&self) -> Option<Vec<u8>> {
+        match &self.target {
+            RenderTarget::Surface(_) => None,
+            RenderTarget::Offscreen(texture) => Some(self.read_texture(texture)),
+        }
+    }
+
+    /// Render the current scene and copy it back to the CPU as RGBA8 pixels, row by row from the top.
+    /// Works for both window surfaces and offscreen targets.
+    pub fn capture_frame(&self) -> Vec<u8> {
+        match &self.target {
+            RenderTarget::Offscreen(texture) => {
+                self.render_to(&texture.create_view(&wgpu::TextureViewDescriptor::default()));
+                self.read_texture(texture)
             }
-            if let Some(index_buffer) = &self.index_buffer {
-                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.draw_indexed(
-                    0..self.indices_len as u32,
-                    0,
-                    0..self.num_instances.unwrap_or(1),
+            // The swap chain can not be copied from, render the scene once more into a texture
+            RenderTarget::Surface(_) => {
+                let texture = target::create_offscreen_texture(
+                    &self.device,
+                    self.config.width,
+                    self.config.height,
+                    self.config.format,
                 );
+                self.render_to(&texture.create_view(&wgpu::TextureViewDescriptor::default()));
+                self.read_texture(&texture)
             }
-            
-            // } else {
-            //     render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-            //     // This is where [[builtin(vertex_index)]] comes from
-            //     render_pass.draw(0..self.vertices_len as u32, 0..1);
-            // }
         }
-        self.queue.submit(Some(encoder.finish()));
-        frame.present();
+    }
+
+    /// Render the current scene to a PNG file.
+    pub fn save_screenshot<P: AsRef<Path>>(&self, path: P) -> crate::Result<()> {
+        let pixels = self.capture_frame();
+        capture::write_png(path, self.config.width, self.config.height, &pixels)
+    }
 
+    /// Write every `every_nth_frame`-th rendered frame to `directory` as a numbered PNG sequence,
+    /// until [`WGPURenderer::stop_recording`] is called.
+    pub fn start_recording<P: AsRef<Path>>(
+        &mut self,
+        directory: P,
+        every_nth_frame: u32,
+    ) -> crate::Result<()> {
+        self.recording = Some(capture::Recording::new(directory, every_nth_frame)?);
         Ok(())
     }
+
+    /// Stop recording frames, returns the finished recording.
+    pub fn stop_recording(&mut self) -> Option<capture::Recording> {
+        self.recording.take()
+    }
+
+    fn record_frame(&mut self, path: &Path, pixels: &[u8]) {
+        if let Err(error) = capture::write_png(path, self.config.width, self.config.height, pixels)
+        {
+            log::error!("{:?}, recording stopped", error);
+            self.recording = None;
+        }
+    }
+
+    // Copy a texture of the size and format of the render target back to the CPU.
+    fn read_texture(&self, texture: &wgpu::Texture) -> Vec<u8> {
+        target::read_texture(
+            &self.device,
+            &self.queue,
+            texture,
+            self.config.format,
+            self.config.width,
+            self.config.height,
+        )
+    }
+}
+
+impl Renderer for WGPURenderer {
+    fn create_mesh(
+        &mut self,
+        vertices: &[Vertex],
+        indices: Option<Indices>,
+    ) -> crate::Result<MeshId> {
+        // Drawn like the mesh of the renderer attributes, but strips restart at the largest index
+        // of their own index format
+        let mut key = self.pipelines[0].key.clone();
+        let elements = indices.as_ref().map_or(vertices.len(), Indices::len);
+        mesh::validate_draw(
+            key.topology,
+            vertices.len(),
+            indices.as_ref(),
+            &(0..elements as u32),
+            0,
+        )?;
+        if key.topology.is_strip() {
+            key.strip_index_format = indices.as_ref().map(Indices::format);
+        }
+        let pipeline = self.pipeline(key);
+        self.meshes.push(scene::Mesh::new(
+            &self.device,
+            pipeline,
+            &vertices.into(),
+            indices.as_ref(),
+        ));
+        Ok(MeshId(self.meshes.len() - 1))
+    }
+
+    fn set_instances(&mut self, mesh: MeshId, instances: &[Instance]) -> crate::Result<()> {
+        self.meshes[mesh.0].set_instances(&self.device, &self.queue, instances)
+    }
+
+    fn set_camera(&mut self, camera: &Camera) {
+        self.camera = camera.clone();
+        self.camera_uniform
+            .update_view_proj(&self.camera, &self.projection);
+        self.write_camera(&[self.camera_uniform]);
+    }
+
+    fn draw(&mut self, mesh: MeshId) {
+        self.draws.push(mesh);
+    }
+
+    fn present(&mut self) -> crate::Result<()> {
+        self.render()
+            .map_err(|error| Error::Render(format!("Failed to present a frame: {}", error)))
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        WGPURenderer::resize(self, width, height);
+    }
+
+    fn resize_to_window(&mut self, window: &Window) {
+        WGPURenderer::resize_to_window(self, window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Indices, Instance, MeshAttributes, Vertex, VertexData, VertexLayout};
+    use glam::{Quat, Vec3};
+    use std::ops::Range;
+    use wgpu::{PrimitiveTopology, VertexFormat};
+
+    // Four vertices, drawn through `indices`
+    fn validate(
+        indices: Option<Indices>,
+        range: Option<Range<u32>>,
+        base_vertex: i32,
+    ) -> crate::Result<()> {
+        MeshAttributes {
+            topology: PrimitiveTopology::TriangleStrip,
+            vertices: vec![Vertex::vertex([0, 0, 0], [0, 0, 0]); 4].into(),
+            indices,
+            range,
+            base_vertex,
+            ..Default::default()
+        }
+        .validate()
+    }
+
+    #[test]
+    fn ranges_stay_within_indices_or_vertices() {
+        assert!(validate(None, None, 0).is_ok());
+        assert!(validate(None, Some(1..4), 0).is_ok());
+        assert!(validate(None, Some(1..5), 0).is_err());
+        assert!(validate(None, Some(Range { start: 3, end: 1 }), 0).is_err());
+        let indices = Indices::U32(vec![0, 1, 2, 3, 2, 1]);
+        assert!(validate(Some(indices.clone()), Some(0..6), 0).is_ok());
+        assert!(validate(Some(indices), Some(0..7), 0).is_err());
+    }
+
+    #[test]
+    fn base_vertex_keeps_drawn_indices_within_vertices() {
+        let indices = Indices::U16(vec![0, 1, 2, 3, 9]);
+        assert!(validate(Some(indices.clone()), Some(0..3), 1).is_ok());
+        assert!(validate(Some(indices.clone()), Some(0..4), 1).is_err());
+        assert!(validate(Some(indices.clone()), Some(1..4), -1).is_ok());
+        assert!(validate(Some(indices.clone()), Some(0..4), -1).is_err());
+        assert!(validate(Some(indices), None, 0).is_err());
+        assert!(validate(None, None, 1).is_err());
+    }
+
+    #[test]
+    fn instance_attributes_match_the_instances() {
+        let tints = |count: usize| {
+            let layout = VertexLayout::per_instance().with_attribute(9, VertexFormat::Float32x4);
+            Some(VertexData::new(layout, &vec![[1.0_f32; 4]; count]).unwrap())
+        };
+        let instances = |count: usize| {
+            let instances = (0..count).map(|_| Instance {
+                position: Vec3::ZERO,
+                rotation: Quat::IDENTITY,
+            });
+            Some(instances.collect())
+        };
+        let validate = |instances, instance_attributes| {
+            MeshAttributes {
+                instances,
+                instance_attributes,
+                ..Default::default()
+            }
+            .validate()
+        };
+        assert!(validate(None, tints(1)).is_ok());
+        assert!(validate(None, tints(2)).is_err());
+        assert!(validate(instances(3), tints(3)).is_ok());
+        assert!(validate(instances(3), tints(2)).is_err());
+    }
+
+    #[test]
+    fn strip_restarts_fetch_no_vertex() {
+        let indices = Indices::U16(vec![0, 1, u16::MAX, 2, 3]);
+        assert!(validate(Some(indices), None, 0).is_ok());
+    }
 }
@@ -0,0 +1,81 @@
+use super::{InstanceRaw, VertexLayout};
+use std::borrow::Cow;
+use wgpu::{IndexFormat, PrimitiveTopology};
+
+/// What makes meshes draw differently: their shader, primitive topology and vertex layouts. Meshes
+/// drawn the same way share one render pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineKey {
+    pub source: &'static str,
+    pub topology: PrimitiveTopology,
+    pub strip_index_format: Option<IndexFormat>,
+    pub vertex_layout: VertexLayout,
+    /// Custom per-instance attributes, read from a buffer next to the model matrices.
+    pub instance_layout: Option<VertexLayout>,
+}
+
+pub struct Pipeline {
+    pub key: PipelineKey,
+    pub shader: wgpu::ShaderModule,
+    pub render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Pipeline {
+    /// Compile the shader of `key` and create a pipeline that renders into `format` with a depth buffer.
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        format: wgpu::TextureFormat,
+        key: PipelineKey,
+    ) -> Pipeline {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(key.source)),
+        });
+
+        let mut buffers = vec![key.vertex_layout.desc(), InstanceRaw::desc()];
+        buffers.extend(key.instance_layout.as_ref().map(VertexLayout::desc));
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent::REPLACE,
+                        alpha: wgpu::BlendComponent::REPLACE,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: key.topology,
+                strip_index_format: key.strip_index_format,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth24Plus,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Pipeline {
+            key,
+            shader,
+            render_pipeline,
+        }
+    }
+}
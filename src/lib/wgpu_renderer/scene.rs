@@ -1,7 +1,211 @@
+use super::{Indices, Instance, InstanceRaw, VertexData};
+use crate::error::Error;
+use glam::{Quat, Vec3};
+use std::ops::Range;
+use wgpu::util::DeviceExt;
+
 pub struct Drawable {
 
 }
 
+/// A mesh in GPU buffers, drawn once for every instance.
 pub struct Mesh {
+    /// Index of the render pipeline in [`super::WGPURenderer::pipelines`].
+    pub pipeline: usize,
+    /// Draw the mesh every frame, not only when queued.
+    pub visible: bool,
+    pub vertex_buffer: wgpu::Buffer,
+    pub vertices_len: usize,
+    pub index_buffer: Option<wgpu::Buffer>,
+    pub index_format: wgpu::IndexFormat,
+    pub indices_len: usize,
+    // Kept to check the indices a new range draws
+    pub(super) indices: Option<Indices>,
+    pub(super) range: Range<u32>,
+    pub(super) base_vertex: i32,
+    pub instance_buffer: wgpu::Buffer,
+    num_instances: u32,
+    // Custom attributes of the instances in the instance layout of the pipeline, always as many as
+    // there are instances.
+    instance_attribute_buffer: Option<wgpu::Buffer>,
+}
+
+impl Mesh {
+    /// Upload `vertices` and `indices`, with a single instance at the origin. The mesh starts hidden,
+    /// and draws all of its indices or vertices.
+    pub fn new(
+        device: &wgpu::Device,
+        pipeline: usize,
+        vertices: &VertexData,
+        indices: Option<&Indices>,
+    ) -> Mesh {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Vertex Buffer"),
+            contents: &vertices.bytes,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = indices.map(|indices| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Mesh Index Buffer"),
+                contents: indices.as_bytes(),
+                usage: wgpu::BufferUsages::INDEX,
+            })
+        });
+        let origin = Instance {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+        };
+        let indices_len = indices.map_or(0, Indices::len);
+        let range = match indices {
+            Some(_) => 0..indices_len as u32,
+            None => 0..vertices.len() as u32,
+        };
+        Mesh {
+            pipeline,
+            visible: false,
+            vertex_buffer,
+            vertices_len: vertices.len(),
+            index_buffer,
+            index_format: indices.map_or(wgpu::IndexFormat::Uint16, Indices::format),
+            indices_len,
+            indices: indices.cloned(),
+            range,
+            base_vertex: 0,
+            instance_buffer: create_instance_buffer(device, &[origin.to_raw()]),
+            num_instances: 1,
+            instance_attribute_buffer: None,
+        }
+    }
+
+    /// The part of the indices that is drawn, or of the vertices without indices.
+    pub fn range(&self) -> Range<u32> {
+        self.range.clone()
+    }
+
+    /// Added to every index before its vertex is fetched, for indexed meshes.
+    pub fn base_vertex(&self) -> i32 {
+        self.base_vertex
+    }
+
+    pub fn num_instances(&self) -> u32 {
+        self.num_instances
+    }
+
+    /// Replace the instances, the buffer is only recreated when their number changes. Fails when
+    /// the mesh has instance attributes for a different number of instances, replace both with
+    /// [`Mesh::set_instances_with_attributes`] instead.
+    pub fn set_instances(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[Instance],
+    ) -> crate::Result<()> {
+        let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        self.write_instances(device, queue, &instance_data)
+    }
+
+    /// Like [`Mesh::set_instances`], for instances already in their GPU layout.
+    pub fn write_instances(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instance_data: &[InstanceRaw],
+    ) -> crate::Result<()> {
+        if self.instance_attribute_buffer.is_some()
+            && instance_data.len() as u32 != self.num_instances
+        {
+            return Err(Error::Render(format!(
+                "A mesh has attributes for {} instances but gets {} instances",
+                self.num_instances,
+                instance_data.len()
+            )));
+        }
+        if instance_data.len() as u32 == self.num_instances {
+            queue.write_buffer(
+                &self.instance_buffer,
+                0,
+                bytemuck::cast_slice(instance_data),
+            );
+        } else {
+            self.instance_buffer = create_instance_buffer(device, instance_data);
+            self.num_instances = instance_data.len() as u32;
+        }
+        Ok(())
+    }
+
+    /// Replace the custom instance attributes, which must be laid out as the pipeline expects.
+    /// Fails when they are not one for every instance.
+    pub fn set_instance_attributes(
+        &mut self,
+        device: &wgpu::Device,
+        instance_attributes: &VertexData,
+    ) -> crate::Result<()> {
+        if instance_attributes.len() as u32 != self.num_instances {
+            return Err(Error::Render(format!(
+                "A mesh has {} instances but gets attributes for {}",
+                self.num_instances,
+                instance_attributes.len()
+            )));
+        }
+        self.instance_attribute_buffer = Some(device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Mesh Instance Attribute Buffer"),
+                contents: &instance_attributes.bytes,
+                usage: wgpu::BufferUsages::VERTEX,
+            },
+        ));
+        Ok(())
+    }
+
+    /// Replace the instances together with their custom attributes, which may change their number.
+    /// Fails when the attributes are not one for every instance.
+    pub fn set_instances_with_attributes(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[Instance],
+        instance_attributes: &VertexData,
+    ) -> crate::Result<()> {
+        if instance_attributes.len() != instances.len() {
+            return Err(Error::Render(format!(
+                "A mesh gets {} instances but attributes for {}",
+                instances.len(),
+                instance_attributes.len()
+            )));
+        }
+        // The old attributes no longer match, they are replaced right after
+        self.instance_attribute_buffer = None;
+        self.set_instances(device, queue, instances)?;
+        self.set_instance_attributes(device, instance_attributes)
+    }
+
+    pub(super) fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.num_instances == 0 || self.range.is_empty() {
+            return;
+        }
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        if let Some(instance_attribute_buffer) = &self.instance_attribute_buffer {
+            render_pass.set_vertex_buffer(2, instance_attribute_buffer.slice(..));
+        }
+        match &self.index_buffer {
+            Some(index_buffer) => {
+                render_pass.set_index_buffer(index_buffer.slice(..), self.index_format);
+                render_pass.draw_indexed(
+                    self.range.clone(),
+                    self.base_vertex,
+                    0..self.num_instances,
+                );
+            }
+            None => render_pass.draw(self.range.clone(), 0..self.num_instances),
+        }
+    }
+}
 
+fn create_instance_buffer(device: &wgpu::Device, instance_data: &[InstanceRaw]) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Mesh Instance Buffer"),
+        contents: bytemuck::cast_slice(instance_data),
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+    })
 }
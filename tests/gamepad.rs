@@ -0,0 +1,90 @@
+//! Gamepad input. The evdev backend is tested end to end with a virtual uinput gamepad, which
+//! needs a writable `/dev/uinput`, run it with `cargo test -- --ignored`.
+use pica::pica_gamepad::{stick_deadzone, trigger_deadzone};
+
+#[test]
+fn stick_deadzone_is_radial_and_rescaled() {
+    assert_eq!(stick_deadzone(0.1, -0.1, 0.2), (0.0, 0.0));
+    assert_eq!(stick_deadzone(1.0, 0.0, 0.2), (1.0, 0.0));
+
+    // Just past the deadzone reads small, in the direction of the stick.
+    let (x, y) = stick_deadzone(0.0, -0.3, 0.2);
+    assert_eq!(x, 0.0);
+    assert!((y + 0.125).abs() < 1e-6);
+
+    // Diagonals are clamped to the unit circle.
+    let (x, y) = stick_deadzone(1.0, 1.0, 0.2);
+    assert!(((x * x + y * y).sqrt() - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn trigger_deadzone_is_rescaled() {
+    assert_eq!(trigger_deadzone(0.05, 0.1), 0.0);
+    assert_eq!(trigger_deadzone(1.0, 0.1), 1.0);
+    assert!((trigger_deadzone(0.55, 0.1) - 0.5).abs() < 1e-6);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[ignore = "needs /dev/uinput"]
+fn virtual_gamepad() {
+    use pica::pica_gamepad::uinput::VirtualGamepad;
+    use pica::pica_gamepad::{GamepadAxis, GamepadButton, Gamepads};
+    use pica::pica_window::event::Event;
+    use std::time::{Duration, Instant};
+
+    const NAME: &str = "PiCa Virtual Gamepad";
+
+    let mut virtual_gamepad = VirtualGamepad::new(NAME).expect("Failed to open /dev/uinput");
+
+    // The event device shows up once udev created it, the gamepads rescan once a second.
+    let mut gamepads = Gamepads::new();
+    let mut events = Vec::new();
+    let start = Instant::now();
+    let slot = loop {
+        gamepads.pull(&mut events);
+        if let Some((slot, _)) = gamepads
+            .connected()
+            .find(|(_, gamepad)| gamepad.name == NAME)
+        {
+            break slot;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "The event device of the virtual gamepad is not readable"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    assert!(events.contains(&Event::GamepadConnected(slot)));
+
+    virtual_gamepad
+        .set_button(GamepadButton::South, true)
+        .unwrap();
+    virtual_gamepad.set_axis(GamepadAxis::LeftX, 1.0).unwrap();
+    virtual_gamepad
+        .set_axis(GamepadAxis::RightTrigger, 0.05)
+        .unwrap();
+    gamepads.pull(&mut events);
+    let gamepad = gamepads.get(slot).unwrap();
+    assert!(gamepad.button(GamepadButton::South).pressed);
+    assert!((gamepad.axis(GamepadAxis::LeftX) - 1.0).abs() < 1e-3);
+    assert_eq!(gamepad.axis(GamepadAxis::RightTrigger), 0.0);
+
+    virtual_gamepad
+        .set_button(GamepadButton::South, false)
+        .unwrap();
+    gamepads.pull(&mut events);
+    assert!(
+        gamepads
+            .get(slot)
+            .unwrap()
+            .button(GamepadButton::South)
+            .released
+    );
+
+    drop(virtual_gamepad);
+    events.clear();
+    gamepads.pull(&mut events);
+    assert_eq!(events, [Event::GamepadDisconnected(slot)]);
+    assert!(gamepads.get(slot).is_none());
+}
@@ -0,0 +1,343 @@
+//! Golden-image regression tests for the wgpu pipeline.
+//!
+//! Scenes are rendered offscreen on a software adapter and compared against the PNG references in
+//! `tests/golden`. They need that adapter and only run with `cargo test --features gpu-tests`,
+//! otherwise they are ignored. The references were rendered by Mesa llvmpipe on the GL backend
+//! (`WGPU_BACKEND=gl`, EGL surfaceless) and are compared with `Tolerance::default()`, at most 2 per
+//! color channel and no pixel above that. Other adapters rasterize edges differently.
+//!
+//! A missing reference fails the test, set `PICA_UPDATE_GOLDEN=1` to write new references or
+//! regenerate all of them after an intended change. On a mismatch `<name>.actual.png` and
+//! `<name>.diff.png` are written next to the reference.
+use std::path::PathBuf;
+
+use glam::{Quat, Vec3};
+use pica::wgpu_renderer::capture;
+use pica::wgpu_renderer::golden::{self, Tolerance};
+use pica::wgpu_renderer::{
+    Indices, Instance, MeshAttributes, RendererAttributes, Vertex, VertexData, VertexLayout,
+    WGPURenderer,
+};
+
+const SIZE: (i32, i32) = (128, 128);
+
+fn reference(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(name)
+}
+
+fn cube_vertices() -> Vec<Vertex> {
+    let positions = [
+        [-1, -1, 1],
+        [1, -1, 1],
+        [1, 1, 1],
+        [-1, 1, 1],
+        [-1, -1, -1],
+        [1, -1, -1],
+        [1, 1, -1],
+        [-1, 1, -1],
+    ];
+    let colors = [
+        [0, 0, 1],
+        [1, 0, 1],
+        [1, 1, 1],
+        [0, 1, 1],
+        [0, 0, 0],
+        [1, 0, 0],
+        [1, 1, 0],
+        [0, 1, 0],
+    ];
+    positions
+        .iter()
+        .zip(colors.iter())
+        .map(|(p, c)| Vertex::vertex(*p, *c))
+        .collect()
+}
+
+fn cube_indices() -> Vec<u16> {
+    vec![
+        0, 1, 2, 2, 3, 0, // front
+        1, 5, 6, 6, 2, 1, // right
+        4, 7, 6, 6, 5, 4, // back
+        0, 3, 7, 7, 4, 0, // left
+        3, 2, 6, 6, 7, 3, // top
+        0, 4, 5, 5, 1, 0, // bottom
+    ]
+}
+
+fn cube_attributes(instances: Vec<Instance>) -> RendererAttributes {
+    RendererAttributes {
+        source: include_str!("../assets/cube_face_color.wgsl"),
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        vertices: Some(cube_vertices()),
+        indices: Some(cube_indices()),
+        camera_position: Vec3::new(0.0, 2.0, 8.0),
+        instances: Some(instances),
+        ..Default::default()
+    }
+}
+
+// Render the renderer attributes, after `setup` added to the scene, and compare with a reference.
+fn assert_scene(
+    name: &str,
+    renderer_attributes: RendererAttributes,
+    setup: impl FnOnce(&mut WGPURenderer),
+) {
+    golden::check_scene(
+        SIZE,
+        renderer_attributes,
+        setup,
+        reference(name),
+        Tolerance::default(),
+    )
+    .unwrap();
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn cube_face_color() {
+    assert_scene(
+        "cube_face_color.png",
+        cube_attributes(vec![Instance {
+            position: Vec3::ZERO,
+            rotation: Quat::from_rotation_y(30.0_f32.to_radians()),
+        }]),
+        |_| {},
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn cube_instances() {
+    let instances = (-1..=1)
+        .flat_map(|x| {
+            (-1..=1).map(move |y| Instance {
+                position: Vec3::new(x as f32 * 3.0, y as f32 * 3.0, -4.0),
+                rotation: Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.0).normalize(), 0.3 * x as f32),
+            })
+        })
+        .collect();
+    assert_scene("cube_instances.png", cube_attributes(instances), |_| {});
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn mixed_topologies() {
+    assert_scene(
+        "mixed_topologies.png",
+        cube_attributes(vec![Instance {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+        }]),
+        |renderer| {
+            let ring = (0..=16)
+                .map(|i| {
+                    let angle = i as f32 / 16.0 * std::f32::consts::TAU;
+                    Vertex {
+                        position: [3.0 * angle.cos(), 3.0 * angle.sin(), 0.0, 1.0],
+                        color: [1.0, 1.0, 0.0, 1.0],
+                    }
+                })
+                .collect::<Vec<_>>();
+            renderer
+                .add_mesh(MeshAttributes {
+                    topology: wgpu::PrimitiveTopology::LineStrip,
+                    vertices: ring.clone().into(),
+                    ..Default::default()
+                })
+                .unwrap();
+            renderer
+                .add_mesh(MeshAttributes {
+                    topology: wgpu::PrimitiveTopology::PointList,
+                    vertices: ring.into(),
+                    instances: Some(vec![Instance {
+                        position: Vec3::new(0.0, 0.0, -1.0),
+                        rotation: Quat::IDENTITY,
+                    }]),
+                    ..Default::default()
+                })
+                .unwrap();
+            assert_eq!(renderer.pipelines.len(), 3);
+        },
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn index_formats_and_draw_ranges() {
+    let renderer_attributes = RendererAttributes {
+        camera_position: Vec3::new(0.0, 4.0, 8.0),
+        ..Default::default()
+    };
+    let turned = |x: f32| {
+        Some(vec![Instance {
+            position: Vec3::new(x, 0.0, 0.0),
+            rotation: Quat::from_rotation_y(-30.0_f32.to_radians()),
+        }])
+    };
+    assert_scene(
+        "index_formats_and_draw_ranges.png",
+        renderer_attributes,
+        |renderer| {
+            // The front and right faces of a cube with 32 bit indices
+            let indices = cube_indices().into_iter().map(u32::from).collect();
+            renderer
+                .add_mesh(MeshAttributes {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    vertices: cube_vertices().into(),
+                    indices: Some(Indices::U32(indices)),
+                    range: Some(0..12),
+                    instances: turned(-1.5),
+                    ..Default::default()
+                })
+                .unwrap();
+            // The top face, from vertices behind four unused ones
+            let mut vertices = vec![Vertex::vertex([0, 0, 0], [0, 0, 0]); 4];
+            vertices.extend(cube_vertices());
+            renderer
+                .add_mesh(MeshAttributes {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    vertices: vertices.into(),
+                    indices: Some(Indices::U16(cube_indices())),
+                    range: Some(24..30),
+                    base_vertex: 4,
+                    instances: turned(1.5),
+                    ..Default::default()
+                })
+                .unwrap();
+        },
+    );
+}
+
+// Positions with packed colors, tinted by a custom attribute of every instance.
+const PACKED_COLOR_SHADER: &str = "
+struct Camera {
+    view_pos: vec4<f32>;
+    view_proj: mat4x4<f32>;
+};
+[[group(1), binding(0)]]
+var<uniform> camera: Camera;
+
+struct Output {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec3<f32>,
+    [[location(1)]] color: vec4<f32>,
+    [[location(5)]] model_0: vec4<f32>,
+    [[location(6)]] model_1: vec4<f32>,
+    [[location(7)]] model_2: vec4<f32>,
+    [[location(8)]] model_3: vec4<f32>,
+    [[location(9)]] tint: vec4<f32>,
+) -> Output {
+    let model = mat4x4<f32>(model_0, model_1, model_2, model_3);
+    var output: Output;
+    output.position = camera.view_proj * model * vec4<f32>(position, 1.0);
+    output.color = color * tint;
+    return output;
+}
+
+[[stage(fragment)]]
+fn fs_main(input: Output) -> [[location(0)]] vec4<f32> {
+    return input.color;
+}
+";
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PackedVertex {
+    position: [f32; 3],
+    color: [u8; 4],
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn custom_vertex_layout() {
+    let renderer_attributes = RendererAttributes {
+        camera_position: Vec3::new(0.0, 2.0, 8.0),
+        ..Default::default()
+    };
+    assert_scene(
+        "custom_vertex_layout.png",
+        renderer_attributes,
+        |renderer| {
+            let vertices = [
+                ([-1.0, -1.0, 0.0], [255, 0, 0, 255]),
+                ([1.0, -1.0, 0.0], [0, 255, 0, 255]),
+                ([0.0, 1.0, 0.0], [0, 0, 255, 255]),
+            ]
+            .map(|(position, color)| PackedVertex { position, color });
+            let vertex_layout = VertexLayout::per_vertex()
+                .with_attribute(0, wgpu::VertexFormat::Float32x3)
+                .with_attribute(1, wgpu::VertexFormat::Unorm8x4);
+            let tints: [[f32; 4]; 2] = [[1.0, 1.0, 1.0, 1.0], [0.5, 0.5, 0.5, 1.0]];
+            let instance_layout =
+                VertexLayout::per_instance().with_attribute(9, wgpu::VertexFormat::Float32x4);
+            renderer
+                .add_mesh(MeshAttributes {
+                    source: PACKED_COLOR_SHADER,
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    vertices: VertexData::new(vertex_layout, &vertices).unwrap(),
+                    instances: Some(vec![
+                        Instance {
+                            position: Vec3::new(-1.5, 0.0, 0.0),
+                            rotation: Quat::IDENTITY,
+                        },
+                        Instance {
+                            position: Vec3::new(1.5, 0.0, 0.0),
+                            rotation: Quat::IDENTITY,
+                        },
+                    ]),
+                    instance_attributes: Some(VertexData::new(instance_layout, &tints).unwrap()),
+                    ..Default::default()
+                })
+                .unwrap();
+            assert_eq!(renderer.pipelines.len(), 2);
+        },
+    );
+}
+
+#[test]
+fn compare_images_counts_mismatched_pixels() {
+    let expected = [10, 20, 30, 255, 0, 0, 0, 255, 200, 200, 200, 255];
+    let actual = [11, 20, 30, 255, 0, 9, 0, 255, 200, 200, 200, 255];
+
+    let image_diff = golden::compare_images(&expected, &actual, 2);
+    assert_eq!(image_diff.mismatched_pixels, 1);
+    assert_eq!(image_diff.max_difference, 9);
+    assert_eq!(image_diff.diff.len(), expected.len());
+    assert_eq!(&image_diff.diff[4..8], &[255, 246, 246, 255]);
+}
+
+#[test]
+fn check_frame_requires_reference_and_writes_diff() {
+    let directory = std::env::temp_dir().join(format!("pica_golden_{}", std::process::id()));
+    let reference = directory.join("frame.png");
+    let _ = std::fs::remove_dir_all(&directory);
+
+    let frame = vec![128; 2 * 2 * 4];
+    assert!(golden::check_frame(&frame, (2, 2), &reference, Tolerance::default()).is_err());
+    assert!(!reference.exists());
+    std::fs::create_dir_all(&directory).unwrap();
+    capture::write_png(&reference, 2, 2, &frame).unwrap();
+    golden::check_frame(&frame, (2, 2), &reference, Tolerance::default()).unwrap();
+
+    let mut changed = frame.clone();
+    changed[0] = 0;
+    assert!(golden::check_frame(&changed, (2, 2), &reference, Tolerance::default()).is_err());
+    assert!(directory.join("frame.diff.png").exists());
+    assert!(directory.join("frame.actual.png").exists());
+
+    let tolerance = Tolerance {
+        max_mismatched_pixels: 1,
+        ..Default::default()
+    };
+    golden::check_frame(&changed, (2, 2), &reference, tolerance).unwrap();
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
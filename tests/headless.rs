@@ -0,0 +1,119 @@
+//! Button edges and camera control, driven frame by frame through the headless backend.
+use glam::Vec3;
+use pica::pica_keyboard::Key;
+use pica::pica_mouse::MouseButton;
+use pica::pica_window::headless::ScriptedInput;
+use pica::pica_window::{Window, WindowAttributes};
+use pica::renderer::camera::Camera;
+
+fn headless_window() -> Box<Window> {
+    Window::new_headless(WindowAttributes::new().with_size(800, 600)).unwrap()
+}
+
+// (down, pressed, released)
+fn edges(window: &Window, key: Key) -> (bool, bool, bool) {
+    let button = window.key(key);
+    (button.down, button.pressed, button.released)
+}
+
+#[test]
+fn key_edges_last_one_frame() {
+    let mut window = headless_window();
+    window.headless().unwrap().queue_all([
+        ScriptedInput::KeyDown(Key::W),
+        ScriptedInput::NextFrame,
+        ScriptedInput::NextFrame,
+        ScriptedInput::KeyUp(Key::W),
+    ]);
+
+    window.pull();
+    assert_eq!(edges(&window, Key::W), (true, true, false));
+    window.pull();
+    assert_eq!(edges(&window, Key::W), (true, false, false));
+    window.pull();
+    assert_eq!(edges(&window, Key::W), (false, false, true));
+    window.pull();
+    assert_eq!(edges(&window, Key::W), (false, false, false));
+}
+
+#[test]
+fn key_repeat_is_no_new_press() {
+    let mut window = headless_window();
+    window.headless().unwrap().queue_all([
+        ScriptedInput::KeyDown(Key::A),
+        ScriptedInput::NextFrame,
+        ScriptedInput::KeyDown(Key::A),
+    ]);
+
+    window.pull();
+    assert!(window.key(Key::A).pressed);
+    window.pull();
+    assert_eq!(edges(&window, Key::A), (true, false, false));
+}
+
+#[test]
+fn mouse_button_edges() {
+    let mut window = headless_window();
+    window.headless().unwrap().queue_all([
+        ScriptedInput::ButtonDown(MouseButton::Right),
+        ScriptedInput::NextFrame,
+        ScriptedInput::ButtonUp(MouseButton::Right),
+    ]);
+
+    window.pull();
+    let right = window.mouse.right_button;
+    assert!(right.down && right.pressed && !right.released);
+    window.pull();
+    let right = window.mouse.right_button;
+    assert!(!right.down && !right.pressed && right.released);
+    window.pull();
+    let right = window.mouse.right_button;
+    assert!(!right.down && !right.pressed && !right.released);
+}
+
+#[test]
+fn camera_moves_while_a_key_is_held() {
+    let mut window = headless_window();
+    // Facing the negative z axis, one unit per second at full speed
+    let mut camera = Camera::new(Vec3::ZERO, -90.0_f32.to_radians(), 0.0, 1.0, 0.001);
+    window.headless().unwrap().queue_all([
+        ScriptedInput::KeyDown(Key::W),
+        ScriptedInput::NextFrame,
+        ScriptedInput::KeyUp(Key::W),
+    ]);
+
+    window.pull();
+    camera.update_camera(&mut window);
+    let dt = window.time.seconds as f32;
+    assert!(dt > 0.0);
+    let moved = camera.position;
+    assert!(moved.x.abs() < 1e-6 && moved.y.abs() < 1e-6);
+    assert!((moved.z + 0.1 * dt).abs() < 1e-6, "{:?}", moved);
+
+    // Released, the camera stays
+    window.pull();
+    camera.update_camera(&mut window);
+    assert_eq!(camera.position, moved);
+}
+
+#[test]
+fn camera_only_looks_around_while_dragging() {
+    let mut window = headless_window();
+    let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0, 1.0, 1.0);
+    let view = camera.calc_matrix();
+    window.headless().unwrap().queue_all([
+        ScriptedInput::MouseMove(40, 0),
+        ScriptedInput::NextFrame,
+        ScriptedInput::ButtonDown(MouseButton::Left),
+        ScriptedInput::MouseMove(40, 0),
+    ]);
+
+    window.pull();
+    camera.update_camera(&mut window);
+    assert_eq!(camera.calc_matrix(), view);
+
+    window.pull();
+    camera.update_camera(&mut window);
+    assert_ne!(camera.calc_matrix(), view);
+    assert_eq!(camera.position, Vec3::ZERO);
+}
@@ -0,0 +1,80 @@
+//! Input handling of the PiCa window, driven through the headless backend.
+use pica::pica_keyboard::Key;
+use pica::pica_mouse::{CursorMode, MouseButton};
+use pica::pica_window::event::Event;
+use pica::pica_window::headless::ScriptedInput;
+use pica::pica_window::{Window, WindowAttributes};
+
+fn headless_window() -> Box<Window> {
+    Window::new_headless(WindowAttributes::new().with_size(800, 600)).unwrap()
+}
+
+#[test]
+fn events_keep_their_order() {
+    let mut window = headless_window();
+    window.headless().unwrap().queue_all([
+        ScriptedInput::KeyDown(Key::A),
+        ScriptedInput::ButtonDown(MouseButton::Middle),
+        ScriptedInput::KeyDown(Key::A),
+        ScriptedInput::KeyUp(Key::A),
+        ScriptedInput::NextFrame,
+        ScriptedInput::ButtonUp(MouseButton::Middle),
+    ]);
+
+    window.pull();
+    assert_eq!(
+        window.events,
+        [
+            Event::KeyDown {
+                key: Key::A,
+                repeat: false
+            },
+            Event::MouseButtonDown(MouseButton::Middle),
+            Event::KeyDown {
+                key: Key::A,
+                repeat: true
+            },
+            Event::KeyUp { key: Key::A },
+        ]
+    );
+    assert!(window.mouse.middle_button.pressed);
+    assert!(window.keys[MouseButton::Middle.virtual_key()].down);
+
+    window.pull();
+    assert_eq!(window.events, [Event::MouseButtonUp(MouseButton::Middle)]);
+    assert!(window.mouse.middle_button.released);
+}
+
+#[test]
+fn precise_wheel_adds_up_to_notches() {
+    let mut window = headless_window();
+    window.headless().unwrap().queue_all([
+        ScriptedInput::Wheel(0.5, 0.25),
+        ScriptedInput::Wheel(0.0, 0.25),
+    ]);
+    window.pull();
+    assert_eq!(window.mouse.precise_delta_wheel, 0.5);
+    assert_eq!(window.mouse.precise_delta_horizontal_wheel, 0.5);
+    assert_eq!((window.mouse.delta_wheel, window.mouse.wheel), (0, 0));
+
+    window.headless().unwrap().queue_all([
+        ScriptedInput::Wheel(0.5, 0.5),
+        ScriptedInput::Wheel(0.0, 1.0),
+    ]);
+    window.pull();
+    assert_eq!((window.mouse.delta_wheel, window.mouse.wheel), (2, 2));
+    assert_eq!(window.mouse.horizontal_wheel, 1);
+}
+
+#[test]
+fn locked_cursor_stays_centered() {
+    let mut window = headless_window();
+    window.set_cursor_mode(CursorMode::Locked);
+    window
+        .headless()
+        .unwrap()
+        .queue(ScriptedInput::MouseMove(25, -10));
+    window.pull();
+    assert_eq!(window.mouse.position, (400, 300));
+    assert_eq!(window.mouse.delta_position, (25, -10));
+}
@@ -0,0 +1,53 @@
+//! Keys and modifiers of the PiCa window, driven through the headless backend.
+use pica::pica_keyboard::{Key, Modifiers};
+use pica::pica_window::headless::ScriptedInput;
+use pica::pica_window::{Window, WindowAttributes};
+
+#[test]
+fn every_key_round_trips_through_its_virtual_key() {
+    for &key in Key::ALL {
+        assert_eq!(Key::from_virtual_key(key.virtual_key() as u8), Some(key));
+    }
+    assert_eq!(Key::from_virtual_key(0), None);
+}
+
+#[test]
+fn left_and_right_modifiers_are_reported() {
+    let shift = Modifiers {
+        shift: true,
+        ..Default::default()
+    };
+    let control = Modifiers {
+        control: true,
+        ..Default::default()
+    };
+    let alt = Modifiers {
+        alt: true,
+        ..Default::default()
+    };
+    for (key, other_side, generic, modifiers) in [
+        (Key::LeftShift, Key::RightShift, Key::Shift, shift),
+        (Key::RightShift, Key::LeftShift, Key::Shift, shift),
+        (Key::LeftControl, Key::RightControl, Key::Control, control),
+        (Key::RightControl, Key::LeftControl, Key::Control, control),
+        (Key::LeftAlt, Key::RightAlt, Key::Alt, alt),
+        (Key::RightAlt, Key::LeftAlt, Key::Alt, alt),
+    ] {
+        let mut window = Window::new_headless(WindowAttributes::new()).unwrap();
+        window.headless().unwrap().queue_all([
+            ScriptedInput::KeyDown(key),
+            ScriptedInput::NextFrame,
+            ScriptedInput::KeyUp(key),
+        ]);
+
+        window.pull();
+        assert_eq!(window.modifiers(), modifiers, "{:?}", key);
+        assert!(window.key(key).down, "{:?}", key);
+        assert!(window.key(generic).down, "{:?}", key);
+        assert!(!window.key(other_side).down, "{:?}", key);
+
+        window.pull();
+        assert_eq!(window.modifiers(), Modifiers::default(), "{:?}", key);
+        assert!(!window.key(generic).down, "{:?}", key);
+    }
+}
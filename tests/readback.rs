@@ -0,0 +1,126 @@
+//! Reading rendered frames back to the CPU. These need a GPU or software adapter, run them with
+//! `cargo test --features gpu-tests`.
+use pica::wgpu_renderer::{golden, target, Gpu, RendererAttributes, WGPURenderer};
+
+// Rows of 50 pixels are 200 bytes, padded to 256 bytes in the copy buffer.
+const SIZE: (i32, i32) = (50, 3);
+
+fn gpu() -> Gpu {
+    pollster::block_on(Gpu::new(None, false)).expect("No adapter available")
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn read_pixels_removes_row_padding() {
+    let gpu = gpu();
+    let mut renderer =
+        WGPURenderer::new_offscreen_with_gpu(&gpu, SIZE, RendererAttributes::default());
+    renderer.clear_color = wgpu::Color {
+        r: 1.0,
+        g: 0.0,
+        b: 1.0,
+        a: 1.0,
+    };
+    renderer.render().unwrap();
+
+    let pixels = renderer.read_pixels().unwrap();
+    assert_eq!(pixels.len(), 4 * 50 * 3);
+    assert!(pixels.chunks(4).all(|pixel| pixel == [255, 0, 255, 255]));
+}
+
+// A texture in which every pixel holds its own coordinates, and those pixels.
+fn coordinate_texture(gpu: &Gpu) -> (wgpu::Texture, Vec<u8>) {
+    let (width, height) = (SIZE.0 as u32, SIZE.1 as u32);
+    let texture = gpu.device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::TEXTURE_BINDING,
+        label: None,
+    });
+    let pattern = (0..height)
+        .flat_map(|y| (0..width).flat_map(move |x| [x as u8, y as u8, 7, 255]))
+        .collect::<Vec<u8>>();
+    gpu.queue.write_texture(
+        texture.as_image_copy(),
+        &pattern,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: std::num::NonZeroU32::new(4 * width),
+            rows_per_image: None,
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    (texture, pattern)
+}
+
+fn read_back(gpu: &Gpu, texture: &wgpu::Texture) -> Vec<u8> {
+    target::read_texture(
+        &gpu.device,
+        &gpu.queue,
+        texture,
+        wgpu::TextureFormat::Rgba8Unorm,
+        SIZE.0 as u32,
+        SIZE.1 as u32,
+    )
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn read_texture_keeps_rows_in_order() {
+    let gpu = gpu();
+    let (texture, pattern) = coordinate_texture(&gpu);
+    assert_eq!(read_back(&gpu, &texture), pattern);
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn blit_copies_pixel_for_pixel() {
+    let gpu = gpu();
+    let (source, pattern) = coordinate_texture(&gpu);
+    let destination = target::create_offscreen_texture(
+        &gpu.device,
+        SIZE.0 as u32,
+        SIZE.1 as u32,
+        wgpu::TextureFormat::Rgba8Unorm,
+    );
+    let blit = target::Blit::new(&gpu.device, wgpu::TextureFormat::Rgba8Unorm);
+    blit.draw(
+        &gpu.device,
+        &gpu.queue,
+        &source,
+        &destination.create_view(&wgpu::TextureViewDescriptor::default()),
+    );
+    assert_eq!(read_back(&gpu, &destination), pattern);
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn recording_writes_every_nth_rendered_frame() {
+    let gpu = gpu();
+    let mut renderer =
+        WGPURenderer::new_offscreen_with_gpu(&gpu, SIZE, RendererAttributes::default());
+    let directory = std::env::temp_dir().join(format!("pica_readback_{}", std::process::id()));
+    renderer.start_recording(&directory, 2).unwrap();
+    for _ in 0..3 {
+        renderer.render().unwrap();
+    }
+    let recording = renderer.stop_recording().unwrap();
+
+    assert_eq!(recording.frames_written(), 2);
+    let (_, _, recorded) = golden::read_png(directory.join("frame_000001.png")).unwrap();
+    assert_eq!(Some(recorded), renderer.read_pixels());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,164 @@
+//! Presenting frames through the `Renderer` attached to a window.
+use glam::{Quat, Vec3};
+use pica::pica_window::headless::ScriptedInput;
+use pica::pica_window::{Window, WindowAttributes};
+use pica::renderer::camera::Camera;
+use pica::renderer::{Indices, Instance, MeshId, Renderer, Vertex};
+use pica::wgpu_renderer::{
+    Gpu, MeshAttributes, RendererAttributes, VertexData, VertexLayout, WGPURenderer,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Keeps track of the calls, shared with the test after the window took the renderer.
+#[derive(Default)]
+struct Calls {
+    meshes: usize,
+    draws: Vec<MeshId>,
+    frames: Vec<Vec<MeshId>>,
+    sizes: Vec<(u32, u32)>,
+}
+
+struct LoggingRenderer(Rc<RefCell<Calls>>);
+
+impl Renderer for LoggingRenderer {
+    fn create_mesh(
+        &mut self,
+        _vertices: &[Vertex],
+        _indices: Option<Indices>,
+    ) -> pica::Result<MeshId> {
+        let mut calls = self.0.borrow_mut();
+        calls.meshes += 1;
+        Ok(MeshId(calls.meshes - 1))
+    }
+
+    fn set_instances(&mut self, _mesh: MeshId, _instances: &[Instance]) -> pica::Result<()> {
+        Ok(())
+    }
+
+    fn set_camera(&mut self, _camera: &Camera) {}
+
+    fn draw(&mut self, mesh: MeshId) {
+        self.0.borrow_mut().draws.push(mesh);
+    }
+
+    fn present(&mut self) -> pica::Result<()> {
+        let mut calls = self.0.borrow_mut();
+        let draws = std::mem::take(&mut calls.draws);
+        calls.frames.push(draws);
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.0.borrow_mut().sizes.push((width, height));
+    }
+}
+
+#[test]
+fn push_presents_with_the_attached_renderer() {
+    let mut window = Window::new_headless(WindowAttributes::new().with_size(800, 600)).unwrap();
+    // Without a renderer there is nothing to present.
+    window.push().unwrap();
+
+    let calls = Rc::new(RefCell::new(Calls::default()));
+    window.set_renderer(Box::new(LoggingRenderer(calls.clone())));
+    let triangle = window.renderer().unwrap().create_mesh(&[], None).unwrap();
+    let line = window.renderer().unwrap().create_mesh(&[], None).unwrap();
+
+    window.pull();
+    window.renderer().unwrap().draw(triangle);
+    window.renderer().unwrap().draw(line);
+    window.push().unwrap();
+
+    window
+        .headless()
+        .unwrap()
+        .queue(ScriptedInput::Resize(640, 480));
+    window.pull();
+    window.renderer().unwrap().draw(line);
+    window.push().unwrap();
+
+    let calls = calls.borrow();
+    assert_eq!(calls.frames, [vec![triangle, line], vec![line]]);
+    assert_eq!(calls.sizes, [(800, 600), (640, 480)]);
+
+    assert!(window.take_renderer().is_some());
+    assert!(window.renderer().is_none());
+}
+
+#[test]
+fn headless_windows_have_no_surface() {
+    let window = Window::new_headless(WindowAttributes::new()).unwrap();
+    let gpu = pollster::block_on(Gpu::new(Some(&window), false));
+    assert!(matches!(gpu, Err(pica::error::Error::Render(_))));
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn renderers_on_a_shared_gpu_need_a_surface() {
+    let window = Window::new_headless(WindowAttributes::new()).unwrap();
+    let gpu = pollster::block_on(Gpu::new(None, false)).unwrap();
+    let renderer = WGPURenderer::new_with_gpu(&gpu, &window, RendererAttributes::default());
+    assert!(matches!(renderer, Err(pica::error::Error::Render(_))));
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn instances_keep_their_attributes() {
+    let gpu = pollster::block_on(Gpu::new(None, false)).unwrap();
+    let mut renderer =
+        WGPURenderer::new_offscreen_with_gpu(&gpu, (16, 16), RendererAttributes::default());
+    let instances = |n: usize| -> Vec<Instance> {
+        (0..n)
+            .map(|_| Instance {
+                position: Vec3::ZERO,
+                rotation: Quat::IDENTITY,
+            })
+            .collect()
+    };
+    let tints = |n: usize| {
+        let layout = VertexLayout::per_instance().with_attribute(9, wgpu::VertexFormat::Float32x4);
+        VertexData::new(layout, &vec![[1.0_f32; 4]; n]).unwrap()
+    };
+    let mesh = renderer
+        .add_mesh(MeshAttributes {
+            vertices: vec![Vertex::vertex([0, 0, 0], [1, 1, 1]); 3].into(),
+            instances: Some(instances(2)),
+            instance_attributes: Some(tints(2)),
+            ..Default::default()
+        })
+        .unwrap();
+
+    assert!(renderer.set_instances(mesh, &instances(2)).is_ok());
+    assert!(matches!(
+        renderer.set_instances(mesh, &instances(3)),
+        Err(pica::error::Error::Render(_))
+    ));
+    assert!(renderer
+        .set_instances_with_attributes(mesh, &instances(3), &tints(2))
+        .is_err());
+    renderer
+        .set_instances_with_attributes(mesh, &instances(3), &tints(3))
+        .unwrap();
+    assert!(renderer.set_instances(mesh, &instances(3)).is_ok());
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn ranges_and_indices_stay_within_the_mesh() {
+    let gpu = pollster::block_on(Gpu::new(None, false)).unwrap();
+    let mut renderer =
+        WGPURenderer::new_offscreen_with_gpu(&gpu, (16, 16), RendererAttributes::default());
+    let vertices = [Vertex::vertex([0, 0, 0], [1, 1, 1]); 3];
+    assert!(renderer
+        .create_mesh(&vertices, Some(Indices::U16(vec![0, 1, 3])))
+        .is_err());
+    let mesh = renderer
+        .create_mesh(&vertices, Some(Indices::U16(vec![0, 1, 2, 2, 1, 0])))
+        .unwrap();
+
+    renderer.set_range(mesh, 3..6).unwrap();
+    assert_eq!(renderer.meshes[mesh.0].range(), 3..6);
+    assert!(renderer.set_range(mesh, 3..7).is_err());
+    assert_eq!(renderer.meshes[mesh.0].range(), 3..6);
+}
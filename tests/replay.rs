@@ -0,0 +1,192 @@
+//! Recording the input of a window and replaying it, driven through the headless backend.
+use pica::pica_keyboard::Key;
+use pica::pica_mouse::MouseButton;
+use pica::pica_window::event::Event;
+use pica::pica_window::headless::ScriptedInput;
+use pica::pica_window::{Window, WindowAttributes};
+use std::path::PathBuf;
+
+fn headless_window() -> Box<Window> {
+    Window::new_headless(WindowAttributes::new().with_size(800, 600)).unwrap()
+}
+
+fn recording_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("pica-{}-{}.rec", name, std::process::id()))
+}
+
+// What a frame saw of the input, to compare a replay with the original.
+fn frame_input(window: &Window) -> String {
+    let mouse = &window.mouse;
+    format!(
+        "{:?} {:?} {:?} {:?} {:?} {} {} {} {:?} {:?} {:?} {} {}",
+        window
+            .keys
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| key.down || key.released)
+            .map(|(index, key)| (index, key.pressed, key.released))
+            .collect::<Vec<_>>(),
+        MouseButton::ALL.map(|button| mouse.button(button)),
+        (mouse.position, mouse.delta_position),
+        (mouse.wheel, mouse.delta_wheel, mouse.precise_delta_wheel),
+        (mouse.horizontal_wheel, mouse.delta_horizontal_wheel),
+        window.text,
+        window.time.ticks,
+        window.time.delta_ticks,
+        window.events,
+        window.dropped_files,
+        window.time.seconds,
+        window.time.delta_nanoseconds,
+        window.time.ticks_per_second,
+    )
+}
+
+#[test]
+fn replay_reproduces_the_recorded_input() {
+    let path = recording_path("reproduce");
+    let mut window = headless_window();
+    window.pull();
+    window.start_input_recording(&path).unwrap();
+    window.headless().unwrap().queue_all([
+        ScriptedInput::KeyDown(Key::W),
+        ScriptedInput::MouseMove(15, -4),
+        ScriptedInput::NextFrame,
+        ScriptedInput::Text("hé".to_string()),
+        ScriptedInput::Wheel(0.5, -1.0),
+        ScriptedInput::ButtonDown(MouseButton::Left),
+        ScriptedInput::NextFrame,
+        ScriptedInput::NextFrame,
+        ScriptedInput::KeyUp(Key::W),
+        ScriptedInput::DropFiles(vec![PathBuf::from("level.toml")], (10, 20)),
+        ScriptedInput::Focus(false),
+        ScriptedInput::NextFrame,
+        ScriptedInput::Close,
+    ]);
+    let mut recorded = Vec::new();
+    while window.pull() {
+        recorded.push(frame_input(&window));
+    }
+    recorded.push(frame_input(&window));
+    window.stop_input_recording().unwrap();
+
+    // Another machine, running at its own pace.
+    let mut replaying = headless_window();
+    replaying.headless().unwrap().frame_time = 1000;
+    replaying.start_replay(&path).unwrap();
+    assert!(replaying.is_replaying());
+    let mut replayed = Vec::new();
+    while replaying.pull() {
+        replayed.push(frame_input(&replaying));
+    }
+    replayed.push(frame_input(&replaying));
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(replayed, recorded);
+    assert_eq!(replayed.len(), 5);
+}
+
+#[test]
+fn live_input_returns_after_the_replay() {
+    let path = recording_path("live");
+    let mut window = headless_window();
+    window.start_input_recording(&path).unwrap();
+    window
+        .headless()
+        .unwrap()
+        .queue(ScriptedInput::KeyDown(Key::Space));
+    window.pull();
+    window.stop_input_recording().unwrap();
+
+    let mut replaying = headless_window();
+    replaying.start_replay(&path).unwrap();
+    // Live input is ignored, window events stay.
+    replaying.headless().unwrap().queue_all([
+        ScriptedInput::KeyDown(Key::A),
+        ScriptedInput::Resize(640, 480),
+    ]);
+    replaying.pull();
+    assert!(replaying.keys[Key::Space.virtual_key()].pressed);
+    assert!(!replaying.keys[Key::A.virtual_key()].down);
+    assert_eq!(
+        replaying.events,
+        [
+            Event::Resized(640, 480),
+            Event::KeyDown {
+                key: Key::Space,
+                repeat: false
+            }
+        ]
+    );
+
+    assert!(!replaying.is_replaying());
+
+    let ticks = replaying.time.ticks;
+    replaying
+        .headless()
+        .unwrap()
+        .queue(ScriptedInput::KeyDown(Key::A));
+    replaying.pull();
+    std::fs::remove_file(&path).unwrap();
+    assert!(replaying.keys[Key::A.virtual_key()].pressed);
+    assert!(replaying.time.ticks > ticks);
+}
+
+#[test]
+fn replay_rejects_other_files() {
+    let path = recording_path("invalid");
+    std::fs::write(&path, b"not a recording").unwrap();
+    let mut window = headless_window();
+    assert!(window.start_replay(&path).is_err());
+    assert!(!window.is_replaying());
+    std::fs::remove_file(&path).unwrap();
+}
+
+// A recording of a few frames holding down W, as bytes.
+fn recording_bytes(name: &str) -> Vec<u8> {
+    let path = recording_path(name);
+    let mut window = headless_window();
+    window.start_input_recording(&path).unwrap();
+    window.headless().unwrap().queue_all([
+        ScriptedInput::KeyDown(Key::W),
+        ScriptedInput::NextFrame,
+        ScriptedInput::NextFrame,
+    ]);
+    for _ in 0..3 {
+        window.pull();
+    }
+    window.stop_input_recording().unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    bytes
+}
+
+// Replay `bytes`, returns the number of frames that saw W held down.
+fn replayed_frames(name: &str, bytes: &[u8]) -> usize {
+    let path = recording_path(name);
+    std::fs::write(&path, bytes).unwrap();
+    let mut window = headless_window();
+    window.start_replay(&path).unwrap();
+    let mut frames = 0;
+    while window.is_replaying() {
+        window.pull();
+        frames += window.key(Key::W).down as usize;
+    }
+    std::fs::remove_file(&path).unwrap();
+    frames
+}
+
+#[test]
+fn replay_stops_at_a_truncated_frame() {
+    let bytes = recording_bytes("complete");
+    assert_eq!(replayed_frames("complete", &bytes), 3);
+    assert_eq!(replayed_frames("truncated", &bytes[..bytes.len() - 1]), 2);
+}
+
+#[test]
+fn replay_stops_at_a_frame_longer_than_the_file() {
+    let mut bytes = recording_bytes("long");
+    // A frame of 2^63 bytes, followed by a few
+    bytes.extend([0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]);
+    bytes.extend([1, 2, 3]);
+    assert_eq!(replayed_frames("long", &bytes), 3);
+}
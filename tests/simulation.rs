@@ -0,0 +1,64 @@
+//! The fixed-timestep loop, fed by hand and by a headless window.
+use pica::pica_simulation::Simulation;
+use pica::pica_window::{Window, WindowAttributes};
+
+const MILLISECOND: i64 = 1000 * 1000;
+
+#[test]
+fn steps_are_independent_of_the_frame_rate() {
+    // One simulated second at 100 steps per second, in frames of 7 ms and of 23 ms.
+    let mut positions = Vec::new();
+    for frame_time in [7 * MILLISECOND, 23 * MILLISECOND] {
+        let mut simulation = Simulation::new(100);
+        let mut position = 0.0;
+        let mut elapsed = 0;
+        while elapsed + frame_time <= 1000 * MILLISECOND {
+            simulation.advance(frame_time, |dt| position += 3.0 * dt);
+            elapsed += frame_time;
+        }
+        simulation.advance(1000 * MILLISECOND - elapsed, |dt| position += 3.0 * dt);
+        assert_eq!(simulation.steps, 100);
+        assert_eq!(simulation.alpha(), 0.0);
+        positions.push(position);
+    }
+    assert_eq!(positions[0], positions[1]);
+    assert!((positions[0] - 3.0f64).abs() < 1e-9);
+}
+
+#[test]
+fn alpha_is_the_partial_step() {
+    let mut simulation = Simulation::new(100);
+    assert_eq!(
+        simulation.advance(25 * MILLISECOND, |dt| assert_eq!(dt, 0.01)),
+        2
+    );
+    assert!((simulation.alpha() - 0.5).abs() < 1e-9);
+    assert_eq!(simulation.advance(5 * MILLISECOND, |_| {}), 1);
+    assert_eq!(simulation.alpha(), 0.0);
+    assert!((simulation.seconds() - 0.03).abs() < 1e-9);
+}
+
+#[test]
+fn long_frames_are_clamped() {
+    let mut simulation = Simulation::new(100);
+    simulation.max_steps = 4;
+    // A one second hitch runs four steps and drops the rest, keeping the partial step.
+    let steps = simulation.advance(1005 * MILLISECOND, |_| {});
+    assert_eq!(steps, 4);
+    assert_eq!(simulation.dropped_steps, 96);
+    assert!((simulation.alpha() - 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn headless_window_drives_the_simulation() {
+    let mut window = Window::new_headless(WindowAttributes::new()).unwrap();
+    window.headless().unwrap().frame_time = 50 * MILLISECOND;
+    let mut simulation = Simulation::new(30);
+    let mut steps = Vec::new();
+    for _ in 0..4 {
+        window.pull();
+        steps.push(simulation.advance(window.time.delta_nanoseconds, |_| {}));
+    }
+    // 50 ms frames on a 33.3 ms step: 1.5 steps per frame
+    assert_eq!(steps, [1, 2, 1, 2]);
+}
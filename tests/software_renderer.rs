@@ -0,0 +1,347 @@
+//! The CPU rasterizer, rendering small scenes into its framebuffer.
+use std::path::PathBuf;
+
+use glam::{Quat, Vec3, Vec4};
+use pica::renderer::camera::Camera;
+use pica::renderer::{Indices, Instance, Renderer, Vertex};
+use pica::software_renderer::raster::{encode_color, ClipVertex, Framebuffer};
+use pica::software_renderer::SoftwareRenderer;
+use pica::wgpu_renderer::golden::{self, Tolerance};
+use pica::wgpu_renderer::{RendererAttributes, WGPURenderer};
+
+const SIZE: (i32, i32) = (64, 64);
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+
+// A renderer looking at the origin along the negative z axis.
+fn renderer(renderer_attributes: RendererAttributes) -> SoftwareRenderer {
+    let mut renderer = SoftwareRenderer::new(SIZE, renderer_attributes);
+    let camera = Camera::new(
+        Vec3::new(0.0, 0.0, 3.0),
+        -90.0_f32.to_radians(),
+        0.0,
+        0.1,
+        0.001,
+    );
+    renderer.set_camera(&camera);
+    renderer
+}
+
+fn triangle(z: f32, color: [f32; 4]) -> Vec<Vertex> {
+    [[-1.0, -1.0], [1.0, -1.0], [0.0, 1.0]]
+        .iter()
+        .map(|[x, y]| Vertex {
+            position: [*x, *y, z, 1.0],
+            color,
+        })
+        .collect()
+}
+
+fn pixel(renderer: &SoftwareRenderer, x: u32, y: u32) -> [u8; 4] {
+    let index = 4 * (y * renderer.framebuffer.width + x) as usize;
+    renderer.read_pixels()[index..index + 4].try_into().unwrap()
+}
+
+fn clear_pixel(renderer: &SoftwareRenderer) -> [u8; 4] {
+    let color = renderer.clear_color;
+    encode_color(Vec4::new(
+        color.r as f32,
+        color.g as f32,
+        color.b as f32,
+        color.a as f32,
+    ))
+}
+
+#[test]
+fn back_faces_are_culled() {
+    let mut renderer = renderer(RendererAttributes::default());
+    let front = renderer
+        .create_mesh(&triangle(0.0, [1.0, 0.0, 0.0, 1.0]), None)
+        .unwrap();
+    let back = renderer
+        .create_mesh(
+            &triangle(0.0, [1.0, 0.0, 0.0, 1.0]),
+            Some(Indices::U32(vec![0, 2, 1])),
+        )
+        .unwrap();
+
+    renderer.draw(front);
+    renderer.present().unwrap();
+    assert_eq!(pixel(&renderer, 32, 32), RED);
+    assert_eq!(pixel(&renderer, 0, 0), clear_pixel(&renderer));
+
+    renderer.draw(back);
+    renderer.present().unwrap();
+    assert_eq!(pixel(&renderer, 32, 32), clear_pixel(&renderer));
+}
+
+#[test]
+fn nearer_triangles_win_the_depth_test() {
+    let mut renderer = renderer(RendererAttributes::default());
+    let near = renderer
+        .create_mesh(&triangle(0.5, [1.0, 0.0, 0.0, 1.0]), None)
+        .unwrap();
+    let far = renderer
+        .create_mesh(&triangle(-0.5, [0.0, 1.0, 0.0, 1.0]), None)
+        .unwrap();
+
+    renderer.draw(near);
+    renderer.draw(far);
+    renderer.present().unwrap();
+    assert_eq!(pixel(&renderer, 32, 32), RED);
+
+    renderer.draw(far);
+    renderer.draw(near);
+    renderer.present().unwrap();
+    assert_eq!(pixel(&renderer, 32, 32), RED);
+
+    renderer.draw(far);
+    renderer.present().unwrap();
+    assert_eq!(pixel(&renderer, 32, 32), GREEN);
+}
+
+#[test]
+fn colors_are_interpolated() {
+    let mut renderer = renderer(RendererAttributes::default());
+    let mut vertices = triangle(0.0, [1.0, 0.0, 0.0, 1.0]);
+    vertices[2].color = [0.0, 0.0, 1.0, 1.0];
+    let mesh = renderer.create_mesh(&vertices, None).unwrap();
+    renderer.draw(mesh);
+    renderer.present().unwrap();
+
+    // Red at the bottom, blue towards the top vertex.
+    let bottom = pixel(&renderer, 32, 50);
+    let top = pixel(&renderer, 32, 16);
+    assert!(bottom[0] > bottom[2]);
+    assert!(top[2] > top[0]);
+}
+
+#[test]
+fn every_instance_is_drawn() {
+    let mut renderer = renderer(RendererAttributes::default());
+    let small: Vec<Vertex> = triangle(0.0, [0.0, 1.0, 0.0, 1.0])
+        .into_iter()
+        .map(|mut vertex| {
+            vertex.position = [
+                vertex.position[0] * 0.25,
+                vertex.position[1] * 0.25,
+                0.0,
+                1.0,
+            ];
+            vertex
+        })
+        .collect();
+    let mesh = renderer.create_mesh(&small, None).unwrap();
+    let instances = [-0.8, 0.8].map(|x| Instance {
+        position: Vec3::new(x, 0.0, 0.0),
+        rotation: Quat::IDENTITY,
+    });
+    renderer.set_instances(mesh, &instances).unwrap();
+    renderer.draw(mesh);
+    renderer.present().unwrap();
+
+    assert_eq!(pixel(&renderer, 14, 32), GREEN);
+    assert_eq!(pixel(&renderer, 49, 32), GREEN);
+    assert_eq!(pixel(&renderer, 32, 32), clear_pixel(&renderer));
+}
+
+#[test]
+fn line_strips_are_drawn_without_gaps() {
+    let mut renderer = renderer(RendererAttributes {
+        topology: wgpu::PrimitiveTopology::LineStrip,
+        ..Default::default()
+    });
+    let line: Vec<Vertex> = [[-0.5, 0.1], [0.0, 0.1], [0.5, 0.1]]
+        .iter()
+        .map(|[x, y]| Vertex {
+            position: [*x, *y, 0.0, 1.0],
+            color: [1.0, 0.0, 0.0, 1.0],
+        })
+        .collect();
+    let mesh = renderer.create_mesh(&line, None).unwrap();
+    renderer.draw(mesh);
+    renderer.present().unwrap();
+
+    let row = (0..64)
+        .find(|&y| pixel(&renderer, 32, y) == RED)
+        .expect("the line crosses the center column");
+    let drawn: Vec<u32> = (0..64)
+        .filter(|&x| pixel(&renderer, x, row) == RED)
+        .collect();
+    // Continuous, and one unit wide seen from a distance of 3 with a field of view of 45 degrees.
+    assert_eq!(drawn.len() as u32, drawn[drawn.len() - 1] - drawn[0] + 1);
+    let expected = 64.0 / (6.0 * 22.5_f32.to_radians().tan());
+    assert!((drawn.len() as f32 - expected).abs() <= 1.0);
+}
+
+#[test]
+fn strips_restart_at_the_largest_index_of_their_format() {
+    let line: Vec<Vertex> = [-0.5, -0.25, 0.25, 0.5]
+        .iter()
+        .map(|x| Vertex {
+            position: [*x, 0.1, 0.0, 1.0],
+            color: [1.0, 0.0, 0.0, 1.0],
+        })
+        .collect();
+    for indices in [
+        Indices::U16(vec![0, 1, u16::MAX, 2, 3]),
+        Indices::U32(vec![0, 1, u32::MAX, 2, 3]),
+    ] {
+        let mut renderer = renderer(RendererAttributes {
+            topology: wgpu::PrimitiveTopology::LineStrip,
+            ..Default::default()
+        });
+        let mesh = renderer.create_mesh(&line, Some(indices)).unwrap();
+        renderer.draw(mesh);
+        renderer.present().unwrap();
+
+        let row = (0..64)
+            .find(|&y| pixel(&renderer, 22, y) == RED)
+            .expect("the first line crosses column 22");
+        assert_eq!(pixel(&renderer, 42, row), RED);
+        assert_eq!(pixel(&renderer, 32, row), clear_pixel(&renderer));
+    }
+}
+
+#[test]
+fn meshes_keep_the_topology_they_were_created_with() {
+    let mut renderer = renderer(RendererAttributes {
+        topology: wgpu::PrimitiveTopology::PointList,
+        ..Default::default()
+    });
+    let points = renderer
+        .create_mesh(&triangle(0.0, [1.0, 0.0, 0.0, 1.0]), None)
+        .unwrap();
+    renderer.topology = wgpu::PrimitiveTopology::TriangleList;
+    let filled = renderer
+        .create_mesh(&triangle(0.0, [0.0, 1.0, 0.0, 1.0]), None)
+        .unwrap();
+
+    renderer.draw(points);
+    renderer.present().unwrap();
+    assert_eq!(pixel(&renderer, 32, 32), clear_pixel(&renderer));
+
+    renderer.draw(filled);
+    renderer.present().unwrap();
+    assert_eq!(pixel(&renderer, 32, 32), GREEN);
+}
+
+// Three rotated cubes, the scene of the software and GPU references.
+fn cube_instances() -> RendererAttributes {
+    let positions = [
+        [-1, -1, 1],
+        [1, -1, 1],
+        [1, 1, 1],
+        [-1, 1, 1],
+        [-1, -1, -1],
+        [1, -1, -1],
+        [1, 1, -1],
+        [-1, 1, -1],
+    ];
+    let colors = [
+        [0, 0, 1],
+        [1, 0, 1],
+        [1, 1, 1],
+        [0, 1, 1],
+        [0, 0, 0],
+        [1, 0, 0],
+        [1, 1, 0],
+        [0, 1, 0],
+    ];
+    let vertices = positions
+        .iter()
+        .zip(colors.iter())
+        .map(|(p, c)| Vertex::vertex(*p, *c))
+        .collect();
+    let indices = vec![
+        0, 1, 2, 2, 3, 0, // front
+        1, 5, 6, 6, 2, 1, // right
+        4, 7, 6, 6, 5, 4, // back
+        0, 3, 7, 7, 4, 0, // left
+        3, 2, 6, 6, 7, 3, // top
+        0, 4, 5, 5, 1, 0, // bottom
+    ];
+    let instances = (-1..=1)
+        .map(|x| Instance {
+            position: Vec3::new(x as f32 * 3.0, 0.0, -4.0),
+            rotation: Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.0).normalize(), 0.3 * x as f32),
+        })
+        .collect();
+    RendererAttributes {
+        vertices: Some(vertices),
+        indices: Some(indices),
+        camera_position: Vec3::new(0.0, 2.0, 8.0),
+        instances: Some(instances),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn cube_instances_match_their_reference() {
+    let mut renderer = SoftwareRenderer::new((128, 128), cube_instances());
+
+    // The rasterizer is exact, frames must not change by a single bit.
+    let tolerance = Tolerance {
+        per_channel: 0,
+        max_mismatched_pixels: 0,
+    };
+    let reference = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join("software_cube_instances.png");
+    golden::check_frame(&renderer.capture_frame(), (128, 128), reference, tolerance).unwrap();
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu-tests"), ignore = "needs a GPU or software adapter")]
+fn cube_instances_match_the_gpu() {
+    let mut software = SoftwareRenderer::new((128, 128), cube_instances());
+    let gpu = pollster::block_on(WGPURenderer::try_new_offscreen(
+        (128, 128),
+        cube_instances(),
+    ))
+    .unwrap();
+
+    // Colors may round differently by 2, and a few edge pixels may fall on the other side where
+    // the adapter has less sub-pixel precision. On llvmpipe the frames differ by at most 1.
+    let diff = golden::compare_images(&gpu.capture_frame(), &software.capture_frame(), 2);
+    assert!(
+        diff.mismatched_pixels <= 16,
+        "{} pixels differ by more than 2",
+        diff.mismatched_pixels
+    );
+}
+
+#[test]
+fn triangles_far_outside_the_frame_keep_their_edges() {
+    let mut framebuffer = Framebuffer::new(64, 64);
+    framebuffer.clear(Vec4::ZERO);
+    // The bottom edge runs from the bottom left corner to the middle of the right side, its far
+    // end must not be moved to fit the fixed point coordinates.
+    let vertices = [[-1.0, -1.0], [2e9, 1e9], [-1.0, 3.0]].map(|[x, y]| ClipVertex {
+        position: Vec4::new(x, y, 0.5, 1.0),
+        color: Vec4::new(1.0, 0.0, 0.0, 1.0),
+    });
+    framebuffer.triangle(vertices, false);
+
+    let pixel = |x: u32, y: u32| {
+        let index = 4 * (y * 64 + x) as usize;
+        &framebuffer.pixels[index..index + 4]
+    };
+    assert_eq!(pixel(62, 24), RED);
+    assert_eq!(pixel(62, 40), [0, 0, 0, 0]);
+    assert_eq!(pixel(32, 40), RED);
+    assert_eq!(pixel(32, 56), [0, 0, 0, 0]);
+}
+
+#[test]
+fn indices_stay_within_the_vertices() {
+    let mut renderer = renderer(RendererAttributes::default());
+    let vertices = triangle(0.0, [1.0, 0.0, 0.0, 1.0]);
+    assert!(renderer
+        .create_mesh(&vertices, Some(Indices::U16(vec![0, 1, 3])))
+        .is_err());
+    assert!(renderer
+        .create_mesh(&vertices, Some(Indices::U16(vec![0, 1, 2])))
+        .is_ok());
+}
@@ -0,0 +1,40 @@
+//! Text input decoding, independent of the window system that delivers it.
+use pica::pica_window::headless::ScriptedInput;
+use pica::pica_window::text::Utf16Decoder;
+use pica::pica_window::{Window, WindowAttributes};
+
+#[test]
+fn utf16_surrogate_pairs() {
+    let units: Vec<u16> = "aπ🦀€".encode_utf16().collect();
+    assert_eq!(Utf16Decoder::decode(&units), "aπ🦀€");
+
+    // One WM_CHAR at a time, the high surrogate waits for its partner.
+    let mut decoder = Utf16Decoder::new();
+    assert_eq!(decoder.push(0xD83E).count(), 0);
+    assert_eq!(decoder.push(0xDD80).collect::<String>(), "🦀");
+}
+
+#[test]
+fn utf16_unpaired_surrogates() {
+    assert_eq!(Utf16Decoder::decode(&[0xDD80, b'a' as u16]), "\u{FFFD}a");
+    assert_eq!(Utf16Decoder::decode(&[0xD83E, b'a' as u16]), "\u{FFFD}a");
+    assert_eq!(
+        Utf16Decoder::decode(&[0xD83E, 0xD83E, 0xDD80]),
+        "\u{FFFD}🦀"
+    );
+}
+
+#[test]
+fn text_has_no_cap() {
+    let mut window = Window::new_headless(WindowAttributes::new()).unwrap();
+    let text = "ÿ日本語🦀".repeat(200);
+    window
+        .headless()
+        .unwrap()
+        .queue(ScriptedInput::Text(text.clone()));
+    window.pull();
+    assert_eq!(window.text, text);
+
+    window.pull();
+    assert!(window.text.is_empty());
+}
@@ -0,0 +1,128 @@
+//! The frame clock, driven by hand without a window.
+use pica::pica_time::{
+    performance_counter, performance_frequency, sleep_until, FrameStats, Time, FRAME_STATS_FRAMES,
+};
+
+const NANOSECONDS_PER_SECOND: i64 = 1000 * 1000 * 1000;
+
+#[test]
+fn update_to_splits_ticks() {
+    // A 10 MHz counter like QueryPerformanceCounter's, started at an arbitrary value.
+    let mut time = Time::with_ticks(5_000, 10_000_000);
+    time.update_to(5_000 + 25_000);
+    assert_eq!(time.ticks, 25_000);
+    assert_eq!(time.delta_ticks, 25_000);
+    assert_eq!(time.nanoseconds, 2_500_000);
+    assert_eq!(time.microseconds, 2_500);
+    assert_eq!(time.milliseconds, 2);
+    assert!((time.seconds - 0.0025).abs() < 1e-12);
+
+    time.update_to(5_000 + 35_000);
+    assert_eq!(time.delta_ticks, 10_000);
+    assert_eq!(time.delta_milliseconds, 1);
+    assert!((time.delta_seconds - 0.001).abs() < 1e-12);
+}
+
+#[test]
+fn long_runs_keep_precision() {
+    // A week of 60 Hz frames on a nanosecond clock.
+    let week = 7 * 24 * 60 * 60 * NANOSECONDS_PER_SECOND;
+    let frame = NANOSECONDS_PER_SECOND / 60;
+    let mut time = Time::with_ticks(0, NANOSECONDS_PER_SECOND);
+    time.update_to(week);
+    time.update_to(week + frame);
+
+    assert_eq!(time.nanoseconds, week + frame);
+    assert_eq!(time.delta_nanoseconds, frame);
+    assert!((time.delta_seconds - 1.0 / 60.0).abs() < 1e-9);
+    assert!((time.seconds - (week + frame) as f64 / 1e9).abs() < 1e-6);
+}
+
+#[test]
+fn update_follows_the_monotonic_clock() {
+    assert!(performance_frequency() > 0);
+    let before = performance_counter();
+    let mut time = Time::new();
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    time.update();
+    assert!(time.milliseconds >= 10);
+    assert!(time.ticks <= performance_counter() - before);
+    assert_eq!(time.delta_ticks, time.ticks);
+}
+
+#[test]
+fn simulation_time_pauses_steps_and_scales() {
+    let frame = NANOSECONDS_PER_SECOND / 100;
+    let mut time = Time::with_ticks(0, NANOSECONDS_PER_SECOND);
+    let mut ticks = 0;
+    let mut next_frame = |time: &mut Time| {
+        ticks += frame;
+        time.update_to(ticks);
+    };
+
+    next_frame(&mut time);
+    assert_eq!(time.simulation.delta_nanoseconds, frame);
+
+    // Paused, the wall clock goes on and the simulation only moves when stepped.
+    time.simulation.paused = true;
+    next_frame(&mut time);
+    assert_eq!(time.simulation.delta_nanoseconds, 0);
+    time.simulation.step(1000);
+    next_frame(&mut time);
+    assert_eq!(time.simulation.delta_nanoseconds, 1000);
+    next_frame(&mut time);
+    assert_eq!(time.simulation.delta_nanoseconds, 0);
+    assert_eq!(time.simulation.nanoseconds, frame + 1000);
+    assert_eq!(time.nanoseconds, 4 * frame);
+
+    // Slow motion carries the fractions of a nanosecond to the next frame.
+    time.simulation.paused = false;
+    time.simulation.scale = 1.0 / 3.0;
+    for _ in 0..3 {
+        next_frame(&mut time);
+    }
+    assert!((time.simulation.nanoseconds - (2 * frame + 1000)).abs() <= 1);
+
+    time.simulation.scale = 2.0;
+    next_frame(&mut time);
+    assert!((time.simulation.delta_nanoseconds - 2 * frame).abs() <= 1);
+    assert!((time.simulation.delta_seconds - 0.02).abs() < 1e-9);
+}
+
+#[test]
+fn frame_stats_over_a_rolling_window() {
+    let mut stats = FrameStats::default();
+    assert_eq!(stats.average(), None);
+    assert_eq!(stats.percentile(50.0), None);
+
+    // One slow frame in a hundred
+    for frame in 1..=100 {
+        stats.push(if frame == 50 { 50_000_000 } else { 10_000_000 });
+    }
+    assert_eq!(stats.min(), Some(10_000_000));
+    assert_eq!(stats.max(), Some(50_000_000));
+    assert_eq!(stats.average(), Some(10_400_000));
+    assert_eq!(stats.percentile(50.0), Some(10_000_000));
+    assert_eq!(stats.percentile(99.0), Some(10_000_000));
+    assert_eq!(stats.percentile(100.0), Some(50_000_000));
+
+    // The slow frame drops out of the window
+    for _ in 0..FRAME_STATS_FRAMES {
+        stats.push(20_000_000);
+    }
+    assert_eq!(stats.len(), FRAME_STATS_FRAMES);
+    assert_eq!(stats.max(), Some(20_000_000));
+    assert_eq!(stats.frames_per_second(), Some(50.0));
+}
+
+#[test]
+fn sleep_until_reaches_the_deadline() {
+    let frequency = performance_frequency();
+    let start = performance_counter();
+    let deadline = start + frequency / 100;
+    sleep_until(deadline);
+    let end = performance_counter();
+    assert!(end >= deadline);
+    // Generous, a loaded test machine may wake us late.
+    assert!(end - deadline < frequency / 10);
+}
@@ -0,0 +1,69 @@
+//! Several native Win32 windows on one thread, each pulling its own messages.
+#![cfg(windows)]
+use pica::pica_window::event::Event;
+use pica::pica_window::{Window, WindowAttributes};
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{
+    IsWindow, PostMessageW, PostQuitMessage, WM_CLOSE, WM_USER,
+};
+
+fn window(title: &str, x: isize) -> Box<Window> {
+    Window::new_with_attributes(
+        WindowAttributes::new()
+            .with_title(title)
+            .with_position(x, 50)
+            .with_size(320, 240),
+    )
+    .unwrap()
+}
+
+fn window_handle(window: &Window) -> HWND {
+    match window.raw_window_handle() {
+        RawWindowHandle::Win32(handle) => HWND(handle.hwnd as isize),
+        handle => panic!("Not a Win32 window: {:?}", handle),
+    }
+}
+
+#[test]
+fn windows_pull_their_own_messages() {
+    let mut first = window("First", 50);
+    let mut second = window("Second", 400);
+    for _ in 0..3 {
+        assert!(first.pull());
+        assert!(second.pull());
+    }
+
+    // Closing the second window is left in the queue by the first one
+    unsafe { PostMessageW(window_handle(&second), WM_CLOSE, WPARAM(0), LPARAM(0)) };
+    assert!(first.pull());
+    assert!(!first.events.contains(&Event::CloseRequested));
+    assert!(!second.pull());
+    assert!(second.events.contains(&Event::CloseRequested));
+
+    // Thread messages are drained by whichever window pulls
+    unsafe { PostQuitMessage(0) };
+    assert!(!first.pull());
+}
+
+#[test]
+fn windows_that_are_not_pulled_do_not_hold_up_the_others() {
+    let mut first = window("First", 50);
+    let second = window("Second", 400);
+    assert!(first.pull());
+
+    // Stays in the queue ahead of the quit, the second window is not pulled anymore
+    unsafe { PostMessageW(window_handle(&second), WM_USER, WPARAM(0), LPARAM(0)) };
+    unsafe { PostQuitMessage(0) };
+    assert!(!first.pull());
+}
+
+#[test]
+fn dropped_windows_are_destroyed() {
+    let mut first = window("First", 50);
+    let second = window("Second", 400);
+    let second_handle = window_handle(&second);
+    drop(second);
+    assert!(!unsafe { IsWindow(second_handle) }.as_bool());
+    assert!(first.pull());
+}
@@ -0,0 +1,151 @@
+//! Window management of the PiCa window, driven through the headless backend.
+use pica::pica_mouse::CursorIcon;
+use pica::pica_window::event::Event;
+use pica::pica_window::headless::{ScriptedInput, MONITOR_SIZE};
+use pica::pica_window::{Icon, Window, WindowAttributes, WindowMode};
+use std::path::PathBuf;
+
+fn headless_window(window_attributes: WindowAttributes) -> Box<Window> {
+    Window::new_headless(window_attributes.with_position(100, 50).with_size(800, 600)).unwrap()
+}
+
+#[test]
+fn fullscreen_restores_the_windowed_geometry() {
+    let mut window = headless_window(WindowAttributes::new());
+
+    window.set_mode(WindowMode::BorderlessFullscreen);
+    window.pull();
+    assert_eq!(window.mode(), WindowMode::BorderlessFullscreen);
+    assert_eq!(window.window_attributes.size, MONITOR_SIZE);
+    assert_eq!(window.window_attributes.position, (0, 0));
+    assert_eq!(
+        window.events,
+        [Event::Resized(MONITOR_SIZE.0, MONITOR_SIZE.1)]
+    );
+
+    // Switching between fullscreen modes keeps the windowed geometry from before.
+    window.set_mode(WindowMode::Fullscreen);
+    window.pull();
+    assert!(window.events.is_empty());
+    window.set_mode(WindowMode::Windowed);
+    window.pull();
+    assert_eq!(window.window_attributes.size, (800, 600));
+    assert_eq!(window.window_attributes.position, (100, 50));
+    assert_eq!(window.events, [Event::Resized(800, 600)]);
+}
+
+#[test]
+fn opens_fullscreen() {
+    let window = headless_window(WindowAttributes::new().with_mode(WindowMode::Fullscreen));
+    assert_eq!(window.window_attributes.size, MONITOR_SIZE);
+}
+
+#[test]
+fn resizing_follows_resizable_and_size_limits() {
+    let mut window = headless_window(
+        WindowAttributes::new()
+            .with_min_size(320, 240)
+            .with_max_size(1024, 768),
+    );
+    window.headless().unwrap().queue_all([
+        ScriptedInput::Resize(100, 100),
+        ScriptedInput::NextFrame,
+        ScriptedInput::Resize(2000, 700),
+    ]);
+    window.pull();
+    assert_eq!(window.window_attributes.size, (320, 240));
+    window.pull();
+    assert_eq!(window.window_attributes.size, (1024, 700));
+
+    window.set_resizable(false);
+    window
+        .headless()
+        .unwrap()
+        .queue(ScriptedInput::Resize(640, 480));
+    window.pull();
+    assert_eq!(window.window_attributes.size, (1024, 700));
+    assert!(window.events.is_empty());
+}
+
+#[test]
+fn attributes_change_at_runtime() {
+    let mut window = headless_window(WindowAttributes::new());
+    window.set_title("Renamed");
+    assert_eq!(window.window_attributes.title, "Renamed");
+
+    assert_eq!(window.cursor_icon(), CursorIcon::Crosshair);
+    window.set_cursor_icon(CursorIcon::Hand);
+    window.set_cursor_visible(false);
+    assert_eq!(window.cursor_icon(), CursorIcon::Hand);
+    assert!(!window.cursor_visible());
+
+    let icon = Icon::from_rgba(vec![255; 4 * 16 * 16], 16, 16).unwrap();
+    window.set_icon(Some(icon.clone()));
+    assert_eq!(window.window_attributes.icon, Some(icon));
+    assert!(Icon::from_rgba(vec![255; 3], 1, 1).is_err());
+}
+
+#[test]
+fn scale_factor_keeps_the_logical_size() {
+    let mut window = headless_window(WindowAttributes::new().with_min_size(400, 300));
+    assert_eq!(window.scale_factor(), 1.0);
+
+    window
+        .headless()
+        .unwrap()
+        .queue(ScriptedInput::ScaleFactor(1.5));
+    window.pull();
+    assert_eq!(
+        window.events,
+        [Event::ScaleFactorChanged(1.5), Event::Resized(1200, 900)]
+    );
+    assert_eq!(window.window_attributes.size, (1200, 900));
+    assert_eq!(window.logical_size(), (800.0, 600.0));
+
+    // Size limits are logical, 400x300 is 600x450 physical pixels now.
+    window
+        .headless()
+        .unwrap()
+        .queue(ScriptedInput::Resize(100, 100));
+    window.pull();
+    assert_eq!(window.window_attributes.size, (600, 450));
+}
+
+#[test]
+fn clipboard_round_trip() {
+    let mut window = headless_window(WindowAttributes::new());
+    assert_eq!(window.clipboard_text(), None);
+    window
+        .set_clipboard_text("gravity = 9.81\nsteps = 120")
+        .unwrap();
+    assert_eq!(
+        window.clipboard_text().as_deref(),
+        Some("gravity = 9.81\nsteps = 120")
+    );
+}
+
+#[test]
+fn dropped_files_last_one_frame() {
+    let mut window = headless_window(WindowAttributes::new());
+    let paths = vec![
+        PathBuf::from("/meshes/cube.obj"),
+        PathBuf::from("scene.toml"),
+    ];
+    window.headless().unwrap().queue_all([
+        ScriptedInput::DropFiles(paths.clone(), (120, 80)),
+        ScriptedInput::NextFrame,
+    ]);
+
+    window.pull();
+    assert_eq!(window.dropped_files, paths);
+    assert_eq!(
+        window.events,
+        [Event::FilesDropped {
+            paths,
+            position: (120, 80)
+        }]
+    );
+    window.pull();
+    assert!(window.dropped_files.is_empty());
+    assert!(window.events.is_empty());
+}